@@ -1,238 +1,412 @@
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-fn read_file(path: &str) -> Option<String> {
-    let mut input = match File::open(path) {
-        Ok(input) => input,
+/// Data points stored as (every point's inputs, every point's expected outputs), one point after another.
+pub type FlatPoints<'a> = (&'a [f64], &'a [f64]);
+/// The contents of a .dset file: (input size, output size, [training points, testing points, validation points]), with the points stored as in [`FlatPoints`].
+pub type DSetData = (u32, u32, [(Vec<f64>, Vec<f64>); 3]);
+/// The contents of a .neur file: (shape, weights, biases, scalers, layers). The scalers are the input then the output scaler's [center, scale], empty when there is no scaler. Networks of only dense layers without dropout store them in the weights and biases and have no layer records; any other network has a record for every layer, with its dropout as a `standard_dropout` or `inverted_dropout` setting, and empty weights and biases.
+pub type NeurData = (
+    Vec<usize>,
+    Vec<Vec<Vec<f64>>>,
+    Vec<Vec<f64>>,
+    Vec<Vec<Vec<f64>>>,
+    Vec<NeurLayer>,
+);
+/// A layer record in a .neur file: (kind, [(setting, value)], [(tensor, shape, values)]).
+pub type NeurLayer = (
+    String,
+    Vec<(String, f64)>,
+    Vec<(String, Vec<usize>, Vec<f64>)>,
+);
+/// The contents of a graph file: (nodes, output nodes). Each node is a layer record, whose kind is `input`, `add`, `multiply`, `concatenate` or a layer's kind, and the nodes it takes its inputs from.
+pub type GraphData = (Vec<(NeurLayer, Vec<usize>)>, Vec<usize>);
+/// The contents of a genome file: ([inputs, outputs], [(id, kind, bias)], [(innovation, from, to, weight, enabled)]). A node's kind is `input`, `hidden` or `output`.
+pub type GenomeData = (
+    [usize; 2],
+    Vec<(usize, String, f64)>,
+    Vec<(usize, usize, usize, f64, bool)>,
+);
+
+pub fn open_file(path: &str) -> Option<File> {
+    match File::open(path) {
+        Ok(file) => Some(file),
         Err(_) => {
             println!("Invalid File Path");
-            return None;
+            None
         }
-    };
-    let mut buf = String::new();
-    if let Err(_) = input.read_to_string(&mut buf) {
-        println!("Could not read file");
-        return None;
     }
-    Some(buf)
 }
 
-fn read_file_bytes(path: &str) -> Option<Vec<u8>> {
-    let mut input = match File::open(path) {
-        Ok(input) => input,
-        Err(_) => {
-            println!("Invalid File Path");
-            return None;
-        }
-    };
-    let mut buf = vec![];
-    if let Err(_) = input.read_to_end(&mut buf) {
-        println!("Could not read file");
-        return None;
+/// How a save should behave when the destination path already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveMode {
+    /// Fail (return None) if the path already exists.
+    CreateNew,
+    /// Replace whatever is at the path.
+    Overwrite,
+    /// If the path already exists, keep adding numbers to the file name (`network.neur`, `network0.neur`, `network1.neur`, ...) until a free one is found.
+    Numbered,
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+fn numbered_path(path: &Path, i: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}{}.{}", stem, i, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{}{}", stem, i)),
     }
-    
-    Some(buf)
 }
 
-fn write_file(path: &str, contents: &str) -> Option<()> {
-    let mut file = match OpenOptions::new().write(true).open(path) {
-        Ok(file) => file,
-        Err(_) => match File::create(&path) {
-            Ok(file) => file,
-            Err(_) => return None,
-        },
+fn sync_parent_dir(path: &Path) {
+    // Makes the rename itself durable. Not every platform allows opening a directory, so this is best-effort.
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
     };
-    let data: Box<[u8]> = contents.as_bytes().iter().map(|x| *x).collect();
-    match file.write_all(&data) {
-        Ok(_) => Some(()),
-        Err(_) => None,
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
     }
 }
 
-fn write_file_bytes(path: &str, contents: Vec<u8>) -> Option<()> {
-    let mut file = match OpenOptions::new().write(true).open(path) {
-        Ok(file) => file,
-        Err(_) => match File::create(&path) {
-            Ok(file) => file,
-            Err(_) => return None,
-        },
-    };
-    let data: Box<[u8]> = contents.iter().map(|x| *x).collect();
-    match file.write_all(&data) {
-        Ok(_) => Some(()),
-        Err(_) => None,
+fn link_new(tmp: &Path, path: &Path) -> std::io::Result<()> {
+    // hard_link fails if the destination exists, so the check and the creation are one step.
+    match fs::hard_link(tmp, path) {
+        Err(err) if err.kind() == ErrorKind::Unsupported => {
+            // Without hard links, creating an empty file claims the name, and the rename then replaces it with the finished one.
+            OpenOptions::new().write(true).create_new(true).open(path)?;
+            fs::rename(tmp, path).inspect_err(|_| {
+                let _ = fs::remove_file(path);
+            })
+        }
+        result => result,
     }
 }
 
-pub fn write_dset_file(
+fn place_file(tmp: &Path, path: &Path, mode: SaveMode) -> Option<PathBuf> {
+    match mode {
+        SaveMode::Overwrite => {
+            fs::rename(tmp, path).ok()?;
+            Some(path.to_path_buf())
+        }
+        SaveMode::CreateNew => {
+            link_new(tmp, path).ok()?;
+            Some(path.to_path_buf())
+        }
+        SaveMode::Numbered => {
+            let mut candidate = path.to_path_buf();
+            let mut i: usize = 0;
+            loop {
+                match link_new(tmp, &candidate) {
+                    Ok(_) => return Some(candidate),
+                    Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                        candidate = numbered_path(path, i);
+                        i += 1;
+                    }
+                    Err(_) => return None,
+                }
+            }
+        }
+    }
+}
+
+/// Writes a file atomically: the contents go to a temporary file in the same directory, which is synced to disk and then moved into place according to the save mode. Returns the path that was written to.
+pub fn write_atomic<F: FnOnce(&mut BufWriter<File>) -> Option<()>>(
     path: &str,
-    training_count: u32,
-    testing_count: u32,
+    mode: SaveMode,
+    write: F,
+) -> Option<String> {
+    let path = Path::new(path);
+    let tmp = temp_path(path);
+    let result = (|| {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)
+            .ok()?;
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        let file = writer.into_inner().ok()?;
+        file.sync_all().ok()?;
+        place_file(&tmp, path, mode)
+    })();
+    if result.is_none() || mode != SaveMode::Overwrite {
+        let _ = fs::remove_file(&tmp);
+    }
+    let written = result?;
+    sync_parent_dir(&written);
+    Some(written.to_string_lossy().into_owned())
+}
+
+pub fn write_dset<W: Write>(
+    mut writer: W,
     input_size: u32,
     output_size: u32,
-    training_points: Vec<(Vec<f64>, Vec<f64>)>,
-    testing_points: Vec<(Vec<f64>, Vec<f64>)>,
+    training_points: FlatPoints<'_>,
+    testing_points: FlatPoints<'_>,
+    validation_points: FlatPoints<'_>,
 ) -> Option<()> {
+    let point_count = |points: FlatPoints| match input_size + output_size {
+        0 => 0,
+        _ => (points.0.len() + points.1.len()) / (input_size + output_size) as usize,
+    };
     let mut file_buf: Vec<u8> = vec![];
-    file_buf.extend_from_slice(&training_count.to_be_bytes());
-    file_buf.extend_from_slice(&testing_count.to_be_bytes());
+    file_buf.extend_from_slice(&(point_count(training_points) as u32).to_be_bytes());
+    file_buf.extend_from_slice(&(point_count(testing_points) as u32).to_be_bytes());
     file_buf.extend_from_slice(&input_size.to_be_bytes());
     file_buf.extend_from_slice(&output_size.to_be_bytes());
 
-    for data_point in training_points {
-        for input in data_point.0 {
-            file_buf.extend_from_slice(&input.to_be_bytes());
+    let validation_count = point_count(validation_points);
+    let (input_size, output_size) = (input_size as usize, output_size as usize);
+    for (split, points) in [training_points, testing_points, validation_points]
+        .into_iter()
+        .enumerate()
+    {
+        if split == 2 && validation_count > 0 {
+            file_buf.extend_from_slice(&(validation_count as u32).to_be_bytes());
         }
-        for output in data_point.1 {
-            file_buf.extend_from_slice(&output.to_be_bytes());
-        }
-    }
-    for data_point in testing_points {
-        for input in data_point.0 {
-            file_buf.extend_from_slice(&input.to_be_bytes());
-        }
-        for output in data_point.1 {
-            file_buf.extend_from_slice(&output.to_be_bytes());
+        for i in 0..point_count(points) {
+            let input = &points.0[i * input_size..(i + 1) * input_size];
+            let output = &points.1[i * output_size..(i + 1) * output_size];
+            for value in input.iter().chain(output.iter()) {
+                file_buf.extend_from_slice(&value.to_be_bytes());
+            }
         }
     }
 
-    write_file_bytes(path, file_buf)?;
-    Some(())
+    writer.write_all(&file_buf).ok()?;
+    writer.flush().ok()
 }
 
-pub fn read_dset_file(path: &str) -> Option<(Vec<(Vec<f64>, Vec<f64>)>, Vec<(Vec<f64>, Vec<f64>)>)> {
-    fn grab_u32(data: &mut Vec<u8>) -> u32 {
-        let mut buf: [u8; 4] = [0; 4];
-        for i in 0..4 {
-            buf[i] = data.remove(0);
-        }
-        u32::from_be_bytes(buf)
-    }
-    fn grab_f64(data: &mut Vec<u8>) -> f64 {
-        let mut buf: [u8; 8] = [0; 8];
-        for i in 0..8 {
-            buf[i] = data.remove(0);
-        }
-        f64::from_be_bytes(buf)
-    }
-    
-    let mut data = read_file_bytes(path)?;
+pub fn write_dset_file(
+    path: &str,
+    mode: SaveMode,
+    input_size: u32,
+    output_size: u32,
+    training_points: FlatPoints<'_>,
+    testing_points: FlatPoints<'_>,
+    validation_points: FlatPoints<'_>,
+) -> Option<String> {
+    write_atomic(path, mode, |file| {
+        write_dset(
+            file,
+            input_size,
+            output_size,
+            training_points,
+            testing_points,
+            validation_points,
+        )
+    })
+}
 
-    let training_count = grab_u32(&mut data);
-    let testing_count = grab_u32(&mut data);
-    let input_size = grab_u32(&mut data);
-    let output_size = grab_u32(&mut data);
+/// The length in bytes of a .dset file's header: the training count, testing count, input size and output size, each a big endian u32.
+pub const DSET_HEADER_LEN: u64 = 16;
+/// The length in bytes of the optional validation count (a big endian u32) following a .dset file's testing points. It and the validation points after it are only written when there are validation points, so older readers still see a plain training/testing file.
+pub const DSET_VALIDATION_COUNT_LEN: u64 = 4;
 
-    let mut buf = (vec![], vec![]);
+pub fn read_dset_header<R: Read>(reader: &mut R) -> Option<(u32, u32, u32, u32)> {
+    let mut buf: [u8; 16] = [0; 16];
+    reader.read_exact(&mut buf).ok()?;
+    let grab_u32 = |i: usize| u32::from_be_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+    Some((grab_u32(0), grab_u32(4), grab_u32(8), grab_u32(12)))
+}
 
-    for training_pnt in 0..training_count {
-        buf.0.push((vec![], vec![]));
-        for input in 0..input_size {
-            buf.0[training_pnt as usize].0.push(grab_f64(&mut data));
-        }
-        for output in 0..output_size {
-            buf.0[training_pnt as usize].1.push(grab_f64(&mut data));
+pub fn read_dset<R: Read>(mut reader: R) -> Option<DSetData> {
+    fn grab_points<R: Read>(
+        reader: &mut R,
+        count: u32,
+        input_size: u32,
+        output_size: u32,
+    ) -> Option<(Vec<f64>, Vec<f64>)> {
+        let mut buf = (vec![], vec![]);
+        let mut value: [u8; 8] = [0; 8];
+        for _ in 0..count {
+            for _ in 0..input_size {
+                reader.read_exact(&mut value).ok()?;
+                buf.0.push(f64::from_be_bytes(value));
+            }
+            for _ in 0..output_size {
+                reader.read_exact(&mut value).ok()?;
+                buf.1.push(f64::from_be_bytes(value));
+            }
         }
+        Some(buf)
     }
-    for testing_pnt in 0..testing_count {
-        buf.1.push((vec![], vec![]));
-        for input in 0..input_size {
-            buf.1[testing_pnt as usize].0.push(grab_f64(&mut data));
-        }
-        for output in 0..output_size {
-            buf.1[testing_pnt as usize].1.push(grab_f64(&mut data));
+
+    let (training_count, testing_count, input_size, output_size) = read_dset_header(&mut reader)?;
+
+    let training = grab_points(&mut reader, training_count, input_size, output_size)?;
+    let testing = grab_points(&mut reader, testing_count, input_size, output_size)?;
+    let mut validation_count: [u8; 4] = [0; 4];
+    let validation = match reader.read_exact(&mut validation_count) {
+        Ok(_) => {
+            let count = u32::from_be_bytes(validation_count);
+            grab_points(&mut reader, count, input_size, output_size)?
         }
-    }
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => (vec![], vec![]),
+        Err(_) => return None,
+    };
 
-    Some(buf)
+    Some((input_size, output_size, [training, testing, validation]))
 }
 
-pub fn parse_neur_file(path: &str) -> Option<(Vec<usize>, Vec<Vec<Vec<f64>>>, Vec<Vec<f64>>)> {
-    let data: String = read_file(path)?;
-    fn rm_whitespace(x: String) -> String {
-        let chars = x.chars();
-        let mut buf = String::new();
-        for char in chars {
-            if char != ' ' && char != '\n' {
-                buf.push(char);
-            }
+pub fn read_dset_file(path: &str) -> Option<DSetData> {
+    read_dset(BufReader::new(open_file(path)?))
+}
+
+fn rm_whitespace(x: String) -> String {
+    let chars = x.chars();
+    let mut buf = String::new();
+    for char in chars {
+        if !char.is_whitespace() {
+            buf.push(char);
         }
-        buf
     }
-    fn trim_trailing_comma(x: String) -> String {
-        let mut char_vec: Vec<char> = x.chars().collect();
-        if char_vec.last().unwrap() == &',' {
-            char_vec.remove(char_vec.len() - 1);
-        }
-        char_vec.iter().collect()
-    }
-    fn vectorize_lists(x: String) -> Vec<String> {
-        let chars = x.chars();
-        let mut buf: Vec<String> = vec![];
-        let mut char_buf: Vec<char> = vec![];
-        let mut depth: i32 = 0;
-        for char in chars {
-            if char == ']' {
-                depth -= 1;
-                if depth == 0 {
-                    buf.push(char_buf.iter().collect::<String>());
-                } else {
-                    char_buf.push(char);
-                }
-            } else if char == '[' {
-                depth += 1;
-                if depth == 1 {
-                    char_buf = vec![];
-                } else {
-                    char_buf.push(char);
-                }
+    buf
+}
+fn trim_trailing_comma(x: String) -> String {
+    let mut char_vec: Vec<char> = x.chars().collect();
+    if char_vec.last() == Some(&',') {
+        char_vec.remove(char_vec.len() - 1);
+    }
+    char_vec.iter().collect()
+}
+fn vectorize_lists(x: String) -> Vec<String> {
+    let chars = x.chars();
+    let mut buf: Vec<String> = vec![];
+    let mut char_buf: Vec<char> = vec![];
+    let mut depth: i32 = 0;
+    for char in chars {
+        if char == ']' {
+            depth -= 1;
+            if depth == 0 {
+                buf.push(char_buf.iter().collect::<String>());
             } else {
                 char_buf.push(char);
             }
-        }
-        buf
-    }
-    fn vectorize_numlist(x: String) -> Vec<String> {
-        let chars = x.chars();
-        let mut char_buf: Vec<char> = vec![];
-        let mut buf: Vec<String> = vec![];
-        for char in chars {
-            if char == ',' {
-                buf.push(char_buf.iter().collect::<String>());
+        } else if char == '[' {
+            depth += 1;
+            if depth == 1 {
                 char_buf = vec![];
             } else {
                 char_buf.push(char);
             }
+        } else {
+            char_buf.push(char);
         }
-        if !char_buf.is_empty() {
+    }
+    buf
+}
+fn vectorize_numlist(x: String) -> Vec<String> {
+    let chars = x.chars();
+    let mut char_buf: Vec<char> = vec![];
+    let mut buf: Vec<String> = vec![];
+    for char in chars {
+        if char == ',' {
             buf.push(char_buf.iter().collect::<String>());
+            char_buf = vec![];
+        } else {
+            char_buf.push(char);
         }
-        buf
     }
-    fn convert_f64_list(x: Vec<String>) -> Vec<f64> {
-        let mut buf: Vec<f64> = vec![];
-        for string in x.iter() {
-            buf.push(
-                string
-                    .parse::<f64>()
-                    .expect("Failed to parse string into f64"),
-            );
-        }
-        buf
+    if !char_buf.is_empty() {
+        buf.push(char_buf.iter().collect::<String>());
     }
-    fn convert_usize_list(x: Vec<String>) -> Vec<usize> {
-        let mut buf: Vec<usize> = vec![];
-        for string in x.iter() {
-            buf.push(
-                string
-                    .parse::<usize>()
-                    .expect("Failed to parse string into f64"),
-            );
+    buf
+}
+fn convert_f64_list(x: Vec<String>) -> Option<Vec<f64>> {
+    x.iter().map(|string| string.parse::<f64>().ok()).collect()
+}
+fn convert_usize_list(x: Vec<String>) -> Option<Vec<usize>> {
+    x.iter()
+        .map(|string| string.parse::<usize>().ok())
+        .collect()
+}
+fn record_name(x: &str) -> String {
+    //The text before a record's or tensor's first list is its name
+    trim_trailing_comma(x.split('[').next().unwrap_or("").to_string())
+}
+fn parse_layer_record(record: &str, parts: &[String]) -> Option<NeurLayer> {
+    //Parses a layer record whose first two lists are its settings and tensors
+    let settings_strings = vectorize_numlist(parts[0].clone());
+    if !settings_strings.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut settings: Vec<(String, f64)> = vec![];
+    for setting in settings_strings.chunks(2) {
+        settings.push((setting[0].clone(), setting[1].parse::<f64>().ok()?));
+    }
+    let mut tensors: Vec<(String, Vec<usize>, Vec<f64>)> = vec![];
+    for tensor_string in vectorize_lists(parts[1].clone()) {
+        let tensor_parts = vectorize_lists(tensor_string.clone());
+        if tensor_parts.len() != 2 {
+            return None;
         }
-        buf
+        tensors.push((
+            record_name(&tensor_string),
+            convert_usize_list(vectorize_numlist(tensor_parts[0].clone()))?,
+            convert_f64_list(vectorize_numlist(tensor_parts[1].clone()))?,
+        ));
+    }
+    Some((record_name(record), settings, tensors))
+}
+fn format_layer_record((kind, settings, tensors): &NeurLayer) -> String {
+    //A layer record without its surrounding brackets: kind,[settings],[tensors]
+    let join = |values: Vec<String>| values.join(",");
+    format!(
+        "{},[{}],[{}]",
+        kind,
+        join(
+            settings
+                .iter()
+                .map(|(name, value)| format!("{},{}", name, value))
+                .collect()
+        ),
+        join(
+            tensors
+                .iter()
+                .map(|(name, shape, values)| {
+                    format!(
+                        "[{},[{}],[{}]]",
+                        name,
+                        join(shape.iter().map(|dim| dim.to_string()).collect()),
+                        join(values.iter().map(|value| value.to_string()).collect())
+                    )
+                })
+                .collect()
+        )
+    )
+}
+
+pub fn parse_neur<R: Read>(mut reader: R) -> Option<NeurData> {
+    let mut data = String::new();
+    if reader.read_to_string(&mut data).is_err() {
+        println!("Could not read file");
+        return None;
     }
     let vectorized_input = vectorize_lists(trim_trailing_comma(rm_whitespace(data)));
-    let shape: Vec<usize> = convert_usize_list(vectorize_numlist(vectorized_input[0].clone()));
+    if vectorized_input.len() < 3 {
+        return None;
+    }
+    let shape: Vec<usize> = convert_usize_list(vectorize_numlist(vectorized_input[0].clone()))?;
     let weights: Vec<Vec<Vec<f64>>> = {
         let layer_strings = vectorize_lists(vectorized_input[1].clone());
         let mut network_buf: Vec<Vec<Vec<f64>>> = vec![];
@@ -241,7 +415,7 @@ pub fn parse_neur_file(path: &str) -> Option<(Vec<usize>, Vec<Vec<Vec<f64>>>, Ve
             let neuron_strings = vectorize_lists(layer_string);
             for neuron_string in neuron_strings {
                 let connection_strings = vectorize_numlist(neuron_string);
-                layer_buf.push(convert_f64_list(connection_strings));
+                layer_buf.push(convert_f64_list(connection_strings)?);
             }
             network_buf.push(layer_buf);
         }
@@ -252,42 +426,72 @@ pub fn parse_neur_file(path: &str) -> Option<(Vec<usize>, Vec<Vec<Vec<f64>>>, Ve
         let mut buf: Vec<Vec<f64>> = vec![];
         for layer_string in layer_strings {
             let neuron_strings = vectorize_numlist(layer_string);
-            buf.push(convert_f64_list(neuron_strings));
+            buf.push(convert_f64_list(neuron_strings)?);
         }
         buf
     };
-    Some((shape, weights, biases))
+    let scalers = match vectorized_input.get(3) {
+        //Files saved without scalers end after the biases
+        Some(scalers_string) => {
+            let mut buf: Vec<Vec<Vec<f64>>> = vec![];
+            for scaler_string in vectorize_lists(scalers_string.clone()) {
+                let mut scaler_buf: Vec<Vec<f64>> = vec![];
+                for values_string in vectorize_lists(scaler_string) {
+                    scaler_buf.push(convert_f64_list(vectorize_numlist(values_string))?);
+                }
+                buf.push(scaler_buf);
+            }
+            buf
+        }
+        None => vec![],
+    };
+    let layers = match vectorized_input.get(4) {
+        //Only networks with layers other than dense ones, or with dropout, have layer records
+        Some(layers_string) => {
+            let mut buf: Vec<NeurLayer> = vec![];
+            for layer_string in vectorize_lists(layers_string.clone()) {
+                let parts = vectorize_lists(layer_string.clone());
+                if parts.len() != 2 {
+                    return None;
+                }
+                buf.push(parse_layer_record(&layer_string, &parts)?);
+            }
+            buf
+        }
+        None => vec![],
+    };
+    Some((shape, weights, biases, scalers, layers))
 }
 
-pub fn write_neur_file(
-    path: &str,
-    data: (Vec<usize>, Vec<Vec<Vec<f64>>>, Vec<Vec<f64>>),
-) -> Option<()> {
+pub fn parse_neur_file(path: &str) -> Option<NeurData> {
+    parse_neur(BufReader::new(open_file(path)?))
+}
+
+pub fn write_neur<W: Write>(mut writer: W, data: NeurData) -> Option<()> {
     let shape = data.0;
     let weights = data.1;
     let biases = data.2;
 
     let mut buf = String::new();
     buf.push('[');
-    for i in 0..shape.len() {
-        let size = shape[i].to_string();
-        buf.push_str(&size);
+    for (i, size) in shape.iter().enumerate() {
+        buf.push_str(&size.to_string());
         if i < shape.len() - 1 {
             //If not the last item (to avoid trailing commas)
             buf.push(',');
         }
     }
-    buf.push_str(&"],\n[");
+    buf.push_str("],\n[");
 
-    for layer_i in 0..weights.len() {
+    for (layer_i, layer) in weights.iter().enumerate() {
         buf.push_str(&{
             let mut buf = String::from("[");
-            for neuron_i in 0..weights[layer_i].len() {
+            for (neuron_i, neuron) in layer.iter().enumerate() {
                 buf.push_str(&{
                     let mut buf = String::from("[");
-                    for connection_i in 0..weights[layer_i][neuron_i].len() {
-                        buf.push_str(&(weights[layer_i][neuron_i][connection_i].to_string()));
-                        if connection_i < weights[layer_i][neuron_i].len() - 1 {
+                    for (connection_i, connection) in neuron.iter().enumerate() {
+                        buf.push_str(&connection.to_string());
+                        if connection_i < neuron.len() - 1 {
                             buf.push(',');
                         } else {
                             buf.push(']');
@@ -295,7 +499,7 @@ pub fn write_neur_file(
                     }
                     buf
                 });
-                if neuron_i < weights[layer_i].len() - 1 {
+                if neuron_i < layer.len() - 1 {
                     buf.push(',');
                 } else {
                     buf.push(']');
@@ -309,15 +513,18 @@ pub fn write_neur_file(
             buf.push(']');
         }
     }
-    buf.push_str(&",\n[");
+    if weights.is_empty() {
+        buf.push(']');
+    }
+    buf.push_str(",\n[");
 
-    for layer_i in 0..biases.len() {
+    for (layer_i, layer) in biases.iter().enumerate() {
         buf.push('[');
         buf.push_str(&{
             let mut buf = String::new();
-            for neuron_i in 0..biases[layer_i].len() {
-                buf.push_str(&(biases[layer_i][neuron_i].to_string()));
-                if neuron_i < biases[layer_i].len() - 1 {
+            for (neuron_i, bias) in layer.iter().enumerate() {
+                buf.push_str(&bias.to_string());
+                if neuron_i < layer.len() - 1 {
                     buf.push(',');
                 } else {
                     buf.push(']');
@@ -331,7 +538,327 @@ pub fn write_neur_file(
             buf.push(']');
         }
     }
+    if biases.is_empty() {
+        buf.push(']');
+    }
+
+    let scalers = data.3;
+    let layers = data.4;
+    if !layers.is_empty() || scalers.iter().any(|scaler| !scaler.is_empty()) {
+        buf.push_str(",\n[");
+        for (scaler_i, scaler) in scalers.iter().enumerate() {
+            buf.push('[');
+            for (values_i, values) in scaler.iter().enumerate() {
+                buf.push('[');
+                buf.push_str(
+                    &values
+                        .iter()
+                        .map(|value| value.to_string())
+                        .collect::<Vec<String>>()
+                        .join(","),
+                );
+                buf.push(']');
+                if values_i < scaler.len() - 1 {
+                    buf.push(',');
+                }
+            }
+            buf.push(']');
+            if scaler_i < scalers.len() - 1 {
+                buf.push(',');
+            }
+        }
+        buf.push(']');
+    }
+
+    if !layers.is_empty() {
+        buf.push_str(",\n[");
+        buf.push_str(
+            &layers
+                .iter()
+                .map(|layer| format!("[{}]", format_layer_record(layer)))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+        buf.push(']');
+    }
+
+    writer.write_all(buf.as_bytes()).ok()?;
+    writer.flush().ok()
+}
+
+pub fn write_neur_file(path: &str, mode: SaveMode, data: NeurData) -> Option<String> {
+    write_atomic(path, mode, |file| write_neur(file, data))
+}
+
+pub fn parse_graph<R: Read>(mut reader: R) -> Option<GraphData> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data).ok()?;
+    let vectorized_input = vectorize_lists(trim_trailing_comma(rm_whitespace(data)));
+    if vectorized_input.len() != 2 {
+        return None;
+    }
+    let mut nodes: Vec<(NeurLayer, Vec<usize>)> = vec![];
+    for node_string in vectorize_lists(vectorized_input[0].clone()) {
+        let parts = vectorize_lists(node_string.clone());
+        if parts.len() != 3 {
+            return None;
+        }
+        nodes.push((
+            parse_layer_record(&node_string, &parts)?,
+            convert_usize_list(vectorize_numlist(parts[2].clone()))?,
+        ));
+    }
+    let outputs = convert_usize_list(vectorize_numlist(vectorized_input[1].clone()))?;
+    Some((nodes, outputs))
+}
+
+pub fn parse_graph_file(path: &str) -> Option<GraphData> {
+    parse_graph(BufReader::new(open_file(path)?))
+}
+
+pub fn write_graph<W: Write>(mut writer: W, data: GraphData) -> Option<()> {
+    let join = |values: Vec<String>| values.join(",");
+    let (nodes, outputs) = data;
+    let buf = format!(
+        "[{}],\n[{}]",
+        join(
+            nodes
+                .iter()
+                .map(|(layer, inputs)| format!(
+                    "[{},[{}]]",
+                    format_layer_record(layer),
+                    join(inputs.iter().map(|input| input.to_string()).collect())
+                ))
+                .collect()
+        ),
+        join(outputs.iter().map(|output| output.to_string()).collect())
+    );
+    writer.write_all(buf.as_bytes()).ok()?;
+    writer.flush().ok()
+}
+
+pub fn write_graph_file(path: &str, mode: SaveMode, data: GraphData) -> Option<String> {
+    write_atomic(path, mode, |file| write_graph(file, data))
+}
+
+pub fn parse_genome<R: Read>(mut reader: R) -> Option<GenomeData> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data).ok()?;
+    let vectorized_input = vectorize_lists(trim_trailing_comma(rm_whitespace(data)));
+    if vectorized_input.len() != 3 {
+        return None;
+    }
+    let sizes = convert_usize_list(vectorize_numlist(vectorized_input[0].clone()))?;
+    if sizes.len() != 2 {
+        return None;
+    }
+    let mut nodes: Vec<(usize, String, f64)> = vec![];
+    for node_string in vectorize_lists(vectorized_input[1].clone()) {
+        let parts = vectorize_numlist(node_string);
+        if parts.len() != 3 {
+            return None;
+        }
+        nodes.push((
+            parts[0].parse().ok()?,
+            parts[1].clone(),
+            parts[2].parse().ok()?,
+        ));
+    }
+    let mut connections: Vec<(usize, usize, usize, f64, bool)> = vec![];
+    for connection_string in vectorize_lists(vectorized_input[2].clone()) {
+        let parts = vectorize_numlist(connection_string);
+        if parts.len() != 5 {
+            return None;
+        }
+        connections.push((
+            parts[0].parse().ok()?,
+            parts[1].parse().ok()?,
+            parts[2].parse().ok()?,
+            parts[3].parse().ok()?,
+            match parts[4].as_str() {
+                "0" => false,
+                "1" => true,
+                _ => return None,
+            },
+        ));
+    }
+    Some(([sizes[0], sizes[1]], nodes, connections))
+}
+
+pub fn parse_genome_file(path: &str) -> Option<GenomeData> {
+    parse_genome(BufReader::new(open_file(path)?))
+}
+
+pub fn write_genome<W: Write>(mut writer: W, data: GenomeData) -> Option<()> {
+    let join = |values: Vec<String>| values.join(",");
+    let ([inputs, outputs], nodes, connections) = data;
+    let buf = format!(
+        "[{},{}],\n[{}],\n[{}]",
+        inputs,
+        outputs,
+        join(
+            nodes
+                .iter()
+                .map(|(id, kind, bias)| format!("[{},{},{}]", id, kind, bias))
+                .collect()
+        ),
+        join(
+            connections
+                .iter()
+                .map(|(innovation, from, to, weight, enabled)| format!(
+                    "[{},{},{},{},{}]",
+                    innovation, from, to, weight, *enabled as u8
+                ))
+                .collect()
+        )
+    );
+    writer.write_all(buf.as_bytes()).ok()?;
+    writer.flush().ok()
+}
+
+pub fn write_genome_file(path: &str, mode: SaveMode, data: GenomeData) -> Option<String> {
+    write_atomic(path, mode, |file| write_genome(file, data))
+}
+
+pub fn read_csv_records<R: Read>(mut reader: R, delimiter: char) -> Option<Vec<Vec<String>>> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data).ok()?;
+
+    let mut records: Vec<Vec<String>> = vec![];
+    let mut record: Vec<String> = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = data.chars().peekable();
+    while let Some(char) = chars.next() {
+        if in_quotes {
+            if char == '"' {
+                if chars.peek() == Some(&'"') {
+                    //An escaped quote
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(char);
+            }
+        } else if char == '"' {
+            in_quotes = true;
+        } else if char == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if char == '\n' || char == '\r' {
+            if char == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            if !(record.len() == 1 && record[0].is_empty()) {
+                //Skip blank lines
+                records.push(std::mem::take(&mut record));
+            }
+            record.clear();
+        } else {
+            field.push(char);
+        }
+    }
+    if in_quotes {
+        return None;
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Some(records)
+}
+
+pub fn write_csv_records<W: Write>(
+    mut writer: W,
+    delimiter: char,
+    records: &[Vec<String>],
+) -> Option<()> {
+    let mut buf = String::new();
+    for record in records {
+        for (i, field) in record.iter().enumerate() {
+            if field.contains(delimiter) || field.contains('"') || field.contains('\n') {
+                buf.push('"');
+                buf.push_str(&field.replace('"', "\"\""));
+                buf.push('"');
+            } else {
+                buf.push_str(field);
+            }
+            if i < record.len() - 1 {
+                buf.push(delimiter);
+            }
+        }
+        buf.push('\n');
+    }
+    writer.write_all(buf.as_bytes()).ok()?;
+    writer.flush().ok()
+}
+
+pub fn read_csv_file(path: &str, delimiter: char) -> Option<Vec<Vec<String>>> {
+    read_csv_records(BufReader::new(open_file(path)?), delimiter)
+}
+
+pub fn write_csv_file(
+    path: &str,
+    mode: SaveMode,
+    delimiter: char,
+    records: &[Vec<String>],
+) -> Option<String> {
+    write_atomic(path, mode, |file| {
+        write_csv_records(file, delimiter, records)
+    })
+}
+
+/// The element type code of IDX unsigned byte data, the type MNIST stores its pixels and labels in.
+pub const IDX_UBYTE: u8 = 0x08;
+
+pub fn read_idx<R: Read>(mut reader: R) -> Option<(u8, Vec<usize>, Vec<f64>)> {
+    let mut magic: [u8; 4] = [0; 4];
+    reader.read_exact(&mut magic).ok()?;
+    if magic[0] != 0 || magic[1] != 0 {
+        return None;
+    }
+    let type_code = magic[2];
+    let element_size = match type_code {
+        0x08 | 0x09 => 1,
+        0x0B => 2,
+        0x0C | 0x0D => 4,
+        0x0E => 8,
+        _ => return None,
+    };
+    let mut dims: Vec<usize> = vec![];
+    for _ in 0..magic[3] {
+        let mut buf: [u8; 4] = [0; 4];
+        reader.read_exact(&mut buf).ok()?;
+        dims.push(u32::from_be_bytes(buf) as usize);
+    }
+    let count = dims
+        .iter()
+        .try_fold(1usize, |acc, dim| acc.checked_mul(*dim))?;
+
+    let mut bytes: Vec<u8> = vec![];
+    reader.read_to_end(&mut bytes).ok()?;
+    if bytes.len() < count.checked_mul(element_size)? {
+        return None;
+    }
+    let values = bytes
+        .chunks_exact(element_size)
+        .take(count)
+        .map(|chunk| match type_code {
+            0x08 => chunk[0] as f64,
+            0x09 => chunk[0] as i8 as f64,
+            0x0B => i16::from_be_bytes([chunk[0], chunk[1]]) as f64,
+            0x0C => i32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64,
+            0x0D => f32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64,
+            _ => f64::from_be_bytes([
+                chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
+            ]),
+        })
+        .collect();
+    Some((type_code, dims, values))
+}
 
-    write_file(path, &buf)?;
-    Some(())
+pub fn read_idx_file(path: &str) -> Option<(u8, Vec<usize>, Vec<f64>)> {
+    read_idx(BufReader::new(open_file(path)?))
 }
@@ -1,7 +1,7 @@
 //! # NeurNet
-//! A crate used for creating neural networks and training them. Currently only supports trainging through gradient descent.
+//! A crate used for creating neural networks and training them, through gradient descent or, without gradients or labelled outputs, a genetic algorithm ([`Evolution`]) or NEAT ([`Neat`]), which also evolves the topology.
 //! # Example
-//! ```
+//! ```no_run
 //! use neurnet::*;
 //! let mut nn = Network::new(
 //!     vec![1, 5, 4, 5, 4, 6, 2],
@@ -20,11 +20,95 @@
 //! for i in -100..=100 {
 //!     println!("({}, {:?})", i, nn.pulse(vec![i as f64]));
 //! }
-//! let mut buf = String::new();
-//! nn.save_safe("test");
+//! nn.save("test.neur");
+//! ```
+
+//! # Features
+//! <ul>
+//! <li> <code>serde</code>: derives <code>Serialize</code>/<code>Deserialize</code> for [`Network`] and [`Graph`] (with each [`Layer`] stored as its [`LayerData`]), [`Activation`], [`DataSet`], [`CsvOptions`], [`EvolutionOptions`], [`Genome`] and [`NeatOptions`], so they can be stored in any format serde supports. Networks using a <code>Custom</code> (closure) activation fail to serialize, so build them with [`Network::with_activation`] instead.
+//! </ul>
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! use neurnet::*;
+//! let nn = Network::with_activation(vec![2, 3, 1], Activation::Tanh, (-1.0, 1.0), (-1.0, 1.0));
+//! let json = serde_json::to_string(&nn).unwrap();
+//! let loaded: Network = serde_json::from_str(&json).unwrap();
+//! assert_eq!(nn.pulse(vec![0.3, -0.2]), loaded.pulse(vec![0.3, -0.2]));
+//!
+//! let custom = Network::new(vec![1, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+//! assert!(serde_json::to_string(&custom).is_err());
+//!
+//! // Data that doesn't describe a valid network is rejected
+//! let broken = json.replace("\"shape\":[2,3,1]", "\"shape\":[2,4,1]");
+//! assert!(serde_json::from_str::<Network>(&broken).is_err());
+//! let broken = json.replace("\"dropout\":[null,null]", "\"dropout\":[null]");
+//! assert!(serde_json::from_str::<Network>(&broken).is_err());
+//!
+//! let ds = DataSet::new(vec![(vec![1.0, 2.0], vec![3.0])], vec![]);
+//! let json = serde_json::to_string(&ds).unwrap();
+//! assert_eq!(serde_json::from_str::<DataSet>(&json).unwrap(), ds);
+//! let broken = json.replace("[1.0,2.0]", "[1.0]");
+//! assert!(serde_json::from_str::<DataSet>(&broken).is_err());
+//!
+//! let mut graph = Graph::new(Activation::Tanh);
+//! let input = graph.input(2);
+//! graph.layer(Box::new(Dense::new(1, 2)), input);
+//! let json = serde_json::to_string(&graph).unwrap();
+//! assert!(serde_json::from_str::<Graph>(&json).is_ok());
+//! let broken = json.replace("\"Input\":2", "\"Input\":3");
+//! assert!(serde_json::from_str::<Graph>(&broken).is_err());
+//!
+//! let genome = Genome::new(2, 1);
+//! let json = serde_json::to_string(&genome).unwrap();
+//! assert_eq!(serde_json::from_str::<Genome>(&json).unwrap(), genome);
+//! let broken = json.replace("\"kind\":\"Output\"", "\"kind\":\"Hidden\"");
+//! assert!(serde_json::from_str::<Genome>(&broken).is_err());
+//! let broken = json.replace("\"to\":2", "\"to\":3");
+//! assert!(serde_json::from_str::<Genome>(&broken).is_err());
+//! // Two hidden nodes feeding each other
+//! let node = |id: usize, kind: &str| format!("{{\"id\":{},\"kind\":\"{}\",\"bias\":0.0}}", id, kind);
+//! let connection = |innovation: usize, from: usize, to: usize| {
+//!     format!("{{\"innovation\":{},\"from\":{},\"to\":{},\"weight\":1.0,\"enabled\":true}}", innovation, from, to)
+//! };
+//! let cyclic = format!(
+//!     "{{\"inputs\":1,\"outputs\":1,\"nodes\":[{},{},{},{}],\"connections\":[{},{},{}]}}",
+//!     node(0, "Input"), node(1, "Output"), node(2, "Hidden"), node(3, "Hidden"),
+//!     connection(0, 0, 2), connection(1, 2, 3), connection(2, 3, 2),
+//! );
+//! assert!(serde_json::from_str::<Genome>(&cyclic).is_err());
+//! assert!(serde_json::from_str::<Genome>(&cyclic.replace("\"from\":3,\"to\":2", "\"from\":3,\"to\":1")).is_ok());
+//! # }
 //! ```
 
 pub mod network;
-pub use network::training::data::{DataSet, NetworkFood};
+pub use files::SaveMode;
+pub use network::activation::Activation;
+pub use network::graph::{Graph, Node};
+pub use network::layers::{
+    AvgPool, BatchNorm, Conv1D, Conv2D, Dense, Embedding, Flatten, GlobalAveragePool, Gru, Layer,
+    LayerData, LayerNorm, LayerParameters, Lstm, MaxPool, MultiHeadAttention, PositionalEncoding,
+    Rnn, TransformerEncoderBlock,
+};
+pub use network::loss::Loss;
+pub use network::neat::{ConnectionGene, Genome, Neat, NeatOptions, NodeGene, NodeKind};
+pub use network::safetensors::SafetensorsError;
+pub use network::scaler::{Scaler, ScalerKind};
+pub use network::training::backprop::LayerGradient;
+pub use network::training::csv::{CsvColumn, CsvOptions, CsvSplit};
+pub use network::training::data::{DataPoint, DataSet, DataSplit, NetworkFood, Points};
+pub use network::training::dropout::Dropout;
+pub use network::training::dset_file::DataSetFile;
+pub use network::training::evaluation::{Average, EvaluationReport};
+pub use network::training::evolution::{Evolution, EvolutionOptions, Selection};
+pub use network::training::idx::IdxOptions;
+pub use network::training::regularization::Regularization;
+pub use network::training::sequence::{
+    NetworkState, Sequence, SequenceDataSet, SequenceFood, SequencePoint,
+};
+pub use network::training::split::KFolds;
 pub use network::Network;
 mod files;
+mod numpy;
+mod onnx;
@@ -0,0 +1,120 @@
+use std::fmt;
+use std::rc::Rc;
+
+/// The activation function applied to the output of every neuron.
+///
+/// The named variants have exact derivatives and can be serialized, while `Custom` wraps any closure (this is what [`Network::new`](super::Network::new) builds) and falls back to a finite difference for its derivative.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Activation {
+    Identity,
+    Sigmoid,
+    Tanh,
+    Relu,
+    /// ReLU with the given slope for negative inputs.
+    LeakyRelu(f64),
+    /// ELU with the given alpha.
+    Elu(f64),
+    Softplus,
+    /// A user supplied function. It can't be serialized, since closures have no name to save.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Rc<dyn Fn(f64) -> f64>),
+}
+
+impl Activation {
+    pub fn custom<F: Fn(f64) -> f64 + 'static>(func: F) -> Activation {
+        //! Wraps a closure as an activation function.
+        Activation::Custom(Rc::new(func))
+    }
+    pub fn apply(&self, x: f64) -> f64 {
+        match self {
+            Activation::Identity => x,
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.max(0.0),
+            Activation::LeakyRelu(slope) => {
+                if x > 0.0 {
+                    x
+                } else {
+                    slope * x
+                }
+            }
+            Activation::Elu(alpha) => {
+                if x > 0.0 {
+                    x
+                } else {
+                    alpha * (x.exp() - 1.0)
+                }
+            }
+            Activation::Softplus => {
+                // ln(1 + e^x) without overflowing for large x
+                x.max(0.0) + (-x.abs()).exp().ln_1p()
+            }
+            Activation::Custom(func) => func(x),
+        }
+    }
+    pub fn derivative(&self, x: f64) -> f64 {
+        //! The derivative of the activation function at x. Exact for the named activations, a central difference for `Custom`.
+        match self {
+            Activation::Identity => 1.0,
+            Activation::Sigmoid => {
+                let s = self.apply(x);
+                s * (1.0 - s)
+            }
+            Activation::Tanh => 1.0 - x.tanh().powi(2),
+            Activation::Relu => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Activation::LeakyRelu(slope) => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    *slope
+                }
+            }
+            Activation::Elu(alpha) => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    alpha * x.exp()
+                }
+            }
+            Activation::Softplus => 1.0 / (1.0 + (-x).exp()),
+            Activation::Custom(func) => {
+                let h = 0.0001;
+                (func(x + h / 2.0) - func(x - h / 2.0)) / h
+            }
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activation::Identity => "identity",
+            Activation::Sigmoid => "sigmoid",
+            Activation::Tanh => "tanh",
+            Activation::Relu => "relu",
+            Activation::LeakyRelu(_) => "leaky_relu",
+            Activation::Elu(_) => "elu",
+            Activation::Softplus => "softplus",
+            Activation::Custom(_) => "custom",
+        }
+    }
+}
+
+impl fmt::Debug for Activation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Activation::Identity => write!(f, "Identity"),
+            Activation::Sigmoid => write!(f, "Sigmoid"),
+            Activation::Tanh => write!(f, "Tanh"),
+            Activation::Relu => write!(f, "Relu"),
+            Activation::LeakyRelu(slope) => write!(f, "LeakyRelu({})", slope),
+            Activation::Elu(alpha) => write!(f, "Elu({})", alpha),
+            Activation::Softplus => write!(f, "Softplus"),
+            Activation::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
@@ -0,0 +1,709 @@
+use super::super::files::{
+    parse_graph, parse_graph_file, write_graph, write_graph_file, GraphData, SaveMode,
+};
+use super::activation::Activation;
+use super::layers::{Layer, LayerData};
+use super::loss::Loss;
+use super::training::backprop::{check_layer_gradients, LayerGradient};
+use super::training::data::{DataSplit, NetworkFood};
+use std::io::{Read, Write};
+
+/// One node of a [`Graph`]. Every node other than an input takes the outputs of nodes added before it, by their index in the graph.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Node {
+    /// One of the graph's inputs, with its length.
+    Input(usize),
+    /// A layer, run on the outputs of the node.
+    Layer(Box<dyn Layer>, usize),
+    /// Adds the outputs of nodes of the same length together, value by value. A node added to the output of an earlier one makes a residual connection.
+    Add(Vec<usize>),
+    /// Multiplies the outputs of nodes of the same length together, value by value, such as to gate one node's outputs by another's.
+    Multiply(Vec<usize>),
+    /// Joins the outputs of the nodes one after another.
+    Concatenate(Vec<usize>),
+}
+
+impl Node {
+    pub fn inputs(&self) -> Vec<usize> {
+        //! The nodes this node takes its inputs from.
+        match self {
+            Node::Input(_) => vec![],
+            Node::Layer(_, input) => vec![*input],
+            Node::Add(inputs) | Node::Multiply(inputs) | Node::Concatenate(inputs) => {
+                inputs.clone()
+            }
+        }
+    }
+}
+
+/// A model whose layers don't have to form a single chain like a [`Network`](super::Network)'s: its [`Node`]s can branch out from and merge into each other, so it can have residual blocks, DenseNet-style connections, and several inputs and outputs.
+///
+/// Nodes can only take inputs from nodes added before them, so the order they were added in is always a topological order, and it is the order the forward pass runs in (and the backward pass runs in reverse). Like a network, a graph takes and returns flat lists of values: its inputs one after another, and its outputs one after another, which lets it train on any [`NetworkFood`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "GraphFields"))]
+pub struct Graph {
+    activation: Activation,
+    nodes: Vec<Node>,
+    outputs: Vec<usize>,
+}
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct GraphFields {
+    activation: Activation,
+    nodes: Vec<Node>,
+    outputs: Vec<usize>,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<GraphFields> for Graph {
+    type Error = String;
+    fn try_from(data: GraphFields) -> Result<Graph, String> {
+        //Goes through the same checks as building the graph node by node
+        Graph::from_nodes(data.nodes, data.outputs, data.activation)
+            .ok_or_else(|| "the nodes don't form a valid graph".to_string())
+    }
+}
+
+impl Graph {
+    pub fn new(activation: Activation) -> Graph {
+        /*!
+        Creates an empty graph, whose layers are activated by the activation function. Nodes are added with [`Graph::input`], [`Graph::layer`] and the merges, and the last node added is the output until [`Graph::set_outputs`] says otherwise.
+        ```
+        # use neurnet::*;
+        // A residual block: the block's input is added back to its output
+        let mut graph = Graph::new(Activation::Tanh);
+        let input = graph.input(2);
+        let hidden = graph.layer(Box::new(Dense::new(4, 2)), input);
+        let block = graph.layer(Box::new(Dense::new(4, 4)), hidden);
+        let block = graph.layer(Box::new(Dense::new(4, 4)), block);
+        let residual = graph.add(&[hidden, block]);
+        graph.layer(Box::new(Dense::new(1, 4)), residual);
+        graph.randomize((-1.0, 1.0), (-0.5, 0.5));
+
+        assert_eq!(graph.pulse(vec![0.3, -0.6]).len(), 1);
+        let errors = graph.gradient_check((&[0.3, -0.6], &[0.5]), &Loss::MeanSquaredError, 1e-6);
+        assert_eq!(errors.len(), 4);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+        ```
+        */
+        Graph {
+            activation,
+            nodes: vec![],
+            outputs: vec![],
+        }
+    }
+    pub fn from_nodes(
+        nodes: Vec<Node>,
+        outputs: Vec<usize>,
+        activation: Activation,
+    ) -> Option<Graph> {
+        //! Builds a graph out of nodes in the order they run in. Returns None if a node takes inputs from itself or a later node, if the lengths of a node's inputs don't fit it, or if an output isn't a node. An empty list of outputs means the last node is the output.
+        let mut graph = Graph::new(activation);
+        for node in nodes {
+            graph.push_node(node)?;
+        }
+        if outputs.iter().any(|output| *output >= graph.nodes.len()) {
+            return None;
+        }
+        graph.outputs = outputs;
+        Some(graph)
+    }
+    fn push_node(&mut self, node: Node) -> Option<usize> {
+        //Adds the node if its inputs exist and fit it
+        let inputs = node.inputs();
+        if inputs.iter().any(|input| *input >= self.nodes.len()) {
+            return None;
+        }
+        let lens: Vec<usize> = inputs.iter().map(|input| self.node_len(*input)).collect();
+        let valid = match &node {
+            Node::Input(_) => true,
+            Node::Layer(layer, _) => layer.input_len() == lens[0],
+            Node::Add(_) | Node::Multiply(_) => {
+                !lens.is_empty() && lens.iter().all(|len| *len == lens[0])
+            }
+            Node::Concatenate(_) => !lens.is_empty(),
+        };
+        if !valid {
+            return None;
+        }
+        self.nodes.push(node);
+        Some(self.nodes.len() - 1)
+    }
+    pub fn input(&mut self, len: usize) -> usize {
+        //! Adds an input with the given length and returns its node. The graph takes its inputs in the order they were added.
+        self.push_node(Node::Input(len)).unwrap()
+    }
+    pub fn layer(&mut self, layer: Box<dyn Layer>, input: usize) -> usize {
+        //! Adds a layer run on the outputs of the input node, and returns its node.
+        //! # Panics
+        //! <ul>
+        //! <li> If the input node doesn't exist.
+        //! <li> If the layer's input length is not equal to the input node's output length.
+        //! </ul>
+        self.push_node(Node::Layer(layer, input))
+            .expect("Layer input length must match the input node's output length")
+    }
+    pub fn add(&mut self, inputs: &[usize]) -> usize {
+        //! Adds a node that adds the outputs of the input nodes together, and returns it.
+        //! # Panics
+        //! <ul>
+        //! <li> If there are no input nodes, or one doesn't exist.
+        //! <li> If the input nodes' output lengths differ.
+        //! </ul>
+        self.push_node(Node::Add(inputs.to_vec()))
+            .expect("Added nodes must exist and have the same length")
+    }
+    pub fn multiply(&mut self, inputs: &[usize]) -> usize {
+        //! Adds a node that multiplies the outputs of the input nodes together, and returns it.
+        //! # Panics
+        //! <ul>
+        //! <li> If there are no input nodes, or one doesn't exist.
+        //! <li> If the input nodes' output lengths differ.
+        //! </ul>
+        self.push_node(Node::Multiply(inputs.to_vec()))
+            .expect("Multiplied nodes must exist and have the same length")
+    }
+    pub fn concatenate(&mut self, inputs: &[usize]) -> usize {
+        /*!
+        Adds a node that joins the outputs of the input nodes one after another, and returns it.
+        ```
+        # use neurnet::*;
+        // DenseNet-style connectivity with two inputs and two outputs
+        let mut graph = Graph::new(Activation::Sigmoid);
+        let a = graph.input(2);
+        let b = graph.input(1);
+        let joined = graph.concatenate(&[a, b]);
+        let first = graph.layer(Box::new(Dense::new(3, 3)), joined);
+        let both = graph.concatenate(&[joined, first]);
+        let second = graph.layer(Box::new(Dense::new(2, 6)), both);
+        let gate = graph.layer(Box::new(Dense::new(2, 3)), first);
+        let gated = graph.multiply(&[second, gate]);
+        let side = graph.layer(Box::new(Dense::new(1, 3)), first);
+        graph.set_outputs(&[gated, side]);
+        graph.randomize((-1.0, 1.0), (-0.5, 0.5));
+        assert_eq!(graph.input_lens(), vec![2, 1]);
+        assert_eq!(graph.output_lens(), vec![2, 1]);
+
+        let outputs = graph.pulse_multi(&[vec![0.1, 0.2], vec![0.3]]);
+        assert_eq!(outputs.concat(), graph.pulse(vec![0.1, 0.2, 0.3]));
+        let errors = graph.gradient_check((&[0.1, 0.2, 0.3], &[1.0, 0.0, 0.5]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+        ```
+        # Panics
+        <ul>
+        <li> If there are no input nodes, or one doesn't exist.
+        </ul>
+        */
+        self.push_node(Node::Concatenate(inputs.to_vec()))
+            .expect("Concatenated nodes must exist")
+    }
+    pub fn set_outputs(&mut self, outputs: &[usize]) {
+        //! Sets which nodes' outputs the graph returns, in order.
+        //! # Panics
+        //! <ul>
+        //! <li> If an output node doesn't exist.
+        //! </ul>
+        assert!(
+            outputs.iter().all(|output| *output < self.nodes.len()),
+            "Output nodes must exist"
+        );
+        self.outputs = outputs.to_vec();
+    }
+    pub fn get_outputs(&self) -> Vec<usize> {
+        //! The nodes whose outputs the graph returns, which is the last node if none were set.
+        match self.outputs.is_empty() {
+            true => self.nodes.len().checked_sub(1).into_iter().collect(),
+            false => self.outputs.clone(),
+        }
+    }
+    pub fn get_nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+    pub fn get_layers(&self) -> Vec<&dyn Layer> {
+        //! The graph's layers in the order of their nodes, which is also the order of the gradients from [`Graph::gradient`].
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Layer(layer, _) => Some(&**layer),
+                _ => None,
+            })
+            .collect()
+    }
+    fn layers_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Layer>> {
+        self.nodes.iter_mut().filter_map(|node| match node {
+            Node::Layer(layer, _) => Some(layer),
+            _ => None,
+        })
+    }
+    pub fn get_activation(&self) -> &Activation {
+        &self.activation
+    }
+    pub fn set_activation(&mut self, activation: Activation) {
+        self.activation = activation;
+    }
+    fn node_len(&self, node: usize) -> usize {
+        match &self.nodes[node] {
+            Node::Input(len) => *len,
+            Node::Layer(layer, _) => layer.output_len(),
+            Node::Add(inputs) | Node::Multiply(inputs) => self.node_len(inputs[0]),
+            Node::Concatenate(inputs) => inputs.iter().map(|input| self.node_len(*input)).sum(),
+        }
+    }
+    pub fn input_lens(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Input(len) => Some(*len),
+                _ => None,
+            })
+            .collect()
+    }
+    pub fn output_lens(&self) -> Vec<usize> {
+        self.get_outputs()
+            .iter()
+            .map(|output| self.node_len(*output))
+            .collect()
+    }
+    pub fn randomize(&mut self, weights_range: (f64, f64), biases_range: (f64, f64)) {
+        //! Randomizes every layer in the graph.
+        for layer in self.layers_mut() {
+            layer.randomize(weights_range, biases_range);
+        }
+    }
+    pub fn pulse(&self, input: Vec<f64>) -> Vec<f64> {
+        //! Runs the inputs, every input node's values one after another, through the graph and returns every output node's values one after another.
+        //! # Panics
+        //! <ul>
+        //! <li> If the input vector's length is not equal to the total length of the input nodes.
+        //! </ul>
+        self.trace(&[&input], false)
+            .concat_outputs(&self.get_outputs(), 0)
+    }
+    pub fn pulse_multi(&self, inputs: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        //! Runs a list of values for each input node through the graph, and returns a list of values for each output node. See [`Graph::concatenate`] for an example.
+        //! # Panics
+        //! <ul>
+        //! <li> If the inputs don't have the lengths of the input nodes.
+        //! </ul>
+        assert_eq!(
+            inputs
+                .iter()
+                .map(|input| input.len())
+                .collect::<Vec<usize>>(),
+            self.input_lens(),
+            "Inputs must match the graph's input nodes"
+        );
+        let output_lens = self.output_lens();
+        let outputs = self.pulse(inputs.concat());
+        let mut start = 0;
+        output_lens
+            .iter()
+            .map(|len| {
+                start += len;
+                outputs[start - len..start].to_vec()
+            })
+            .collect()
+    }
+    fn trace(&self, inputs: &[&[f64]], training: bool) -> Trace {
+        //Runs a batch through every node in order, keeping every node's outputs
+        let input_len: usize = self.input_lens().iter().sum();
+        for input in inputs {
+            if input.len() != input_len {
+                panic!("Graph was passed a different amount of inputs than its input nodes take");
+            }
+        }
+        let mut values: Vec<Vec<Vec<f64>>> = vec![];
+        let mut input_start = 0;
+        for node in self.nodes.iter() {
+            let outputs = match node {
+                Node::Input(len) => {
+                    input_start += len;
+                    inputs
+                        .iter()
+                        .map(|input| input[input_start - len..input_start].to_vec())
+                        .collect()
+                }
+                Node::Layer(layer, input) => match training {
+                    true => layer.forward_batch(&values[*input], &self.activation),
+                    false => values[*input]
+                        .iter()
+                        .map(|input| layer.forward(input, &self.activation))
+                        .collect(),
+                },
+                Node::Add(nodes) | Node::Multiply(nodes) => (0..inputs.len())
+                    .map(|point| {
+                        let mut outputs = values[nodes[0]][point].clone();
+                        for node_i in nodes[1..].iter() {
+                            for (output, x) in outputs.iter_mut().zip(values[*node_i][point].iter())
+                            {
+                                match node {
+                                    Node::Add(_) => *output += x,
+                                    _ => *output *= x,
+                                }
+                            }
+                        }
+                        outputs
+                    })
+                    .collect(),
+                Node::Concatenate(nodes) => (0..inputs.len())
+                    .map(|point| {
+                        nodes
+                            .iter()
+                            .flat_map(|node_i| values[*node_i][point].iter().copied())
+                            .collect()
+                    })
+                    .collect(),
+            };
+            values.push(outputs);
+        }
+        Trace { values }
+    }
+    pub fn batch_loss(&self, points: &[(&[f64], &[f64])], loss: &Loss) -> f64 {
+        //! The average loss over a batch of points the way [`Graph::train_with_loss`] sees it, with layers like [`BatchNorm`](super::layers::BatchNorm) using the batch's statistics.
+        let inputs: Vec<&[f64]> = points.iter().map(|(inputs, _)| *inputs).collect();
+        let trace = self.trace(&inputs, true);
+        let outputs = self.get_outputs();
+        let total: f64 = points
+            .iter()
+            .enumerate()
+            .map(|(point, (_, expected_outputs))| {
+                loss.cost(&trace.concat_outputs(&outputs, point), expected_outputs)
+            })
+            .sum();
+        total / points.len() as f64
+    }
+    pub fn gradient(
+        &self,
+        inputs: &[f64],
+        expected_outputs: &[f64],
+        loss: &Loss,
+    ) -> Vec<LayerGradient> {
+        //! Computes the exact gradient of the loss for a single data point with respect to every layer's parameters, using backpropagation through the nodes in reverse order. There is one gradient per layer, in the order of [`Graph::get_layers`].
+        self.batch_loss_gradient(&[(inputs, expected_outputs)], loss, false)
+            .0
+    }
+    pub fn batch_gradient(&self, points: &[(&[f64], &[f64])], loss: &Loss) -> Vec<LayerGradient> {
+        //! Computes the exact gradient of [`Graph::batch_loss`] with respect to every layer's parameters.
+        self.batch_loss_gradient(points, loss, true).0
+    }
+    fn batch_loss_gradient(
+        &self,
+        points: &[(&[f64], &[f64])],
+        loss: &Loss,
+        training: bool,
+    ) -> (Vec<LayerGradient>, Trace) {
+        //Returns the gradient of the batch's average loss, along with every node's outputs
+        let inputs: Vec<&[f64]> = points.iter().map(|(inputs, _)| *inputs).collect();
+        let trace = self.trace(&inputs, training);
+        let outputs = self.get_outputs();
+
+        //The gradient with respect to every node's outputs, for every point
+        let mut grads: Vec<Option<Vec<Vec<f64>>>> = vec![None; self.nodes.len()];
+        for (point, (_, expected_outputs)) in points.iter().enumerate() {
+            let mut output_grad =
+                loss.derivative(&trace.concat_outputs(&outputs, point), expected_outputs);
+            for grad in output_grad.iter_mut() {
+                *grad /= points.len() as f64;
+            }
+            let mut start = 0;
+            for output in outputs.iter() {
+                let len = self.node_len(*output);
+                self.add_grad(
+                    &mut grads,
+                    *output,
+                    point,
+                    points.len(),
+                    &output_grad[start..start + len],
+                );
+                start += len;
+            }
+        }
+
+        let mut gradients: Vec<LayerGradient> = vec![];
+        for (node_i, node) in self.nodes.iter().enumerate().rev() {
+            let node_grads = grads[node_i].take();
+            match node {
+                Node::Input(_) => {}
+                Node::Layer(layer, input) => {
+                    let node_grads = node_grads
+                        .unwrap_or_else(|| vec![vec![0.0; layer.output_len()]; points.len()]);
+                    let (input_grads, gradient) = match training {
+                        true => layer.backward_batch(
+                            &trace.values[*input],
+                            &node_grads,
+                            &self.activation,
+                        ),
+                        false => {
+                            let mut input_grads: Vec<Vec<f64>> = vec![];
+                            let mut total = layer.zero_gradient();
+                            for (input, output_grad) in
+                                trace.values[*input].iter().zip(node_grads.iter())
+                            {
+                                let (input_grad, gradient) =
+                                    layer.backward(input, output_grad, &self.activation);
+                                input_grads.push(input_grad);
+                                total.add(&gradient);
+                            }
+                            (input_grads, total)
+                        }
+                    };
+                    for (point, input_grad) in input_grads.iter().enumerate() {
+                        self.add_grad(&mut grads, *input, point, points.len(), input_grad);
+                    }
+                    gradients.push(gradient);
+                }
+                Node::Add(nodes) | Node::Multiply(nodes) => {
+                    let Some(node_grads) = node_grads else {
+                        continue;
+                    };
+                    for (point, node_grad) in node_grads.iter().enumerate() {
+                        for (k, input) in nodes.iter().enumerate() {
+                            let mut input_grad = node_grad.clone();
+                            if let Node::Multiply(_) = node {
+                                //Each input's gradient is scaled by every other input
+                                for (j, other) in nodes.iter().enumerate() {
+                                    if j == k {
+                                        continue;
+                                    }
+                                    for (grad, x) in input_grad
+                                        .iter_mut()
+                                        .zip(trace.values[*other][point].iter())
+                                    {
+                                        *grad *= x;
+                                    }
+                                }
+                            }
+                            self.add_grad(&mut grads, *input, point, points.len(), &input_grad);
+                        }
+                    }
+                }
+                Node::Concatenate(nodes) => {
+                    let Some(node_grads) = node_grads else {
+                        continue;
+                    };
+                    for (point, node_grad) in node_grads.iter().enumerate() {
+                        let mut start = 0;
+                        for input in nodes.iter() {
+                            let len = self.node_len(*input);
+                            self.add_grad(
+                                &mut grads,
+                                *input,
+                                point,
+                                points.len(),
+                                &node_grad[start..start + len],
+                            );
+                            start += len;
+                        }
+                    }
+                }
+            }
+        }
+        gradients.reverse();
+        (gradients, trace)
+    }
+    fn add_grad(
+        &self,
+        grads: &mut [Option<Vec<Vec<f64>>>],
+        node: usize,
+        point: usize,
+        points: usize,
+        grad: &[f64],
+    ) {
+        //Adds to the gradient of a node's outputs, which are all zeros until something depends on them
+        let len = self.node_len(node);
+        let node_grads = grads[node].get_or_insert_with(|| vec![vec![0.0; len]; points]);
+        for (total, grad) in node_grads[point].iter_mut().zip(grad.iter()) {
+            *total += grad;
+        }
+    }
+    pub fn gradient_check(&self, point: (&[f64], &[f64]), loss: &Loss, epsilon: f64) -> Vec<f64> {
+        //! Compares the gradient from [`Graph::gradient`] against central differences for every parameter of every layer, and returns the largest relative error in each layer, like [`Network::gradient_check`](super::Network::gradient_check).
+        let (inputs, expected_outputs) = point;
+        let gradients = self.gradient(inputs, expected_outputs, loss);
+        let loss_of = |graph: &Graph| loss.cost(&graph.pulse(inputs.to_vec()), expected_outputs);
+        check_layer_gradients(
+            self,
+            &self.get_layers(),
+            &gradients,
+            &|graph: &mut Graph, layer, parameters| {
+                graph
+                    .layers_mut()
+                    .nth(layer)
+                    .unwrap()
+                    .set_parameters(parameters);
+            },
+            &loss_of,
+            epsilon,
+        )
+    }
+    pub fn apply_gradient(&mut self, gradients: &[LayerGradient], rate: f64) {
+        //! Takes a gradient descent step, moving every layer's parameters against its gradient scaled by the rate.
+        for (layer, gradient) in self.layers_mut().zip(gradients.iter()) {
+            layer.apply_gradient(gradient, rate);
+        }
+    }
+    pub fn train_with_loss(
+        &mut self,
+        food: &impl NetworkFood,
+        loss: &Loss,
+        rate: f64,
+        batch_size: usize,
+        epochs: usize,
+    ) {
+        /*!
+        Trains the graph with mini-batch gradient descent on exact gradients, like [`Network::train_with_loss`](super::Network::train_with_loss). Each data point's inputs are the graph's inputs one after another, and its expected outputs are the graph's outputs one after another.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::gen_from_fn(
+            |x| vec![1.5 * x[0] - 0.2],
+            (-20..20).map(|x| vec![x as f64 / 20.0]).collect(),
+            (-5..5).map(|x| vec![x as f64 / 5.0]).collect(),
+        );
+        // The skip connection carries the input straight to the output, so the layers only learn the difference
+        let mut graph = Graph::new(Activation::Tanh);
+        let input = graph.input(1);
+        let hidden = graph.layer(Box::new(Dense::new(4, 1)), input);
+        let correction = graph.layer(Box::new(Dense::new(1, 4)), hidden);
+        graph.add(&[input, correction]);
+        graph.randomize((-0.5, 0.5), (-0.1, 0.1));
+        let before = graph.test_loss(&ds, &Loss::MeanSquaredError);
+        graph.train_with_loss(&ds, &Loss::MeanSquaredError, 0.2, 8, 300);
+        assert!(graph.test_loss(&ds, &Loss::MeanSquaredError) < before / 20.0);
+        ```
+        */
+        let batch_size = batch_size.max(1);
+        let len = food.len(DataSplit::Training);
+        for _ in 0..epochs {
+            for start in (0..len).step_by(batch_size) {
+                let batch = food.batch(DataSplit::Training, start..(start + batch_size).min(len));
+                if batch.is_empty() {
+                    continue;
+                }
+                let points: Vec<(&[f64], &[f64])> = batch
+                    .iter()
+                    .map(|(inputs, expected_outputs)| (inputs.as_ref(), expected_outputs.as_ref()))
+                    .collect();
+                let (gradients, trace) = self.batch_loss_gradient(&points, loss, true);
+                let layer_inputs: Vec<usize> = self
+                    .nodes
+                    .iter()
+                    .filter_map(|node| match node {
+                        Node::Layer(_, input) => Some(*input),
+                        _ => None,
+                    })
+                    .collect();
+                for (layer, input) in self.layers_mut().zip(layer_inputs) {
+                    layer.update_statistics(&trace.values[input]);
+                }
+                self.apply_gradient(&gradients, rate);
+            }
+        }
+    }
+    pub fn test_loss(&self, food: &impl NetworkFood, loss: &Loss) -> f64 {
+        //! Returns the average loss over the testing data.
+        let len = food.len(DataSplit::Testing);
+        let total: f64 = food
+            .points(DataSplit::Testing)
+            .map(|(inputs, expected_outputs)| {
+                loss.cost(&self.pulse(inputs.to_vec()), &expected_outputs)
+            })
+            .sum();
+        total / len as f64
+    }
+    pub fn save(&self, path: &str) -> Option<()> {
+        //! Saves the graph to the path specified, replacing any file that is already there, like [`Network::save`](super::Network::save). Will return None if saving fails for any reason.
+        self.save_with_mode(path, SaveMode::Overwrite)?;
+        Some(())
+    }
+    pub fn save_with_mode(&self, path: &str, mode: SaveMode) -> Option<String> {
+        //! Atomically saves the graph to the path specified, with the [`SaveMode`] deciding what happens if the path already exists. Returns the path the graph was actually saved to, or None if saving failed.
+        write_graph_file(path, mode, self.to_graph_data())
+    }
+    pub fn write_to<W: Write>(&self, writer: W) -> Option<()> {
+        /*!
+        Writes the graph to any writer. The format is the one .neur files store layers in: a record for every node, holding its kind, its settings, its tensors and the nodes it takes its inputs from, and then the list of output nodes.
+        ```
+        # use neurnet::*;
+        let mut graph = Graph::new(Activation::Tanh);
+        let a = graph.input(2);
+        let b = graph.input(2);
+        let normalized = graph.layer(Box::new(LayerNorm::new(2)), b);
+        let sum = graph.add(&[a, normalized]);
+        let out = graph.layer(Box::new(Dense::new(1, 2)), sum);
+        graph.set_outputs(&[out, sum]);
+        graph.randomize((-1.0, 1.0), (-1.0, 1.0));
+
+        let mut buf: Vec<u8> = vec![];
+        graph.write_to(&mut buf).unwrap();
+        let loaded = Graph::read_from(buf.as_slice(), |x| x.tanh()).unwrap();
+        assert_eq!(loaded.get_outputs(), vec![out, sum]);
+        assert_eq!(loaded.pulse(vec![0.1, 0.2, 0.3, 0.4]), graph.pulse(vec![0.1, 0.2, 0.3, 0.4]));
+
+        // Nodes can't take inputs from later nodes
+        assert!(Graph::read_from("[[add,[],[],[1]],[input,[len,1],[],[]]],\n[0]".as_bytes(), |x| x).is_none());
+        ```
+        */
+        write_graph(writer, self.to_graph_data())
+    }
+    pub fn load<AF: Fn(f64) -> f64 + 'static>(path: &str, activation_fn: AF) -> Option<Graph> {
+        //! Loads a graph from the path specified. Like [`Network::load`](super::Network::load), the activation function has to be specified. Returns None if loading failed.
+        Graph::from_graph_data(parse_graph_file(path)?, Activation::custom(activation_fn))
+    }
+    pub fn read_from<R: Read, AF: Fn(f64) -> f64 + 'static>(
+        reader: R,
+        activation_fn: AF,
+    ) -> Option<Graph> {
+        //! Reads a graph from any reader. Like [`Graph::load`], the activation function has to be specified. Returns None if the data is not a valid graph.
+        Graph::from_graph_data(parse_graph(reader)?, Activation::custom(activation_fn))
+    }
+    fn to_graph_data(&self) -> GraphData {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let data = match node {
+                    Node::Input(len) => LayerData::new("input").with_setting("len", *len as f64),
+                    Node::Layer(layer, _) => layer.serialize(),
+                    Node::Add(_) => LayerData::new("add"),
+                    Node::Multiply(_) => LayerData::new("multiply"),
+                    Node::Concatenate(_) => LayerData::new("concatenate"),
+                };
+                ((data.kind, data.settings, data.tensors), node.inputs())
+            })
+            .collect();
+        (nodes, self.outputs.clone())
+    }
+    fn from_graph_data(data: GraphData, activation: Activation) -> Option<Graph> {
+        let (records, outputs) = data;
+        let mut nodes: Vec<Node> = vec![];
+        for ((kind, settings, tensors), inputs) in records {
+            let data = LayerData {
+                kind,
+                settings,
+                tensors,
+            };
+            nodes.push(match (data.kind.as_str(), inputs.as_slice()) {
+                ("input", []) => Node::Input(data.usize_setting("len")?),
+                ("add", _) => Node::Add(inputs),
+                ("multiply", _) => Node::Multiply(inputs),
+                ("concatenate", _) => Node::Concatenate(inputs),
+                (_, [input]) => Node::Layer(data.to_layer()?, *input),
+                _ => return None,
+            });
+        }
+        Graph::from_nodes(nodes, outputs, activation)
+    }
+}
+
+struct Trace {
+    values: Vec<Vec<Vec<f64>>>, //[node] [point] [output]
+}
+impl Trace {
+    fn concat_outputs(&self, outputs: &[usize], point: usize) -> Vec<f64> {
+        outputs
+            .iter()
+            .flat_map(|output| self.values[*output][point].iter().copied())
+            .collect()
+    }
+}
@@ -0,0 +1,816 @@
+use super::super::activation::Activation;
+use super::{set_no_parameters, Dense, Layer, LayerData, LayerNorm, LayerParameters};
+use rand::RngCore;
+
+fn linear(weights: &[f64], biases: &[f64], input: &[f64]) -> Vec<f64> {
+    //Runs every row of the input through weights laid out as (outputs, inputs)
+    let in_len = weights.len() / biases.len().max(1);
+    input
+        .chunks(in_len.max(1))
+        .flat_map(|row| {
+            biases.iter().enumerate().map(move |(o, bias)| {
+                bias + weights[o * in_len..(o + 1) * in_len]
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(w, x)| w * x)
+                    .sum::<f64>()
+            })
+        })
+        .collect()
+}
+fn linear_backward(
+    weights: &[f64],
+    out_len: usize,
+    input: &[f64],
+    output_grad: &[f64],
+) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    //Returns the gradient with respect to the input, the weights and the biases, summed over the rows
+    let in_len = weights.len() / out_len.max(1);
+    let mut input_grad = vec![0.0; input.len()];
+    let mut weights_grad = vec![0.0; weights.len()];
+    let mut biases_grad = vec![0.0; out_len];
+    for (row, (x, grad)) in input
+        .chunks(in_len.max(1))
+        .zip(output_grad.chunks(out_len.max(1)))
+        .enumerate()
+    {
+        for (o, grad) in grad.iter().enumerate() {
+            biases_grad[o] += grad;
+            for (i, x) in x.iter().enumerate() {
+                weights_grad[o * in_len + i] += grad * x;
+                input_grad[row * in_len + i] += grad * weights[o * in_len + i];
+            }
+        }
+    }
+    (input_grad, weights_grad, biases_grad)
+}
+
+/// Multi-head scaled dot-product self-attention over a sequence of shape (sequence length, model size), read from the flat input in row-major order. Every position is projected to a query, a key and a value; each head compares every query with every key to decide how much of each position's value to take, and the heads' results are joined and projected back to the model size. The output has the same shape as the input, and the network's activation function is not applied.
+///
+/// A mask can stop positions from attending to others, such as a causal mask that only lets each position see itself and the positions before it.
+///
+/// Its [`LayerParameters`] are the query, key and value projection weights, each laid out as (model size, model size), then the output projection's, like PyTorch's `MultiheadAttention.in_proj_weight` and `out_proj.weight`, and the biases in the same order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiHeadAttention {
+    seq_len: usize,
+    model_dim: usize,
+    heads: usize,
+    mask: Option<Vec<bool>>, //[query] [key], whether the query may attend to the key
+    in_weights: Vec<f64>,    //[query, key or value] [output] [input]
+    in_biases: Vec<f64>,
+    out_weights: Vec<f64>,
+    out_biases: Vec<f64>,
+}
+
+struct AttentionTrace {
+    projections: Vec<f64>, //[position] [query, key or value] [value], the in projection's outputs
+    probabilities: Vec<f64>, //[head] [query] [key]
+    context: Vec<f64>,     //[position] [value], the heads' joined results
+}
+
+impl MultiHeadAttention {
+    pub fn new(seq_len: usize, model_dim: usize, heads: usize) -> MultiHeadAttention {
+        /*!
+        Creates an attention layer over sequences of the given length, with the given model size split evenly between the heads, and no mask.
+        ```
+        # use neurnet::*;
+        let attention = MultiHeadAttention::new(4, 6, 2).with_causal_mask();
+        let mut nn = Network::from_layers(
+            vec![
+                Box::new(PositionalEncoding::sinusoidal(4, 6)),
+                Box::new(attention),
+                Box::new(Dense::new(2, 24)),
+            ],
+            Activation::Tanh,
+        )
+        .unwrap();
+        nn.randomize((-0.5, 0.5), (-0.5, 0.5));
+        let sequence: Vec<f64> = (0..24).map(|i| ((i * 5) % 7) as f64 / 7.0 - 0.5).collect();
+        let errors = nn.gradient_check((&sequence, &[0.3, -0.3]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // With a causal mask, changing the last position doesn't change the outputs before it
+        let attention = nn.get_layers()[1].clone();
+        let mut changed = sequence.clone();
+        changed[23] += 1.0;
+        let outputs = attention.forward(&sequence, &Activation::Identity);
+        let changed_outputs = attention.forward(&changed, &Activation::Identity);
+        assert_eq!(outputs[..18], changed_outputs[..18]);
+        assert_ne!(outputs[18..], changed_outputs[18..]);
+        ```
+        # Panics
+        <ul>
+        <li> If there are no heads, or the model size isn't a multiple of the number of heads.
+        </ul>
+        */
+        assert!(
+            heads > 0 && model_dim.is_multiple_of(heads),
+            "The model size must be a multiple of the number of heads"
+        );
+        MultiHeadAttention {
+            seq_len,
+            model_dim,
+            heads,
+            mask: None,
+            in_weights: vec![1.0; 3 * model_dim * model_dim],
+            in_biases: vec![0.0; 3 * model_dim],
+            out_weights: vec![1.0; model_dim * model_dim],
+            out_biases: vec![0.0; model_dim],
+        }
+    }
+    pub fn with_causal_mask(self) -> MultiHeadAttention {
+        //! Masks the attention so each position can only attend to itself and the positions before it.
+        let seq_len = self.seq_len;
+        self.with_mask(
+            (0..seq_len * seq_len)
+                .map(|i| i % seq_len <= i / seq_len)
+                .collect(),
+        )
+    }
+    pub fn with_mask(mut self, mask: Vec<bool>) -> MultiHeadAttention {
+        //! Sets which positions can attend to which, laid out as (query position, key position). A position that can't attend to any position outputs just the output projection's biases.
+        //! ```
+        //! # use neurnet::*;
+        //! let mut attention = MultiHeadAttention::new(2, 2, 1).with_mask(vec![false, false, true, true]);
+        //! attention.randomize((-1.0, 1.0), (-1.0, 1.0));
+        //! let outputs = attention.forward(&[0.1, 0.2, 0.3, 0.4], &Activation::Identity);
+        //! assert_eq!(outputs[..2], attention.parameters().biases[6..]);
+        //!
+        //! // The mask is saved with the layer
+        //! let loaded = attention.serialize().to_layer().unwrap();
+        //! assert_eq!(loaded.forward(&[0.1, 0.2, 0.3, 0.4], &Activation::Identity), outputs);
+        //! ```
+        //! # Panics
+        //! <ul>
+        //! <li> If the mask's length is not the square of the sequence length.
+        //! </ul>
+        assert_eq!(
+            mask.len(),
+            self.seq_len * self.seq_len,
+            "The mask must have a value for every pair of positions"
+        );
+        self.mask = Some(mask);
+        self
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<MultiHeadAttention> {
+        let seq_len = data.usize_setting("seq_len")?;
+        let heads = data.usize_setting("heads")?;
+        let (in_biases_shape, in_biases) = data.tensor("in_proj_bias")?;
+        let model_dim = match in_biases_shape.as_slice() {
+            [len] if len.is_multiple_of(3) => len / 3,
+            _ => return None,
+        };
+        if heads == 0 || !model_dim.is_multiple_of(heads) {
+            return None;
+        }
+        let (in_weights_shape, in_weights) = data.tensor("in_proj_weight")?;
+        let (out_weights_shape, out_weights) = data.tensor("out_proj_weight")?;
+        let (_, out_biases) = data.tensor("out_proj_bias")?;
+        //The weights have to be in the file before the layer allocates them, and the sequence length can't overflow
+        if *in_weights_shape != vec![3 * model_dim, model_dim]
+            || *out_weights_shape != vec![model_dim, model_dim]
+            || seq_len.checked_mul(model_dim).is_none()
+        {
+            return None;
+        }
+        let mask_len = seq_len.checked_mul(seq_len)?;
+        let mut attention = MultiHeadAttention::new(seq_len, model_dim, heads);
+        if let Some((_, mask)) = data.tensor("mask") {
+            if mask.len() != mask_len {
+                return None;
+            }
+            attention.mask = Some(mask.iter().map(|allowed| *allowed != 0.0).collect());
+        }
+        attention.set_parameters(&LayerParameters {
+            weights: [in_weights.as_slice(), out_weights.as_slice()].concat(),
+            biases: [in_biases.as_slice(), out_biases.as_slice()].concat(),
+            rows: vec![],
+        })?;
+        Some(attention)
+    }
+    pub fn seq_len(&self) -> usize {
+        self.seq_len
+    }
+    pub fn model_dim(&self) -> usize {
+        self.model_dim
+    }
+    pub fn heads(&self) -> usize {
+        self.heads
+    }
+    pub fn mask(&self) -> Option<&Vec<bool>> {
+        self.mask.as_ref()
+    }
+    fn allowed(&self, query: usize, key: usize) -> bool {
+        self.mask
+            .as_ref()
+            .is_none_or(|mask| mask[query * self.seq_len + key])
+    }
+    fn projection(&self, kind: usize, position: usize, head: usize) -> usize {
+        //The index of the first value of a head's query (kind 0), key (1) or value (2) at the position
+        let head_dim = self.model_dim / self.heads;
+        (position * 3 + kind) * self.model_dim + head * head_dim
+    }
+    fn trace(&self, input: &[f64]) -> AttentionTrace {
+        let (seq_len, head_dim) = (self.seq_len, self.model_dim / self.heads);
+        let scale = 1.0 / (head_dim as f64).sqrt();
+        let mut trace = AttentionTrace {
+            projections: linear(&self.in_weights, &self.in_biases, input),
+            probabilities: vec![0.0; self.heads * seq_len * seq_len],
+            context: vec![0.0; seq_len * self.model_dim],
+        };
+        for head in 0..self.heads {
+            for query in 0..seq_len {
+                let q = self.projection(0, query, head);
+                let scores: Vec<Option<f64>> = (0..seq_len)
+                    .map(|key| {
+                        if !self.allowed(query, key) {
+                            return None;
+                        }
+                        let k = self.projection(1, key, head);
+                        Some(
+                            (0..head_dim)
+                                .map(|i| trace.projections[q + i] * trace.projections[k + i])
+                                .sum::<f64>()
+                                * scale,
+                        )
+                    })
+                    .collect();
+                let max = scores.iter().flatten().fold(f64::MIN, |a, b| a.max(*b));
+                let exps: Vec<f64> = scores
+                    .iter()
+                    .map(|score| score.map_or(0.0, |score| (score - max).exp()))
+                    .collect();
+                let total: f64 = exps.iter().sum();
+                if total == 0.0 {
+                    continue;
+                }
+                for (key, exp) in exps.iter().enumerate() {
+                    let probability = exp / total;
+                    trace.probabilities[(head * seq_len + query) * seq_len + key] = probability;
+                    let v = self.projection(2, key, head);
+                    for i in 0..head_dim {
+                        trace.context[query * self.model_dim + head * head_dim + i] +=
+                            probability * trace.projections[v + i];
+                    }
+                }
+            }
+        }
+        trace
+    }
+}
+
+impl Layer for MultiHeadAttention {
+    fn input_len(&self) -> usize {
+        self.seq_len * self.model_dim
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.seq_len, self.model_dim]
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        linear(
+            &self.out_weights,
+            &self.out_biases,
+            &self.trace(input).context,
+        )
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let (seq_len, head_dim) = (self.seq_len, self.model_dim / self.heads);
+        let scale = 1.0 / (head_dim as f64).sqrt();
+        let trace = self.trace(input);
+        let (context_grad, out_weights_grad, out_biases_grad) = linear_backward(
+            &self.out_weights,
+            self.model_dim,
+            &trace.context,
+            output_grad,
+        );
+
+        let mut projections_grad = vec![0.0; trace.projections.len()];
+        for head in 0..self.heads {
+            for query in 0..seq_len {
+                let row = (head * seq_len + query) * seq_len;
+                let context = query * self.model_dim + head * head_dim;
+                //The gradient with respect to each probability, then through the softmax to each score
+                let probabilities_grad: Vec<f64> = (0..seq_len)
+                    .map(|key| {
+                        let v = self.projection(2, key, head);
+                        (0..head_dim)
+                            .map(|i| context_grad[context + i] * trace.projections[v + i])
+                            .sum()
+                    })
+                    .collect();
+                let weighted: f64 = (0..seq_len)
+                    .map(|key| trace.probabilities[row + key] * probabilities_grad[key])
+                    .sum();
+                let q = self.projection(0, query, head);
+                for (key, probability_grad) in probabilities_grad.iter().enumerate() {
+                    let probability = trace.probabilities[row + key];
+                    if probability == 0.0 {
+                        continue;
+                    }
+                    let (k, v) = (self.projection(1, key, head), self.projection(2, key, head));
+                    let score_grad = probability * (probability_grad - weighted) * scale;
+                    for i in 0..head_dim {
+                        projections_grad[v + i] += probability * context_grad[context + i];
+                        projections_grad[q + i] += score_grad * trace.projections[k + i];
+                        projections_grad[k + i] += score_grad * trace.projections[q + i];
+                    }
+                }
+            }
+        }
+        let (input_grad, in_weights_grad, in_biases_grad) = linear_backward(
+            &self.in_weights,
+            3 * self.model_dim,
+            input,
+            &projections_grad,
+        );
+        (
+            input_grad,
+            LayerParameters {
+                weights: [in_weights_grad, out_weights_grad].concat(),
+                biases: [in_biases_grad, out_biases_grad].concat(),
+                rows: vec![],
+            },
+        )
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: [self.in_weights.as_slice(), self.out_weights.as_slice()].concat(),
+            biases: [self.in_biases.as_slice(), self.out_biases.as_slice()].concat(),
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        if parameters.weights.len() != self.in_weights.len() + self.out_weights.len()
+            || parameters.biases.len() != self.in_biases.len() + self.out_biases.len()
+        {
+            return None;
+        }
+        let (in_weights, out_weights) = parameters.weights.split_at(self.in_weights.len());
+        let (in_biases, out_biases) = parameters.biases.split_at(self.in_biases.len());
+        self.in_weights.copy_from_slice(in_weights);
+        self.out_weights.copy_from_slice(out_weights);
+        self.in_biases.copy_from_slice(in_biases);
+        self.out_biases.copy_from_slice(out_biases);
+        Some(())
+    }
+    fn serialize(&self) -> LayerData {
+        let d = self.model_dim;
+        let mut data = LayerData::new("multi_head_attention")
+            .with_setting("seq_len", self.seq_len as f64)
+            .with_setting("heads", self.heads as f64)
+            .with_tensor("in_proj_weight", vec![3 * d, d], self.in_weights.clone())
+            .with_tensor("in_proj_bias", vec![3 * d], self.in_biases.clone())
+            .with_tensor("out_proj_weight", vec![d, d], self.out_weights.clone())
+            .with_tensor("out_proj_bias", vec![d], self.out_biases.clone());
+        if let Some(mask) = &self.mask {
+            data = data.with_tensor(
+                "mask",
+                vec![self.seq_len, self.seq_len],
+                mask.iter().map(|allowed| *allowed as u8 as f64).collect(),
+            );
+        }
+        data
+    }
+}
+
+/// Adds a position dependent pattern to a sequence of shape (sequence length, model size), so that layers like [`MultiHeadAttention`], which otherwise treat every position the same, can tell the positions apart.
+///
+/// A sinusoidal encoding adds the fixed sines and cosines of the original Transformer and has no parameters, though its table is still saved with it. A learned encoding adds a table of its own, starting at zero, which makes up its [`LayerParameters`] weights. The network's activation function is not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionalEncoding {
+    seq_len: usize,
+    model_dim: usize,
+    learned: bool,
+    table: Vec<f64>, //[position] [value]
+}
+
+impl PositionalEncoding {
+    pub fn sinusoidal(seq_len: usize, model_dim: usize) -> PositionalEncoding {
+        /*!
+        Creates a fixed encoding where value `2i` of position `p` gets `sin(p / 10000^(2i / model size))` added, and value `2i + 1` the cosine of the same.
+        ```
+        # use neurnet::*;
+        let encoding = PositionalEncoding::sinusoidal(3, 4);
+        let outputs = encoding.forward(&[0.0; 12], &Activation::Identity);
+        assert_eq!(outputs[..4], [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(outputs[4], 1.0f64.sin());
+        assert_eq!(outputs[7], 0.01f64.cos());
+        assert!(encoding.parameters().weights.is_empty());
+
+        // A saved encoding has to hold its table, so a file can't ask for one of any size
+        let data = LayerData::new("positional_encoding")
+            .with_setting("seq_len", 4294967296.0)
+            .with_setting("model_dim", 4294967296.0)
+            .with_setting("learned", 0.0);
+        assert!(data.to_layer().is_none());
+        assert!(encoding.serialize().to_layer().is_some());
+        ```
+        */
+        PositionalEncoding {
+            seq_len,
+            model_dim,
+            learned: false,
+            table: (0..seq_len * model_dim)
+                .map(|i| {
+                    let (position, value) = ((i / model_dim) as f64, i % model_dim);
+                    let angle =
+                        position / 10000f64.powf((value - value % 2) as f64 / model_dim as f64);
+                    match value % 2 {
+                        0 => angle.sin(),
+                        _ => angle.cos(),
+                    }
+                })
+                .collect(),
+        }
+    }
+    pub fn learned(seq_len: usize, model_dim: usize) -> PositionalEncoding {
+        //! Creates a learned encoding, whose table starts at zero. See [`TransformerEncoderBlock::new`] for an example.
+        PositionalEncoding {
+            seq_len,
+            model_dim,
+            learned: true,
+            table: vec![0.0; seq_len * model_dim],
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<PositionalEncoding> {
+        let seq_len = data.usize_setting("seq_len")?;
+        let model_dim = data.usize_setting("model_dim")?;
+        //Both kinds store their table, so its size is backed by the file before it is allocated
+        let (shape, table) = data.tensor("weight")?;
+        if *shape != vec![seq_len, model_dim] {
+            return None;
+        }
+        match data.usize_setting("learned")? {
+            0 => Some(PositionalEncoding::sinusoidal(seq_len, model_dim)),
+            1 => Some(PositionalEncoding {
+                seq_len,
+                model_dim,
+                learned: true,
+                table: table.clone(),
+            }),
+            _ => None,
+        }
+    }
+    pub fn is_learned(&self) -> bool {
+        self.learned
+    }
+}
+
+impl Layer for PositionalEncoding {
+    fn input_len(&self) -> usize {
+        self.seq_len * self.model_dim
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.seq_len, self.model_dim]
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        input
+            .iter()
+            .zip(self.table.iter())
+            .map(|(x, encoding)| x + encoding)
+            .collect()
+    }
+    fn backward(
+        &self,
+        _input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let gradient = LayerParameters {
+            weights: match self.learned {
+                true => output_grad.to_vec(),
+                false => vec![],
+            },
+            biases: vec![],
+            rows: vec![],
+        };
+        (output_grad.to_vec(), gradient)
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: match self.learned {
+                true => self.table.clone(),
+                false => vec![],
+            },
+            biases: vec![],
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        if !self.learned {
+            return set_no_parameters(parameters);
+        }
+        if parameters.weights.len() != self.table.len() || !parameters.biases.is_empty() {
+            return None;
+        }
+        self.table.copy_from_slice(&parameters.weights);
+        Some(())
+    }
+    fn serialize(&self) -> LayerData {
+        LayerData::new("positional_encoding")
+            .with_setting("seq_len", self.seq_len as f64)
+            .with_setting("model_dim", self.model_dim as f64)
+            .with_setting("learned", self.learned as u8 as f64)
+            .with_tensor(
+                "weight",
+                vec![self.seq_len, self.model_dim],
+                self.table.clone(),
+            )
+    }
+}
+
+fn rows_forward(layer: &dyn Layer, input: &[f64], activation: &Activation) -> Vec<f64> {
+    //Runs every position of a sequence through the layer on its own
+    input
+        .chunks(layer.input_len())
+        .flat_map(|row| layer.forward(row, activation))
+        .collect()
+}
+fn rows_backward(
+    layer: &dyn Layer,
+    input: &[f64],
+    output_grad: &[f64],
+    activation: &Activation,
+) -> (Vec<f64>, LayerParameters) {
+    let mut input_grad: Vec<f64> = vec![];
+    let mut total = layer.zero_gradient();
+    for (row, grad) in input
+        .chunks(layer.input_len())
+        .zip(output_grad.chunks(layer.output_len()))
+    {
+        let (row_grad, gradient) = layer.backward(row, grad, activation);
+        input_grad.extend(row_grad);
+        total.add(&gradient);
+    }
+    (input_grad, total)
+}
+fn add(a: &[f64], b: &[f64]) -> Vec<f64> {
+    a.iter().zip(b.iter()).map(|(a, b)| a + b).collect()
+}
+
+/// A Transformer encoder block over a sequence of shape (sequence length, model size), as in "Attention Is All You Need": [`MultiHeadAttention`] whose output is added back to its input and run through a [`LayerNorm`], then a feed-forward network of two [`Dense`] layers applied to every position on its own, whose output is again added back and normalized. The first dense layer uses the network's activation function, and the second isn't activated.
+///
+/// Its [`LayerParameters`] are those of the attention, the first layer normalization, the two dense layers and the second layer normalization, one after another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformerEncoderBlock {
+    attention: MultiHeadAttention,
+    norm1: LayerNorm,
+    linear1: Dense,
+    linear2: Dense,
+    norm2: LayerNorm,
+}
+
+impl TransformerEncoderBlock {
+    pub fn new(
+        seq_len: usize,
+        model_dim: usize,
+        heads: usize,
+        feedforward_dim: usize,
+    ) -> TransformerEncoderBlock {
+        /*!
+        Creates an encoder block over sequences of the given length and model size, with the given number of attention heads and neurons in the feed-forward network's hidden layer.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(
+            vec![
+                Box::new(PositionalEncoding::learned(3, 4)),
+                Box::new(TransformerEncoderBlock::new(3, 4, 2, 8)),
+                Box::new(Dense::new(1, 12)),
+            ],
+            Activation::Tanh,
+        )
+        .unwrap();
+        nn.randomize((-0.5, 0.5), (-0.5, 0.5));
+        let sequence = [0.5, -0.2, 0.1, 0.9, -0.4, 0.3, 0.8, -0.6, 0.2, 0.0, -0.1, 0.7];
+        let errors = nn.gradient_check((&sequence, &[0.4]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // It trains like any other layer
+        let ds = DataSet::new(vec![(sequence.to_vec(), vec![0.4]), (sequence.iter().map(|x| -x).collect(), vec![-0.4])], vec![]);
+        let before = nn.batch_loss(&[(&sequence, &[0.4])], &Loss::MeanSquaredError);
+        nn.train_with_loss(&ds, &Loss::MeanSquaredError, 0.1, 2, 50);
+        assert!(nn.batch_loss(&[(&sequence, &[0.4])], &Loss::MeanSquaredError) < before);
+
+        let mut buf: Vec<u8> = vec![];
+        nn.write_to(&mut buf).unwrap();
+        let loaded = Network::read_from(buf.as_slice(), |x| x.tanh()).unwrap();
+        assert_eq!(loaded.pulse(sequence.to_vec()), nn.pulse(sequence.to_vec()));
+        ```
+        # Panics
+        <ul>
+        <li> If there are no heads, or the model size isn't a multiple of the number of heads.
+        </ul>
+        */
+        TransformerEncoderBlock {
+            attention: MultiHeadAttention::new(seq_len, model_dim, heads),
+            norm1: LayerNorm::new(model_dim),
+            linear1: Dense::new(feedforward_dim, model_dim),
+            linear2: Dense::new(model_dim, feedforward_dim),
+            norm2: LayerNorm::new(model_dim),
+        }
+    }
+    pub fn with_causal_mask(self) -> TransformerEncoderBlock {
+        //! Masks the block's attention so each position can only attend to itself and the positions before it.
+        TransformerEncoderBlock {
+            attention: self.attention.with_causal_mask(),
+            ..self
+        }
+    }
+    pub fn with_mask(self, mask: Vec<bool>) -> TransformerEncoderBlock {
+        //! Sets the block's attention mask, like [`MultiHeadAttention::with_mask`].
+        //! # Panics
+        //! <ul>
+        //! <li> If the mask's length is not the square of the sequence length.
+        //! </ul>
+        TransformerEncoderBlock {
+            attention: self.attention.with_mask(mask),
+            ..self
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<TransformerEncoderBlock> {
+        //Every part is stored as its own data, with its settings and tensors named `{part}.{name}`
+        let part = |prefix: &str, kind: &str| {
+            let strip =
+                |name: &str| Some(name.strip_prefix(prefix)?.strip_prefix('.')?.to_string());
+            LayerData {
+                kind: kind.to_string(),
+                settings: data
+                    .settings
+                    .iter()
+                    .filter_map(|(name, value)| Some((strip(name)?, *value)))
+                    .collect(),
+                tensors: data
+                    .tensors
+                    .iter()
+                    .filter_map(|(name, shape, values)| {
+                        Some((strip(name)?, shape.clone(), values.clone()))
+                    })
+                    .collect(),
+            }
+        };
+        let block = TransformerEncoderBlock {
+            attention: MultiHeadAttention::from_data(&part("self_attn", "multi_head_attention"))?,
+            norm1: LayerNorm::from_data(&part("norm1", "layer_norm"))?,
+            linear1: Dense::from_data(&part("linear1", "dense"))?,
+            linear2: Dense::from_data(&part("linear2", "dense"))?,
+            norm2: LayerNorm::from_data(&part("norm2", "layer_norm"))?,
+        };
+        let d = block.attention.model_dim;
+        if block.norm1.len() != d
+            || block.linear1.prev_layer_len() != d
+            || block.linear2.prev_layer_len() != block.linear1.len()
+            || block.linear2.len() != d
+            || block.norm2.len() != d
+        {
+            return None;
+        }
+        Some(block)
+    }
+    pub fn attention(&self) -> &MultiHeadAttention {
+        &self.attention
+    }
+    fn parts(&self) -> [(&str, &dyn Layer); 5] {
+        [
+            ("self_attn", &self.attention),
+            ("norm1", &self.norm1),
+            ("linear1", &self.linear1),
+            ("linear2", &self.linear2),
+            ("norm2", &self.norm2),
+        ]
+    }
+    fn parts_mut(&mut self) -> [&mut dyn Layer; 5] {
+        [
+            &mut self.attention,
+            &mut self.norm1,
+            &mut self.linear1,
+            &mut self.linear2,
+            &mut self.norm2,
+        ]
+    }
+}
+
+impl Layer for TransformerEncoderBlock {
+    fn input_len(&self) -> usize {
+        self.attention.input_len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        self.attention.output_shape()
+    }
+    fn forward(&self, input: &[f64], activation: &Activation) -> Vec<f64> {
+        let attended = add(input, &self.attention.forward(input, activation));
+        let normalized = rows_forward(&self.norm1, &attended, activation);
+        let hidden = rows_forward(&self.linear1, &normalized, activation);
+        let fed_forward = rows_forward(&self.linear2, &hidden, &Activation::Identity);
+        rows_forward(&self.norm2, &add(&normalized, &fed_forward), activation)
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let attended = add(input, &self.attention.forward(input, activation));
+        let normalized = rows_forward(&self.norm1, &attended, activation);
+        let hidden = rows_forward(&self.linear1, &normalized, activation);
+        let fed_forward = rows_forward(&self.linear2, &hidden, &Activation::Identity);
+
+        let (fed_forward_grad, norm2_gradient) = rows_backward(
+            &self.norm2,
+            &add(&normalized, &fed_forward),
+            output_grad,
+            activation,
+        );
+        let (hidden_grad, linear2_gradient) = rows_backward(
+            &self.linear2,
+            &hidden,
+            &fed_forward_grad,
+            &Activation::Identity,
+        );
+        let (normalized_grad, linear1_gradient) =
+            rows_backward(&self.linear1, &normalized, &hidden_grad, activation);
+        let (attended_grad, norm1_gradient) = rows_backward(
+            &self.norm1,
+            &attended,
+            &add(&fed_forward_grad, &normalized_grad),
+            activation,
+        );
+        let (input_grad, attention_gradient) =
+            self.attention.backward(input, &attended_grad, activation);
+
+        let mut gradient = LayerParameters::default();
+        for part in [
+            attention_gradient,
+            norm1_gradient,
+            linear1_gradient,
+            linear2_gradient,
+            norm2_gradient,
+        ] {
+            gradient.weights.extend(part.weights);
+            gradient.biases.extend(part.biases);
+        }
+        (add(&input_grad, &attended_grad), gradient)
+    }
+    fn parameters(&self) -> LayerParameters {
+        let mut parameters = LayerParameters::default();
+        for (_, part) in self.parts() {
+            let part = part.parameters();
+            parameters.weights.extend(part.weights);
+            parameters.biases.extend(part.biases);
+        }
+        parameters
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        let lens: Vec<(usize, usize)> = self
+            .parts()
+            .iter()
+            .map(|(_, part)| {
+                let part = part.parameters();
+                (part.weights.len(), part.biases.len())
+            })
+            .collect();
+        if parameters.weights.len() != lens.iter().map(|lens| lens.0).sum::<usize>()
+            || parameters.biases.len() != lens.iter().map(|lens| lens.1).sum::<usize>()
+        {
+            return None;
+        }
+        let (mut weights, mut biases) = (0, 0);
+        for (part, (weights_len, biases_len)) in self.parts_mut().into_iter().zip(lens) {
+            part.set_parameters(&LayerParameters {
+                weights: parameters.weights[weights..weights + weights_len].to_vec(),
+                biases: parameters.biases[biases..biases + biases_len].to_vec(),
+                rows: vec![],
+            })?;
+            weights += weights_len;
+            biases += biases_len;
+        }
+        Some(())
+    }
+    fn serialize(&self) -> LayerData {
+        let mut data = LayerData::new("transformer_encoder_block");
+        for (prefix, part) in self.parts() {
+            let part = part.serialize();
+            for (name, value) in part.settings {
+                data = data.with_setting(&format!("{}.{}", prefix, name), value);
+            }
+            for (name, shape, values) in part.tensors {
+                data = data.with_tensor(&format!("{}.{}", prefix, name), shape, values);
+            }
+        }
+        data
+    }
+    fn randomize_with(
+        &mut self,
+        weights_range: (f64, f64),
+        biases_range: (f64, f64),
+        rng: &mut dyn RngCore,
+    ) {
+        for part in self.parts_mut() {
+            part.randomize_with(weights_range, biases_range, rng);
+        }
+    }
+}
@@ -0,0 +1,472 @@
+use super::super::activation::Activation;
+use super::{Layer, LayerData, LayerParameters};
+
+/// How a kernel slides over a (channels, height, width) input. One dimensional inputs have a height of 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Window {
+    pub channels: usize,
+    pub height: usize,
+    pub width: usize,
+    pub kernel: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+}
+
+impl Window {
+    pub fn new(input: (usize, usize, usize), kernel: (usize, usize)) -> Window {
+        Window {
+            channels: input.0,
+            height: input.1,
+            width: input.2,
+            kernel,
+            stride: (1, 1),
+            padding: (0, 0),
+        }
+    }
+    fn padded(&self) -> Option<(usize, usize)> {
+        //The height and width with the padding added, or None if they overflow
+        Some((
+            self.height.checked_add(self.padding.0.checked_mul(2)?)?,
+            self.width.checked_add(self.padding.1.checked_mul(2)?)?,
+        ))
+    }
+    pub fn is_valid(&self) -> bool {
+        let (height, width) = match self.padded() {
+            Some(padded) => padded,
+            None => return false,
+        };
+        self.kernel.0 > 0
+            && self.kernel.1 > 0
+            && self.stride.0 > 0
+            && self.stride.1 > 0
+            && self.kernel.0 <= height
+            && self.kernel.1 <= width
+            && self.kernel.0.checked_mul(self.kernel.1).is_some()
+            && self
+                .channels
+                .checked_mul(self.height)
+                .and_then(|len| len.checked_mul(self.width))
+                .is_some()
+            && self
+                .out_height()
+                .checked_mul(self.out_width())
+                .and_then(|positions| positions.checked_mul(self.channels))
+                .is_some()
+    }
+    pub fn assert_valid(&self) {
+        assert!(
+            self.is_valid(),
+            "The kernel must fit in the padded input, kernel sizes and strides must be positive, and the input and output sizes must fit in a usize"
+        );
+    }
+    pub fn input_len(&self) -> usize {
+        self.channels * self.height * self.width
+    }
+    pub fn out_height(&self) -> usize {
+        (self.height + 2 * self.padding.0 - self.kernel.0) / self.stride.0 + 1
+    }
+    pub fn out_width(&self) -> usize {
+        (self.width + 2 * self.padding.1 - self.kernel.1) / self.stride.1 + 1
+    }
+    pub fn positions(&self) -> usize {
+        self.out_height() * self.out_width()
+    }
+    pub fn kernel_len(&self) -> usize {
+        self.kernel.0 * self.kernel.1
+    }
+    pub fn input_index(&self, channel: usize, position: usize, offset: usize) -> Option<usize> {
+        //The input under the kernel offset at an output position, or None if it's padding
+        let (out_y, out_x) = (position / self.out_width(), position % self.out_width());
+        let (ky, kx) = (offset / self.kernel.1, offset % self.kernel.1);
+        let y = (out_y * self.stride.0 + ky).checked_sub(self.padding.0)?;
+        let x = (out_x * self.stride.1 + kx).checked_sub(self.padding.1)?;
+        if y >= self.height || x >= self.width {
+            return None;
+        }
+        Some((channel * self.height + y) * self.width + x)
+    }
+}
+
+/// A 2D convolution over an input of shape (channels, height, width), read from the flat input in row-major order. Each of its filters slides a (kernel height, kernel width) kernel over every channel, and its output has the shape (filters, output height, output width), activated by the network's activation function.
+///
+/// Its [`LayerParameters`] are the weights laid out as (filters, channels, kernel height, kernel width), like PyTorch's `Conv2d.weight`, then one bias per filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conv2D {
+    window: Window,
+    filters: usize,
+    weights: Vec<f64>,
+    biases: Vec<f64>,
+}
+
+impl Conv2D {
+    pub fn new(
+        input_shape: (usize, usize, usize),
+        filters: usize,
+        kernel: (usize, usize),
+    ) -> Conv2D {
+        /*!
+        Creates a convolution with a stride of 1 and no padding, taking inputs of shape (channels, height, width).
+        ```
+        # use neurnet::*;
+        // A 1 channel 6x6 image, 4 filters, 2x2 max pooling and a dense classifier
+        let conv = Conv2D::new((1, 6, 6), 4, (3, 3)).with_padding((1, 1));
+        assert_eq!(conv.output_shape(), vec![4, 6, 6]);
+        let pool = MaxPool::new(&[4, 6, 6], &[2, 2]);
+        assert_eq!(pool.output_shape(), vec![4, 3, 3]);
+        let mut nn = Network::from_layers(
+            vec![Box::new(conv), Box::new(pool), Box::new(Flatten::new(&[4, 3, 3])), Box::new(Dense::new(2, 36))],
+            Activation::Tanh,
+        )
+        .unwrap();
+        nn.randomize((-0.5, 0.5), (-0.1, 0.1));
+        assert_eq!(nn.get_shape(), &vec![36, 144, 36, 36, 2]);
+
+        let image: Vec<f64> = (0..36).map(|i| ((i * 7) % 11) as f64 / 10.0).collect();
+        let errors = nn.gradient_check((&image, &[1.0, 0.0]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        let mut buf: Vec<u8> = vec![];
+        nn.write_to(&mut buf).unwrap();
+        let loaded = Network::read_from(buf.as_slice(), |x| x.tanh()).unwrap();
+        assert_eq!(loaded.pulse(image.clone()), nn.pulse(image));
+        ```
+        # Panics
+        <ul>
+        <li> If the kernel doesn't fit in the input.
+        <li> If a kernel size is 0.
+        </ul>
+        */
+        let window = Window::new(input_shape, kernel);
+        window.assert_valid();
+        Conv2D::from_window(window, filters)
+    }
+    fn from_window(window: Window, filters: usize) -> Conv2D {
+        Conv2D {
+            window,
+            filters,
+            weights: vec![1.0; filters * window.channels * window.kernel_len()],
+            biases: vec![0.0; filters],
+        }
+    }
+    pub fn with_stride(mut self, stride: (usize, usize)) -> Conv2D {
+        //! Sets how far the kernel moves between outputs, (down, across).
+        //! # Panics
+        //! <ul>
+        //! <li> If a stride is 0.
+        //! </ul>
+        self.window.stride = stride;
+        self.window.assert_valid();
+        self
+    }
+    pub fn with_padding(mut self, padding: (usize, usize)) -> Conv2D {
+        //! Sets how many zeros are added around the input, (above and below, left and right). A padding of half the kernel size with a stride of 1 keeps the output as large as the input.
+        self.window.padding = padding;
+        self.window.assert_valid();
+        self
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Conv2D> {
+        let setting = |name: &str| data.usize_setting(name);
+        let window = Window {
+            channels: setting("channels")?,
+            height: setting("height")?,
+            width: setting("width")?,
+            kernel: (setting("kernel_height")?, setting("kernel_width")?),
+            stride: (setting("stride_height")?, setting("stride_width")?),
+            padding: (setting("padding_height")?, setting("padding_width")?),
+        };
+        Conv2D::from_window_data(window, data)
+    }
+    fn from_window_data(window: Window, data: &LayerData) -> Option<Conv2D> {
+        if !window.is_valid() {
+            return None;
+        }
+        let (_, biases) = data.tensor("bias")?;
+        let (_, weights) = data.tensor("weight")?;
+        //The sizes come from the file, so the parameters have to be backed by its tensors before anything is allocated
+        let weights_len = biases
+            .len()
+            .checked_mul(window.channels)
+            .and_then(|len| len.checked_mul(window.kernel_len()));
+        if weights_len != Some(weights.len())
+            || biases.len().checked_mul(window.positions()).is_none()
+        {
+            return None;
+        }
+        let mut conv = Conv2D::from_window(window, biases.len());
+        conv.set_parameters(&LayerParameters {
+            weights: weights.clone(),
+            biases: biases.clone(),
+            rows: vec![],
+        })?;
+        Some(conv)
+    }
+    pub fn get_weight(
+        &self,
+        filter: usize,
+        channel: usize,
+        kernel_y: usize,
+        kernel_x: usize,
+    ) -> Option<&f64> {
+        if channel >= self.window.channels
+            || kernel_y >= self.window.kernel.0
+            || kernel_x >= self.window.kernel.1
+        {
+            return None;
+        }
+        self.weights.get(self.weight_index(
+            filter,
+            channel,
+            kernel_y * self.window.kernel.1 + kernel_x,
+        ))
+    }
+    pub fn get_bias(&self, filter: usize) -> Option<&f64> {
+        self.biases.get(filter)
+    }
+    pub fn filters(&self) -> usize {
+        self.filters
+    }
+    fn weight_index(&self, filter: usize, channel: usize, offset: usize) -> usize {
+        (filter * self.window.channels + channel) * self.window.kernel_len() + offset
+    }
+    fn for_each_connection(&self, mut f: impl FnMut(usize, usize, usize)) {
+        //Calls f with (output index, weight index, input index) for every connection that isn't to padding
+        let positions = self.window.positions();
+        for filter in 0..self.filters {
+            for position in 0..positions {
+                for channel in 0..self.window.channels {
+                    for offset in 0..self.window.kernel_len() {
+                        if let Some(input) = self.window.input_index(channel, position, offset) {
+                            f(
+                                filter * positions + position,
+                                self.weight_index(filter, channel, offset),
+                                input,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+    fn sums(&self, input: &[f64]) -> Vec<f64> {
+        let positions = self.window.positions();
+        let mut sums: Vec<f64> = (0..self.filters * positions)
+            .map(|output| self.biases[output / positions])
+            .collect();
+        self.for_each_connection(|output, weight, input_i| {
+            sums[output] += self.weights[weight] * input[input_i];
+        });
+        sums
+    }
+    fn conv_backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let positions = self.window.positions();
+        let deltas: Vec<f64> = output_grad
+            .iter()
+            .zip(self.sums(input))
+            .map(|(grad, z)| grad * activation.derivative(z))
+            .collect();
+        let mut input_grad = vec![0.0; input.len()];
+        let mut gradient = LayerParameters {
+            weights: vec![0.0; self.weights.len()],
+            biases: vec![0.0; self.filters],
+            rows: vec![],
+        };
+        for (output, delta) in deltas.iter().enumerate() {
+            gradient.biases[output / positions] += delta;
+        }
+        self.for_each_connection(|output, weight, input_i| {
+            gradient.weights[weight] += deltas[output] * input[input_i];
+            input_grad[input_i] += deltas[output] * self.weights[weight];
+        });
+        (input_grad, gradient)
+    }
+}
+
+impl Layer for Conv2D {
+    fn input_len(&self) -> usize {
+        self.window.input_len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![
+            self.filters,
+            self.window.out_height(),
+            self.window.out_width(),
+        ]
+    }
+    fn forward(&self, input: &[f64], activation: &Activation) -> Vec<f64> {
+        self.sums(input)
+            .into_iter()
+            .map(|z| activation.apply(z))
+            .collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        self.conv_backward(input, output_grad, activation)
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: self.weights.clone(),
+            biases: self.biases.clone(),
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        if parameters.weights.len() != self.weights.len()
+            || parameters.biases.len() != self.biases.len()
+        {
+            return None;
+        }
+        self.weights.copy_from_slice(&parameters.weights);
+        self.biases.copy_from_slice(&parameters.biases);
+        Some(())
+    }
+    fn serialize(&self) -> LayerData {
+        let w = &self.window;
+        LayerData::new("conv2d")
+            .with_setting("channels", w.channels as f64)
+            .with_setting("height", w.height as f64)
+            .with_setting("width", w.width as f64)
+            .with_setting("kernel_height", w.kernel.0 as f64)
+            .with_setting("kernel_width", w.kernel.1 as f64)
+            .with_setting("stride_height", w.stride.0 as f64)
+            .with_setting("stride_width", w.stride.1 as f64)
+            .with_setting("padding_height", w.padding.0 as f64)
+            .with_setting("padding_width", w.padding.1 as f64)
+            .with_tensor(
+                "weight",
+                vec![self.filters, w.channels, w.kernel.0, w.kernel.1],
+                self.weights.clone(),
+            )
+            .with_tensor("bias", vec![self.filters], self.biases.clone())
+    }
+}
+
+/// A 1D convolution over an input of shape (channels, length), such as a multi-channel time series, read from the flat input in row-major order. It works like [`Conv2D`] with a height of 1: its output has the shape (filters, output length), and its weights are laid out as (filters, channels, kernel size), like PyTorch's `Conv1d.weight`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conv1D {
+    conv: Conv2D,
+}
+
+impl Conv1D {
+    pub fn new(input_shape: (usize, usize), filters: usize, kernel: usize) -> Conv1D {
+        /*!
+        Creates a convolution with a stride of 1 and no padding, taking inputs of shape (channels, length).
+        ```
+        # use neurnet::*;
+        // 2 sensors over 12 time steps
+        let conv = Conv1D::new((2, 12), 3, 4).with_stride(2);
+        assert_eq!(conv.output_shape(), vec![3, 5]);
+        let mut nn = Network::from_layers(
+            vec![Box::new(conv), Box::new(GlobalAveragePool::new(&[3, 5])), Box::new(Dense::new(1, 3))],
+            Activation::Tanh,
+        )
+        .unwrap();
+        nn.randomize((-0.5, 0.5), (-0.1, 0.1));
+        let series: Vec<f64> = (0..24).map(|i| (i as f64 / 3.0).sin()).collect();
+        let errors = nn.gradient_check((&series, &[0.5]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // Sizes read from a file that would overflow are rejected
+        let mut data = Conv1D::new((1, 4), 1, 2).serialize();
+        data.settings.retain(|(name, _)| name != "padding");
+        assert!(data.with_setting("padding", 9223372036854775808.0).to_layer().is_none());
+        ```
+        # Panics
+        <ul>
+        <li> If the kernel doesn't fit in the input.
+        <li> If the kernel size is 0.
+        </ul>
+        */
+        Conv1D {
+            conv: Conv2D::new((input_shape.0, 1, input_shape.1), filters, (1, kernel)),
+        }
+    }
+    pub fn with_stride(self, stride: usize) -> Conv1D {
+        //! Sets how far the kernel moves between outputs.
+        //! # Panics
+        //! <ul>
+        //! <li> If the stride is 0.
+        //! </ul>
+        Conv1D {
+            conv: self.conv.with_stride((1, stride)),
+        }
+    }
+    pub fn with_padding(self, padding: usize) -> Conv1D {
+        //! Sets how many zeros are added to each end of the input.
+        Conv1D {
+            conv: self.conv.with_padding((0, padding)),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Conv1D> {
+        let setting = |name: &str| data.usize_setting(name);
+        let window = Window {
+            channels: setting("channels")?,
+            height: 1,
+            width: setting("length")?,
+            kernel: (1, setting("kernel")?),
+            stride: (1, setting("stride")?),
+            padding: (0, setting("padding")?),
+        };
+        Some(Conv1D {
+            conv: Conv2D::from_window_data(window, data)?,
+        })
+    }
+    pub fn get_weight(&self, filter: usize, channel: usize, kernel_i: usize) -> Option<&f64> {
+        self.conv.get_weight(filter, channel, 0, kernel_i)
+    }
+    pub fn get_bias(&self, filter: usize) -> Option<&f64> {
+        self.conv.get_bias(filter)
+    }
+    pub fn filters(&self) -> usize {
+        self.conv.filters
+    }
+}
+
+impl Layer for Conv1D {
+    fn input_len(&self) -> usize {
+        self.conv.input_len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.conv.filters, self.conv.window.out_width()]
+    }
+    fn forward(&self, input: &[f64], activation: &Activation) -> Vec<f64> {
+        self.conv.forward(input, activation)
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        self.conv.conv_backward(input, output_grad, activation)
+    }
+    fn parameters(&self) -> LayerParameters {
+        self.conv.parameters()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        self.conv.set_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        let w = &self.conv.window;
+        LayerData::new("conv1d")
+            .with_setting("channels", w.channels as f64)
+            .with_setting("length", w.width as f64)
+            .with_setting("kernel", w.kernel.1 as f64)
+            .with_setting("stride", w.stride.1 as f64)
+            .with_setting("padding", w.padding.1 as f64)
+            .with_tensor(
+                "weight",
+                vec![self.conv.filters, w.channels, w.kernel.1],
+                self.conv.weights.clone(),
+            )
+            .with_tensor("bias", vec![self.conv.filters], self.conv.biases.clone())
+    }
+}
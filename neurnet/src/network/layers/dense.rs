@@ -0,0 +1,177 @@
+use super::super::activation::Activation;
+use super::{Layer, LayerData, LayerParameters};
+
+/// A fully connected layer: every neuron takes a weighted sum of all the previous layer's outputs plus its bias, and runs it through the activation function.
+///
+/// Its [`LayerParameters`] are the weights neuron by neuron (so weight `neuron * prev_layer_len + prev_layer_neuron`), then one bias per neuron.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dense {
+    weights: Vec<Vec<f64>>, //[neuron in this layer] [connecting neuron in prev layer]
+    biases: Vec<f64>,
+}
+
+impl Dense {
+    pub fn new(layer_size: usize, prev_layer_size: usize) -> Dense {
+        Dense {
+            weights: vec![vec![1.0; prev_layer_size]; layer_size],
+            biases: vec![0.0; layer_size],
+        }
+    }
+    pub(crate) fn from_weights(weights: Vec<Vec<f64>>, biases: Vec<f64>) -> Option<Dense> {
+        let prev_layer_len = weights.first().map_or(0, |neuron| neuron.len());
+        if weights.len() != biases.len()
+            || weights.iter().any(|neuron| neuron.len() != prev_layer_len)
+        {
+            return None;
+        }
+        Some(Dense { weights, biases })
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Dense> {
+        let (weight_shape, weights) = data.tensor("weight")?;
+        let (bias_shape, biases) = data.tensor("bias")?;
+        match (weight_shape.as_slice(), bias_shape.as_slice()) {
+            ([neurons, prev_layer_len], [bias_len]) if neurons == bias_len => Dense::from_weights(
+                (0..*neurons)
+                    .map(|neuron| {
+                        weights[neuron * prev_layer_len..(neuron + 1) * prev_layer_len].to_vec()
+                    })
+                    .collect(),
+                biases.clone(),
+            ),
+            _ => None,
+        }
+    }
+    pub(crate) fn weights(&self) -> &Vec<Vec<f64>> {
+        &self.weights
+    }
+    pub(crate) fn biases(&self) -> &Vec<f64> {
+        &self.biases
+    }
+    fn sums(&self, input: &[f64]) -> Vec<f64> {
+        self.weights
+            .iter()
+            .zip(self.biases.iter())
+            .map(|(weights, bias)| {
+                //Sum the weighted inputs
+                weights
+                    .iter()
+                    .zip(input.iter())
+                    .map(|(w, x)| w * x)
+                    .sum::<f64>()
+                    + bias
+            })
+            .collect()
+    }
+    pub fn set_weight(&mut self, neuron: usize, prev_layer_neuron: usize, weight: f64) {
+        *(self
+            .weights
+            .get_mut(neuron)
+            .expect("A viable neuron ID")
+            .get_mut(prev_layer_neuron)
+            .expect("A viable ID to a neuron in the previous layer")) = weight;
+    }
+    pub fn get_weight(&self, neuron: usize, prev_layer_neuron: usize) -> Option<&f64> {
+        self.weights.get(neuron)?.get(prev_layer_neuron)
+    }
+    pub fn set_bias(&mut self, neuron: usize, bias: f64) {
+        *(self.biases.get_mut(neuron).expect("A valid neuron ID")) = bias;
+    }
+    pub fn get_bias(&self, neuron: usize) -> Option<&f64> {
+        self.biases.get(neuron)
+    }
+    pub fn len(&self) -> usize {
+        self.biases.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.biases.is_empty()
+    }
+    pub fn prev_layer_len(&self) -> usize {
+        self.weights.first().map_or(0, |neuron| neuron.len())
+    }
+}
+
+impl Layer for Dense {
+    fn input_len(&self) -> usize {
+        self.prev_layer_len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.len()]
+    }
+    fn forward(&self, input: &[f64], activation: &Activation) -> Vec<f64> {
+        self.sums(input)
+            .into_iter()
+            .map(|z| activation.apply(z))
+            .collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let deltas: Vec<f64> = output_grad
+            .iter()
+            .zip(self.sums(input))
+            .map(|(grad, z)| grad * activation.derivative(z))
+            .collect();
+        let input_grad = (0..input.len())
+            .map(|prev_i| {
+                self.weights
+                    .iter()
+                    .zip(deltas.iter())
+                    .map(|(weights, delta)| weights[prev_i] * delta)
+                    .sum()
+            })
+            .collect();
+        let gradient = LayerParameters {
+            weights: deltas
+                .iter()
+                .flat_map(|delta| input.iter().map(move |x| delta * x))
+                .collect(),
+            biases: deltas,
+            rows: vec![],
+        };
+        (input_grad, gradient)
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: self.weights.iter().flatten().copied().collect(),
+            biases: self.biases.clone(),
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        let prev_layer_len = self.prev_layer_len();
+        if parameters.weights.len() != self.len() * prev_layer_len
+            || parameters.biases.len() != self.len()
+        {
+            return None;
+        }
+        for (neuron, weights) in self.weights.iter_mut().enumerate() {
+            weights.copy_from_slice(
+                &parameters.weights[neuron * prev_layer_len..(neuron + 1) * prev_layer_len],
+            );
+        }
+        self.biases.copy_from_slice(&parameters.biases);
+        Some(())
+    }
+    fn serialize(&self) -> LayerData {
+        LayerData::new("dense")
+            .with_tensor(
+                "weight",
+                vec![self.len(), self.prev_layer_len()],
+                self.weights.iter().flatten().copied().collect(),
+            )
+            .with_tensor("bias", vec![self.len()], self.biases.clone())
+    }
+    fn apply_max_norm(&mut self, max_norm: f64) {
+        for weights in self.weights.iter_mut() {
+            let norm = weights.iter().map(|w| w * w).sum::<f64>().sqrt();
+            if norm > max_norm {
+                for weight in weights.iter_mut() {
+                    *weight *= max_norm / norm;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use super::super::super::files::{write_csv_file, write_csv_records, SaveMode};
+use super::super::activation::Activation;
+use super::{Layer, LayerData, LayerParameters};
+use rand::{Rng, RngCore};
+use std::io::Write;
+
+/// Looks up a trainable vector for every one of a fixed number of tokens, so categorical inputs don't have to be one-hot expanded. Each input is a token's index into the vocabulary, carried as an f64 and rounded to the nearest integer, and the output has the shape (tokens, embedding size), each token's vector one after another. The network's activation function is not applied, and the inputs get no gradient.
+///
+/// Its [`LayerParameters`] weights are the table of vectors, laid out as (vocabulary size, embedding size), and it has no biases. Its gradients are sparse, holding only the rows of the tokens that were looked up (see [`LayerParameters`]), so summing a batch's gradients and taking a gradient descent step cost as much as the tokens in the batch rather than the whole vocabulary. Regularizing its weights spreads the gradient over the whole table again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embedding {
+    vocab_size: usize,
+    dim: usize,
+    tokens: usize,
+    padding_index: Option<usize>,
+    table: Vec<f64>, //[token] [value]
+}
+
+impl Embedding {
+    pub fn new(vocab_size: usize, dim: usize, tokens: usize) -> Embedding {
+        /*!
+        Creates an embedding of a vocabulary of the given size into vectors of the given size, taking the given number of tokens at once. Every vector starts at zero, so it should be randomized before training.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(
+            vec![Box::new(Embedding::new(5, 3, 2)), Box::new(Dense::new(1, 6))],
+            Activation::Tanh,
+        )
+        .unwrap();
+        nn.randomize((-0.5, 0.5), (-0.5, 0.5));
+        let before = nn.get_layers()[0].parameters();
+        let errors = nn.gradient_check((&[1.0, 3.0], &[0.5]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // Training on tokens 1 and 3 leaves every other token's vector untouched
+        let ds = DataSet::new(vec![(vec![1.0, 3.0], vec![0.5]), (vec![3.0, 1.0], vec![-0.5])], vec![]);
+        nn.train_with_loss(&ds, &Loss::MeanSquaredError, 0.1, 2, 10);
+        let after = nn.get_layers()[0].parameters();
+        for token in 0..5 {
+            let changed = before.weights[token * 3..token * 3 + 3] != after.weights[token * 3..token * 3 + 3];
+            assert_eq!(changed, token == 1 || token == 3);
+        }
+        ```
+        # Panics
+        <ul>
+        <li> If the vector size is 0.
+        </ul>
+        */
+        assert!(dim > 0, "The vector size must be positive");
+        Embedding {
+            vocab_size,
+            dim,
+            tokens,
+            padding_index: None,
+            table: vec![0.0; vocab_size * dim],
+        }
+    }
+    pub fn with_padding_index(mut self, padding_index: usize) -> Embedding {
+        /*!
+        Sets a token, such as the one sequences are padded with, whose vector is kept at zero: it never gets a gradient and stays zero when the layer is randomized.
+        ```
+        # use neurnet::*;
+        let mut embedding = Embedding::new(4, 2, 3).with_padding_index(0);
+        embedding.randomize((-1.0, 1.0), (-1.0, 1.0));
+        let outputs = embedding.forward(&[2.0, 0.0, 0.0], &Activation::Identity);
+        assert_eq!(outputs[..2], embedding.embedding(2).unwrap()[..]);
+        assert_eq!(outputs[2..], [0.0; 4]);
+        let (_, gradient) = embedding.backward(&[0.0, 2.0, 0.0], &[1.0; 6], &Activation::Identity);
+        assert_eq!(gradient.rows, vec![2]);
+        assert_eq!(gradient.weights, vec![1.0, 1.0]);
+
+        // The padding index is saved with the layer
+        let loaded = embedding.serialize().to_layer().unwrap();
+        assert_eq!(loaded.downcast_ref::<Embedding>(), Some(&embedding));
+        let mut data = embedding.serialize();
+        data.settings.retain(|(name, _)| name != "padding_index");
+        assert!(data.with_setting("padding_index", 2.7).to_layer().is_none());
+        assert!(LayerData::new("embedding").with_setting("tokens", 1.0).with_tensor("weight", vec![3, 0], vec![]).to_layer().is_none());
+        ```
+        # Panics
+        <ul>
+        <li> If the index is outside the vocabulary.
+        </ul>
+        */
+        assert!(
+            padding_index < self.vocab_size,
+            "The padding index must be inside the vocabulary"
+        );
+        self.table[padding_index * self.dim..(padding_index + 1) * self.dim].fill(0.0);
+        self.padding_index = Some(padding_index);
+        self
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Embedding> {
+        let tokens = data.usize_setting("tokens")?;
+        let (shape, table) = data.tensor("weight")?;
+        let (vocab_size, dim) = match shape.as_slice() {
+            [vocab_size, dim] if *dim > 0 && tokens.checked_mul(*dim).is_some() => {
+                (*vocab_size, *dim)
+            }
+            _ => return None,
+        };
+        let mut embedding = Embedding::new(vocab_size, dim, tokens);
+        embedding.set_parameters(&LayerParameters {
+            weights: table.clone(),
+            biases: vec![],
+            rows: vec![],
+        })?;
+        if data.setting("padding_index").is_some() {
+            let padding_index = data.usize_setting("padding_index")?;
+            if padding_index >= vocab_size {
+                return None;
+            }
+            embedding.padding_index = Some(padding_index);
+        }
+        Some(embedding)
+    }
+    pub fn vocab_size(&self) -> usize {
+        self.vocab_size
+    }
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+    pub fn padding_index(&self) -> Option<usize> {
+        self.padding_index
+    }
+    pub fn embedding(&self, index: usize) -> Option<&[f64]> {
+        //! The vector of the token at the index, or None if it is outside the vocabulary.
+        self.table.chunks(self.dim).nth(index)
+    }
+    pub fn embeddings(&self) -> Vec<Vec<f64>> {
+        //! Every token's vector, in vocabulary order.
+        self.table
+            .chunks(self.dim)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+    pub fn nearest(&self, vector: &[f64], count: usize) -> Vec<(usize, f64)> {
+        /*!
+        Finds the tokens whose vectors are most similar to the vector given, by cosine similarity, and returns up to the count of them with their similarities, most similar first. Tokens with a zero vector, like the padding token, are skipped.
+        ```
+        # use neurnet::*;
+        let mut embedding = Embedding::new(4, 2, 1);
+        embedding.set_parameters(&LayerParameters {
+            weights: vec![1.0, 0.0, 0.0, 1.0, 2.0, 0.1, -1.0, 0.0],
+            biases: vec![],
+            rows: vec![],
+        });
+        let nearest = embedding.nearest(embedding.embedding(0).unwrap(), 2);
+        assert_eq!(nearest[0], (0, 1.0));
+        assert_eq!(nearest[1].0, 2);
+        ```
+        */
+        let norm = |vector: &[f64]| vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+        let vector_norm = norm(vector);
+        let mut similarities: Vec<(usize, f64)> = self
+            .table
+            .chunks(self.dim)
+            .enumerate()
+            .filter(|(_, row)| norm(row) > 0.0)
+            .map(|(index, row)| {
+                let dot: f64 = row.iter().zip(vector.iter()).map(|(a, b)| a * b).sum();
+                (index, dot / (norm(row) * vector_norm))
+            })
+            .collect();
+        similarities.sort_by(|a, b| b.1.total_cmp(&a.1));
+        similarities.truncate(count);
+        similarities
+    }
+    pub fn to_tsv(&self, path: &str) -> Option<()> {
+        //! Saves every token's vector to a tab separated file, one token per line in vocabulary order, the format embedding projectors like TensorBoard's read.
+        write_csv_file(path, SaveMode::Overwrite, '\t', &self.to_tsv_records())?;
+        Some(())
+    }
+    pub fn write_tsv<W: Write>(&self, writer: W) -> Option<()> {
+        /*!
+        Writes every token's vector as tab separated values to any writer. See [`Embedding::to_tsv`].
+        ```
+        # use neurnet::*;
+        let mut embedding = Embedding::new(2, 2, 1);
+        embedding.set_parameters(&LayerParameters { weights: vec![0.5, -1.0, 0.0, 2.0], biases: vec![], rows: vec![] });
+        let mut buf: Vec<u8> = vec![];
+        embedding.write_tsv(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "0.5\t-1\n0\t2\n");
+        ```
+        */
+        write_csv_records(writer, '\t', &self.to_tsv_records())
+    }
+    fn to_tsv_records(&self) -> Vec<Vec<String>> {
+        self.table
+            .chunks(self.dim)
+            .map(|row| row.iter().map(|x| x.to_string()).collect())
+            .collect()
+    }
+    fn index(&self, input: f64) -> usize {
+        let index = input.round();
+        assert!(
+            index >= 0.0 && (index as usize) < self.vocab_size,
+            "Embedding inputs must be indices inside the vocabulary, got {}",
+            input
+        );
+        index as usize
+    }
+}
+
+impl Layer for Embedding {
+    fn input_len(&self) -> usize {
+        self.tokens
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.tokens, self.dim]
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        //Panics if an input isn't an index inside the vocabulary
+        input
+            .iter()
+            .flat_map(|token| {
+                let index = self.index(*token);
+                self.table[index * self.dim..(index + 1) * self.dim].iter()
+            })
+            .copied()
+            .collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        //Only the rows of the tokens looked up are kept, in the order they first appear
+        let mut gradient = LayerParameters::default();
+        for (token, grad) in input.iter().zip(output_grad.chunks(self.dim)) {
+            let index = self.index(*token);
+            if Some(index) == self.padding_index {
+                continue;
+            }
+            gradient.add(&LayerParameters {
+                weights: grad.to_vec(),
+                biases: vec![],
+                rows: vec![index],
+            });
+        }
+        (vec![0.0; input.len()], gradient)
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: self.table.clone(),
+            biases: vec![],
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        if parameters.weights.len() != self.table.len() || !parameters.biases.is_empty() {
+            return None;
+        }
+        self.table.copy_from_slice(&parameters.weights);
+        Some(())
+    }
+    fn zero_gradient(&self) -> LayerParameters {
+        LayerParameters::default()
+    }
+    fn apply_gradient(&mut self, gradient: &LayerParameters, rate: f64) {
+        //A sparse gradient only reaches the rows of the tokens that were looked up, a dense one (as regularization leaves it) every row
+        if gradient.rows.is_empty() {
+            for (weight, grad) in self.table.iter_mut().zip(gradient.weights.iter()) {
+                *weight -= rate * grad;
+            }
+            return;
+        }
+        let row_len = gradient.weights.len() / gradient.rows.len();
+        for (row, grad) in gradient.rows.iter().zip(gradient.weights.chunks(row_len)) {
+            for (weight, grad) in self.table[row * self.dim..(row + 1) * self.dim]
+                .iter_mut()
+                .zip(grad.iter())
+            {
+                *weight -= rate * grad;
+            }
+        }
+    }
+    fn serialize(&self) -> LayerData {
+        let mut data = LayerData::new("embedding").with_setting("tokens", self.tokens as f64);
+        if let Some(padding_index) = self.padding_index {
+            data = data.with_setting("padding_index", padding_index as f64);
+        }
+        data.with_tensor(
+            "weight",
+            vec![self.vocab_size, self.dim],
+            self.table.clone(),
+        )
+    }
+    fn randomize_with(
+        &mut self,
+        weights_range: (f64, f64),
+        _biases_range: (f64, f64),
+        rng: &mut dyn RngCore,
+    ) {
+        for weight in self.table.iter_mut() {
+            *weight = weights_range.0 + rng.gen::<f64>() * (weights_range.1 - weights_range.0);
+        }
+        if let Some(padding_index) = self.padding_index {
+            self.table[padding_index * self.dim..(padding_index + 1) * self.dim].fill(0.0);
+        }
+    }
+}
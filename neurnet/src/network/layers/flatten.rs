@@ -0,0 +1,63 @@
+use super::super::activation::Activation;
+use super::{set_no_parameters, Layer, LayerData, LayerParameters};
+
+/// Turns a shaped input, like the (channels, height, width) output of a [`Conv2D`](super::Conv2D), into a plain list of values for a [`Dense`](super::Dense) layer. The values themselves pass through unchanged, since they are always stored flat; only the declared shape changes. The network's activation function is not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flatten {
+    input_shape: Vec<usize>,
+}
+
+impl Flatten {
+    pub fn new(input_shape: &[usize]) -> Flatten {
+        //! Creates a flatten layer for inputs of the given shape. See [`Conv2D::new`](super::Conv2D::new) for an example.
+        Flatten {
+            input_shape: input_shape.to_vec(),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Flatten> {
+        let mut input_shape = vec![];
+        while let Some(dim) = data.setting(&format!("dim{}", input_shape.len())) {
+            if dim < 0.0 || dim.fract() != 0.0 {
+                return None;
+            }
+            input_shape.push(dim as usize);
+        }
+        Some(Flatten { input_shape })
+    }
+    pub fn input_shape(&self) -> &Vec<usize> {
+        &self.input_shape
+    }
+}
+
+impl Layer for Flatten {
+    fn input_len(&self) -> usize {
+        self.input_shape.iter().product()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.input_len()]
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        input.to_vec()
+    }
+    fn backward(
+        &self,
+        _input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        (output_grad.to_vec(), LayerParameters::default())
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters::default()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        set_no_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        let mut data = LayerData::new("flatten");
+        for (i, dim) in self.input_shape.iter().enumerate() {
+            data = data.with_setting(&format!("dim{}", i), *dim as f64);
+        }
+        data
+    }
+}
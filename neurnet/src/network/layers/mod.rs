@@ -0,0 +1,432 @@
+use super::activation::Activation;
+use super::Network;
+use rand::{Rng, RngCore};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+pub mod attention;
+pub mod convolution;
+pub mod dense;
+pub mod embedding;
+pub mod flatten;
+pub mod normalization;
+pub mod pooling;
+pub mod recurrent;
+pub use attention::{MultiHeadAttention, PositionalEncoding, TransformerEncoderBlock};
+pub use convolution::{Conv1D, Conv2D};
+pub use dense::Dense;
+pub use embedding::Embedding;
+pub use flatten::Flatten;
+pub use normalization::{BatchNorm, LayerNorm};
+pub use pooling::{AvgPool, GlobalAveragePool, MaxPool};
+pub use recurrent::{Gru, Lstm, Rnn};
+
+/// A layer's trainable parameters, flattened into weights (which regularization treats as weights) and biases. The layout within each list is up to the layer, and a gradient returned by [`Layer::backward`] uses the same layout.
+///
+/// A gradient can also be sparse, holding only some rows of the weights: when `rows` isn't empty, `weights` holds just those rows one after another, all of the same length, and every other weight's gradient is zero. [`Embedding`] returns these, so a step only costs as much as the tokens it looked up. Parameters themselves are never sparse.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LayerParameters {
+    pub weights: Vec<f64>,
+    pub biases: Vec<f64>,
+    /// The rows of the weights a sparse gradient holds, or nothing if it is dense.
+    pub rows: Vec<usize>,
+}
+impl LayerParameters {
+    pub fn add(&mut self, other: &LayerParameters) {
+        /*!
+        Adds the other parameters (or gradient) value by value. A sparse gradient added to a dense one is added to the rows it holds, two sparse ones are merged row by row, and an empty gradient, like the one [`Embedding`] starts a batch from, takes on the other's values.
+        ```
+        # use neurnet::*;
+        let mut total = LayerParameters { weights: vec![1.0, 2.0], biases: vec![], rows: vec![3] };
+        total.add(&LayerParameters { weights: vec![1.0, 1.0, 5.0, 5.0], biases: vec![], rows: vec![3, 0] });
+        assert_eq!(total.weights, vec![2.0, 3.0, 5.0, 5.0]);
+        assert_eq!(total.rows, vec![3, 0]);
+        assert_eq!(total.to_dense(8).weights, vec![5.0, 5.0, 0.0, 0.0, 0.0, 0.0, 2.0, 3.0]);
+        ```
+        */
+        if self.weights.is_empty() && self.biases.is_empty() {
+            *self = other.clone();
+            return;
+        }
+        for (value, other) in self.biases.iter_mut().zip(other.biases.iter()) {
+            *value += other;
+        }
+        match (self.rows.is_empty(), other.rows.is_empty()) {
+            (true, true) => {
+                for (value, other) in self.weights.iter_mut().zip(other.weights.iter()) {
+                    *value += other;
+                }
+            }
+            (true, false) => {
+                let row_len = other.weights.len() / other.rows.len();
+                for (row, values) in other.rows.iter().zip(other.weights.chunks(row_len)) {
+                    let start = row * row_len;
+                    for (value, other) in self.weights[start..start + row_len]
+                        .iter_mut()
+                        .zip(values.iter())
+                    {
+                        *value += other;
+                    }
+                }
+            }
+            (false, true) => {
+                let mut weights = other.weights.clone();
+                let row_len = self.weights.len() / self.rows.len();
+                for (row, values) in self.rows.iter().zip(self.weights.chunks(row_len)) {
+                    for (value, own) in weights[row * row_len..].iter_mut().zip(values.iter()) {
+                        *value += own;
+                    }
+                }
+                self.weights = weights;
+                self.rows = vec![];
+            }
+            (false, false) => {
+                let row_len = self.weights.len() / self.rows.len();
+                let mut positions: HashMap<usize, usize> = self
+                    .rows
+                    .iter()
+                    .enumerate()
+                    .map(|(position, row)| (*row, position))
+                    .collect();
+                for (row, values) in other.rows.iter().zip(other.weights.chunks(row_len)) {
+                    match positions.get(row) {
+                        Some(position) => {
+                            let start = position * row_len;
+                            for (value, other) in self.weights[start..start + row_len]
+                                .iter_mut()
+                                .zip(values.iter())
+                            {
+                                *value += other;
+                            }
+                        }
+                        None => {
+                            positions.insert(*row, self.rows.len());
+                            self.rows.push(*row);
+                            self.weights.extend_from_slice(values);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    pub fn scale(&mut self, factor: f64) {
+        for value in self.weights.iter_mut().chain(self.biases.iter_mut()) {
+            *value *= factor;
+        }
+    }
+    pub fn to_dense(&self, weights_len: usize) -> LayerParameters {
+        //! A copy of a gradient laid out like the parameters, with the given amount of weights, which fills the rows a sparse (or empty) gradient doesn't hold with zeros.
+        if self.rows.is_empty() && self.weights.len() == weights_len {
+            return self.clone();
+        }
+        let mut dense = LayerParameters {
+            weights: vec![0.0; weights_len],
+            biases: vec![0.0; self.biases.len()],
+            rows: vec![],
+        };
+        dense.add(self);
+        dense
+    }
+}
+
+pub(crate) fn set_no_parameters(parameters: &LayerParameters) -> Option<()> {
+    //For layers without parameters, which can only be set to nothing
+    if !parameters.weights.is_empty() || !parameters.biases.is_empty() {
+        return None;
+    }
+    Some(())
+}
+
+/// Everything needed to rebuild a layer: its kind, its named settings and its named tensors as (name, shape, values in row-major order). It is what [`Layer::serialize`] returns and what .neur files and the `serde` feature store for each layer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerData {
+    pub kind: String,
+    pub settings: Vec<(String, f64)>,
+    pub tensors: Vec<(String, Vec<usize>, Vec<f64>)>,
+}
+
+impl LayerData {
+    pub fn new(kind: &str) -> LayerData {
+        LayerData {
+            kind: kind.to_string(),
+            settings: vec![],
+            tensors: vec![],
+        }
+    }
+    pub fn with_setting(mut self, name: &str, value: f64) -> LayerData {
+        self.settings.push((name.to_string(), value));
+        self
+    }
+    pub fn with_tensor(mut self, name: &str, shape: Vec<usize>, values: Vec<f64>) -> LayerData {
+        self.tensors.push((name.to_string(), shape, values));
+        self
+    }
+    pub fn setting(&self, name: &str) -> Option<f64> {
+        self.settings
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| *value)
+    }
+    pub fn usize_setting(&self, name: &str) -> Option<usize> {
+        //! Returns a setting that has to be a whole, non-negative number, like a size.
+        let value = self.setting(name)?;
+        if value < 0.0 || value.fract() != 0.0 {
+            return None;
+        }
+        Some(value as usize)
+    }
+    pub fn tensor(&self, name: &str) -> Option<(&Vec<usize>, &Vec<f64>)> {
+        //! Returns a tensor's shape and values, or None if it is missing, its shape's size overflows, or its values don't fill its shape.
+        let (_, shape, values) = self.tensors.iter().find(|(n, _, _)| n == name)?;
+        let len = shape
+            .iter()
+            .try_fold(1usize, |acc, dim| acc.checked_mul(*dim))?;
+        if len != values.len() {
+            return None;
+        }
+        Some((shape, values))
+    }
+    pub fn to_layer(&self) -> Option<Box<dyn Layer>> {
+        /*!
+        Rebuilds the layer this data was serialized from. Returns None if the kind is unknown or the settings or tensors don't describe a valid layer.
+        ```
+        # use neurnet::*;
+        let mut dense = Dense::new(2, 3);
+        dense.set_weight(1, 2, 0.5);
+        let layer = dense.serialize().to_layer().unwrap();
+        assert_eq!(layer.downcast_ref::<Dense>(), Some(&dense));
+
+        assert!(LayerData::new("dense").to_layer().is_none());
+        assert!(LayerData::new("unknown").to_layer().is_none());
+
+        // A shape too large to count is rejected rather than overflowing
+        let data = LayerData::new("dense").with_tensor("weight", vec![usize::MAX, 2], vec![1.0]);
+        assert!(data.tensor("weight").is_none());
+        ```
+        */
+        Some(match self.kind.as_str() {
+            "dense" => Box::new(Dense::from_data(self)?),
+            "batch_norm" => Box::new(BatchNorm::from_data(self)?),
+            "layer_norm" => Box::new(LayerNorm::from_data(self)?),
+            "conv1d" => Box::new(Conv1D::from_data(self)?),
+            "conv2d" => Box::new(Conv2D::from_data(self)?),
+            "max_pool" => Box::new(MaxPool::from_data(self)?),
+            "avg_pool" => Box::new(AvgPool::from_data(self)?),
+            "global_average_pool" => Box::new(GlobalAveragePool::from_data(self)?),
+            "flatten" => Box::new(Flatten::from_data(self)?),
+            "rnn" => Box::new(Rnn::from_data(self)?),
+            "lstm" => Box::new(Lstm::from_data(self)?),
+            "gru" => Box::new(Gru::from_data(self)?),
+            "embedding" => Box::new(Embedding::from_data(self)?),
+            "multi_head_attention" => Box::new(MultiHeadAttention::from_data(self)?),
+            "positional_encoding" => Box::new(PositionalEncoding::from_data(self)?),
+            "transformer_encoder_block" => Box::new(TransformerEncoderBlock::from_data(self)?),
+            _ => return None,
+        })
+    }
+}
+
+/// Lets [`Layer`] trait objects be cloned and downcast. It is implemented for every layer that is `Clone`, so it never has to be implemented by hand.
+pub trait LayerBase {
+    fn box_clone(&self) -> Box<dyn Layer>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+impl<T: Layer + Clone + 'static> LayerBase for T {
+    fn box_clone(&self) -> Box<dyn Layer> {
+        Box::new(self.clone())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// One layer of a [`Network`](super::Network), which turns the previous layer's outputs into its own. [`Dense`] is the fully connected layer networks are built from by default.
+///
+/// A layer takes and returns flat lists of values. Layers that work on images or sequences read them in row-major order, with [`Layer::output_shape`] telling the next layer how the values are laid out.
+pub trait Layer: fmt::Debug + LayerBase {
+    fn input_len(&self) -> usize;
+    fn output_shape(&self) -> Vec<usize>;
+    fn output_len(&self) -> usize {
+        self.output_shape().iter().product()
+    }
+    /// Runs the input through the layer. The activation is the network's, which a layer may ignore if it isn't meant to be activated.
+    fn forward(&self, input: &[f64], activation: &Activation) -> Vec<f64>;
+    /// Takes the layer's input and the gradient of the loss with respect to the layer's outputs, and returns the gradient with respect to the input and to the layer's parameters.
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters);
+    /// Runs a batch of inputs through the layer while training. Layers whose outputs depend on the whole batch, like [`BatchNorm`], override this; by default every input goes through [`Layer::forward`].
+    fn forward_batch(&self, inputs: &[Vec<f64>], activation: &Activation) -> Vec<Vec<f64>> {
+        inputs
+            .iter()
+            .map(|input| self.forward(input, activation))
+            .collect()
+    }
+    /// The backward pass of [`Layer::forward_batch`]: returns the gradient with respect to every input, and the parameters' gradient summed over the batch.
+    fn backward_batch(
+        &self,
+        inputs: &[Vec<f64>],
+        output_grads: &[Vec<f64>],
+        activation: &Activation,
+    ) -> (Vec<Vec<f64>>, LayerParameters) {
+        let mut input_grads: Vec<Vec<f64>> = vec![];
+        let mut total: Option<LayerParameters> = None;
+        for (input, output_grad) in inputs.iter().zip(output_grads.iter()) {
+            let (input_grad, gradient) = self.backward(input, output_grad, activation);
+            input_grads.push(input_grad);
+            match &mut total {
+                Some(total) => total.add(&gradient),
+                None => total = Some(gradient),
+            }
+        }
+        (input_grads, total.unwrap_or_else(|| self.zero_gradient()))
+    }
+    /// The length of the state a recurrent layer, like [`Lstm`], carries from one step of a sequence to the next. Layers that treat every step on its own have none.
+    fn state_len(&self) -> usize {
+        0
+    }
+    /// Runs one step of a sequence through the layer, given the state the previous step left (all zeros before the first step), and returns the outputs and the new state. Layers without state run the step through [`Layer::forward`].
+    fn forward_step(
+        &self,
+        input: &[f64],
+        _state: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>) {
+        (self.forward(input, activation), vec![])
+    }
+    /// The backward pass of [`Layer::forward_step`]: takes the gradient of the loss with respect to the step's outputs and to the new state, and returns the gradient with respect to the input, to the previous state and to the layer's parameters.
+    fn backward_step(
+        &self,
+        input: &[f64],
+        _state: &[f64],
+        output_grad: &[f64],
+        _state_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>, LayerParameters) {
+        let (input_grad, gradient) = self.backward(input, output_grad, activation);
+        (input_grad, vec![], gradient)
+    }
+    /// Updates whatever the layer keeps track of across training batches, like [`BatchNorm`]'s running statistics, from the inputs of one batch. Called once per batch by [`Network::train_with_loss`].
+    fn update_statistics(&mut self, _inputs: &[Vec<f64>]) {}
+    fn parameters(&self) -> LayerParameters;
+    /// Replaces the layer's parameters. Returns None, leaving the layer unchanged, if they are laid out differently from [`Layer::parameters`].
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()>;
+    /// A zero gradient for the gradients of a batch to be added to. By default it is laid out like [`Layer::parameters`]; layers with sparse gradients, like [`Embedding`], return an empty one instead.
+    fn zero_gradient(&self) -> LayerParameters {
+        let mut zeros = self.parameters();
+        zeros.scale(0.0);
+        zeros
+    }
+    /// Takes a gradient descent step, moving every parameter against its gradient, laid out like [`Layer::parameters`], scaled by the rate. A sparse gradient is spread out over every parameter first, so layers that return them should override this.
+    fn apply_gradient(&mut self, gradient: &LayerParameters, rate: f64) {
+        let mut parameters = self.parameters();
+        let gradient = gradient.to_dense(parameters.weights.len());
+        for (weight, grad) in parameters.weights.iter_mut().zip(gradient.weights.iter()) {
+            *weight -= rate * grad;
+        }
+        for (bias, grad) in parameters.biases.iter_mut().zip(gradient.biases.iter()) {
+            *bias -= rate * grad;
+        }
+        self.set_parameters(&parameters);
+    }
+    fn serialize(&self) -> LayerData;
+    /// Scales down the weights so they satisfy a [`Regularization::max_norm`](crate::Regularization::max_norm) constraint. Does nothing for layers without such a constraint.
+    fn apply_max_norm(&mut self, _max_norm: f64) {}
+    /// Sets the weights and biases to random values in the given ranges. Layers change how they are randomized through [`Layer::randomize_with`].
+    fn randomize(&mut self, weights_range: (f64, f64), biases_range: (f64, f64)) {
+        self.randomize_with(weights_range, biases_range, &mut rand::thread_rng());
+    }
+    /// Like [`Layer::randomize`], drawing from the given random number generator. Layers whose parameters shouldn't be random, like [`BatchNorm`], reset them instead.
+    fn randomize_with(
+        &mut self,
+        weights_range: (f64, f64),
+        biases_range: (f64, f64),
+        rng: &mut dyn RngCore,
+    ) {
+        let mut rand_float = |range: (f64, f64)| {
+            let mut buf: f64 = rng.gen();
+            buf *= range.1 - range.0;
+            buf += range.0;
+            buf
+        };
+        let mut parameters = self.parameters();
+        for weight in parameters.weights.iter_mut() {
+            *weight = rand_float(weights_range);
+        }
+        for bias in parameters.biases.iter_mut() {
+            *bias = rand_float(biases_range);
+        }
+        self.set_parameters(&parameters);
+    }
+}
+
+impl dyn Layer {
+    pub fn downcast_ref<T: Layer + 'static>(&self) -> Option<&T> {
+        //! Returns the layer as its concrete type, such as [`Dense`], if it is one.
+        self.as_any().downcast_ref()
+    }
+    pub fn downcast_mut<T: Layer + 'static>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+}
+
+impl Clone for Box<dyn Layer> {
+    fn clone(&self) -> Self {
+        (**self).box_clone()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Box<dyn Layer> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&Layer::serialize(&**self), serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Box<dyn Layer> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = <LayerData as serde::Deserialize>::deserialize(deserializer)?;
+        data.to_layer()
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid {} layer", data.kind)))
+    }
+}
+
+impl Network {
+    pub(crate) fn layer_tensors(&self) -> Vec<(String, Vec<usize>, Vec<f64>)> {
+        //Every layer's tensors, named `layers.{i}.{tensor}`
+        let mut buf = vec![];
+        for (i, layer) in self.layers.iter().enumerate() {
+            for (name, shape, values) in layer.serialize().tensors {
+                buf.push((format!("layers.{}.{}", i, name), shape, values));
+            }
+        }
+        buf
+    }
+    pub(crate) fn update_statistics(&mut self, layer_inputs: &[Vec<Vec<f64>>]) {
+        for (layer, inputs) in self.layers.iter_mut().zip(layer_inputs.iter()) {
+            layer.update_statistics(inputs);
+        }
+    }
+    pub(crate) fn set_layer_tensors(&mut self, values: Vec<Vec<f64>>) -> Option<()> {
+        //Takes new values for every tensor in the order of layer_tensors, and only changes the network if every layer can be rebuilt from them
+        let mut values = values.into_iter();
+        let mut layers: Vec<Box<dyn Layer>> = vec![];
+        for layer in self.layers.iter() {
+            let mut data = layer.serialize();
+            for tensor in data.tensors.iter_mut() {
+                tensor.2 = values.next()?;
+            }
+            layers.push(data.to_layer()?);
+        }
+        self.layers = layers;
+        Some(())
+    }
+}
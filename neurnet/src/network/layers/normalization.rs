@@ -0,0 +1,442 @@
+use super::super::activation::Activation;
+use super::{Layer, LayerData, LayerParameters};
+use rand::RngCore;
+
+const EPSILON: f64 = 1e-5;
+
+fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    (mean, variance)
+}
+fn normalized_backward(normalized: &[f64], normalized_grad: &[f64], inverse_std: f64) -> Vec<f64> {
+    //The gradient of (x - mean(x)) / std(x) for values that were normalized together
+    let n = normalized.len() as f64;
+    let grad_sum: f64 = normalized_grad.iter().sum();
+    let grad_dot: f64 = normalized_grad
+        .iter()
+        .zip(normalized.iter())
+        .map(|(grad, x)| grad * x)
+        .sum();
+    normalized_grad
+        .iter()
+        .zip(normalized.iter())
+        .map(|(grad, x)| inverse_std / n * (n * grad - grad_sum - x * grad_dot))
+        .collect()
+}
+fn affine_gradient(normalized: &[f64], output_grad: &[f64]) -> LayerParameters {
+    LayerParameters {
+        weights: output_grad
+            .iter()
+            .zip(normalized.iter())
+            .map(|(grad, x)| grad * x)
+            .collect(),
+        biases: output_grad.to_vec(),
+        rows: vec![],
+    }
+}
+fn set_affine(scale: &mut [f64], shift: &mut [f64], parameters: &LayerParameters) -> Option<()> {
+    if parameters.weights.len() != scale.len() || parameters.biases.len() != shift.len() {
+        return None;
+    }
+    scale.copy_from_slice(&parameters.weights);
+    shift.copy_from_slice(&parameters.biases);
+    Some(())
+}
+
+/// Batch normalization: normalizes each input by the mean and variance it has across the training batch, then applies a learnable scale and shift (its [`LayerParameters`] weights and biases, starting at 1 and 0).
+///
+/// While training with [`Network::train_with_loss`](crate::Network::train_with_loss) the statistics of each batch are used, and running averages of them are kept. Everywhere else, including [`Network::pulse`](crate::Network::pulse), the running averages are used instead, so the output for a point doesn't depend on the other points. The network's activation function is not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchNorm {
+    scale: Vec<f64>,
+    shift: Vec<f64>,
+    running_mean: Vec<f64>,
+    running_variance: Vec<f64>,
+    momentum: f64,
+    epsilon: f64,
+}
+
+impl BatchNorm {
+    pub fn new(len: usize) -> BatchNorm {
+        /*!
+        Creates a batch normalization layer for the given amount of inputs, with a momentum of 0.1: every training batch moves the running statistics 10% of the way towards its own.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(
+            vec![
+                Box::new(Dense::new(4, 2)),
+                Box::new(BatchNorm::new(4)),
+                Box::new(Dense::new(1, 4)),
+            ],
+            Activation::Tanh,
+        )
+        .unwrap();
+        // Seeded, so the example trains the same way every run
+        nn.randomize_seeded((-1.0, 1.0), (0.0, 0.0), 1);
+
+        // Training uses the batch's statistics, which gradient checks cover too
+        let points: Vec<(&[f64], &[f64])> = vec![
+            (&[0.5, -0.2], &[0.3]),
+            (&[-0.4, 0.9], &[-0.6]),
+            (&[0.1, 0.3], &[0.1]),
+        ];
+        let errors = nn.batch_gradient_check(&points, &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+        let errors = nn.gradient_check(points[0], &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        let ds = DataSet::gen_from_fn(
+            |x| vec![0.5 * x[0] - 0.25 * x[1]],
+            (-10..10).map(|x| vec![x as f64 / 10.0, (x * x) as f64 / 100.0]).collect(),
+            (-5..5).map(|x| vec![x as f64 / 5.0, (x * x) as f64 / 25.0]).collect(),
+        );
+        let before = nn.test(&ds)[0];
+        nn.train_with_loss(&ds, &Loss::MeanSquaredError, 0.05, 5, 100);
+        assert!(nn.test(&ds)[0] < before);
+
+        // The running statistics are saved with the network
+        let norm = nn.get_layers()[1].downcast_ref::<BatchNorm>().unwrap();
+        assert!(norm.running_mean().iter().all(|mean| *mean != 0.0));
+        let mut buf: Vec<u8> = vec![];
+        nn.write_to(&mut buf).unwrap();
+        let loaded = Network::read_from(buf.as_slice(), |x| x.tanh()).unwrap();
+        assert_eq!(loaded.get_layers()[1].downcast_ref::<BatchNorm>(), Some(norm));
+        assert_eq!(loaded.pulse(vec![0.2, 0.04]), nn.pulse(vec![0.2, 0.04]));
+        ```
+        */
+        BatchNorm {
+            scale: vec![1.0; len],
+            shift: vec![0.0; len],
+            running_mean: vec![0.0; len],
+            running_variance: vec![1.0; len],
+            momentum: 0.1,
+            epsilon: EPSILON,
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<BatchNorm> {
+        let (_, scale) = data.tensor("weight")?;
+        let (_, shift) = data.tensor("bias")?;
+        let (_, running_mean) = data.tensor("running_mean")?;
+        let (_, running_variance) = data.tensor("running_var")?;
+        if [shift.len(), running_mean.len(), running_variance.len()]
+            .iter()
+            .any(|len| *len != scale.len())
+        {
+            return None;
+        }
+        Some(BatchNorm {
+            scale: scale.clone(),
+            shift: shift.clone(),
+            running_mean: running_mean.clone(),
+            running_variance: running_variance.clone(),
+            momentum: data.setting("momentum")?,
+            epsilon: data.setting("epsilon")?,
+        })
+    }
+    pub fn get_momentum(&self) -> f64 {
+        self.momentum
+    }
+    pub fn set_momentum(&mut self, momentum: f64) {
+        //! Sets how far each training batch moves the running statistics towards its own, from 0 (not at all) to 1 (all the way).
+        self.momentum = momentum;
+    }
+    pub fn running_mean(&self) -> &Vec<f64> {
+        &self.running_mean
+    }
+    pub fn running_variance(&self) -> &Vec<f64> {
+        &self.running_variance
+    }
+    pub fn len(&self) -> usize {
+        self.scale.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scale.is_empty()
+    }
+    fn inverse_std(&self, feature: usize) -> f64 {
+        1.0 / (self.running_variance[feature] + self.epsilon).sqrt()
+    }
+    fn normalize_batch(&self, inputs: &[Vec<f64>]) -> (Vec<Vec<f64>>, Vec<f64>) {
+        //Returns every input normalized by the batch's statistics, and each feature's inverse standard deviation
+        let mut normalized = vec![vec![0.0; self.len()]; inputs.len()];
+        let mut inverse_stds = vec![];
+        for feature in 0..self.len() {
+            let column: Vec<f64> = inputs.iter().map(|input| input[feature]).collect();
+            let (mean, variance) = mean_and_variance(&column);
+            let inverse_std = 1.0 / (variance + self.epsilon).sqrt();
+            for (point, x) in column.iter().enumerate() {
+                normalized[point][feature] = (x - mean) * inverse_std;
+            }
+            inverse_stds.push(inverse_std);
+        }
+        (normalized, inverse_stds)
+    }
+}
+
+impl Layer for BatchNorm {
+    fn input_len(&self) -> usize {
+        self.len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.len()]
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        (0..self.len())
+            .map(|i| {
+                (input[i] - self.running_mean[i]) * self.inverse_std(i) * self.scale[i]
+                    + self.shift[i]
+            })
+            .collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let normalized: Vec<f64> = (0..self.len())
+            .map(|i| (input[i] - self.running_mean[i]) * self.inverse_std(i))
+            .collect();
+        let input_grad = (0..self.len())
+            .map(|i| output_grad[i] * self.scale[i] * self.inverse_std(i))
+            .collect();
+        (input_grad, affine_gradient(&normalized, output_grad))
+    }
+    fn forward_batch(&self, inputs: &[Vec<f64>], _activation: &Activation) -> Vec<Vec<f64>> {
+        self.normalize_batch(inputs)
+            .0
+            .into_iter()
+            .map(|normalized| {
+                normalized
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| x * self.scale[i] + self.shift[i])
+                    .collect()
+            })
+            .collect()
+    }
+    fn backward_batch(
+        &self,
+        inputs: &[Vec<f64>],
+        output_grads: &[Vec<f64>],
+        _activation: &Activation,
+    ) -> (Vec<Vec<f64>>, LayerParameters) {
+        let (normalized, inverse_stds) = self.normalize_batch(inputs);
+        let mut input_grads = vec![vec![0.0; self.len()]; inputs.len()];
+        let mut gradient = LayerParameters {
+            weights: vec![0.0; self.len()],
+            biases: vec![0.0; self.len()],
+            rows: vec![],
+        };
+        for feature in 0..self.len() {
+            let column: Vec<f64> = normalized.iter().map(|point| point[feature]).collect();
+            let grads: Vec<f64> = output_grads.iter().map(|grad| grad[feature]).collect();
+            let feature_gradient = affine_gradient(&column, &grads);
+            gradient.weights[feature] = feature_gradient.weights.iter().sum();
+            gradient.biases[feature] = feature_gradient.biases.iter().sum();
+            let normalized_grads: Vec<f64> = grads
+                .iter()
+                .map(|grad| grad * self.scale[feature])
+                .collect();
+            for (point, grad) in
+                normalized_backward(&column, &normalized_grads, inverse_stds[feature])
+                    .into_iter()
+                    .enumerate()
+            {
+                input_grads[point][feature] = grad;
+            }
+        }
+        (input_grads, gradient)
+    }
+    fn update_statistics(&mut self, inputs: &[Vec<f64>]) {
+        if inputs.is_empty() {
+            return;
+        }
+        let n = inputs.len() as f64;
+        for feature in 0..self.len() {
+            let column: Vec<f64> = inputs.iter().map(|input| input[feature]).collect();
+            let (mean, variance) = mean_and_variance(&column);
+            //The running variance estimates the population's, so it uses the unbiased batch variance
+            let variance = match inputs.len() {
+                1 => variance,
+                _ => variance * n / (n - 1.0),
+            };
+            self.running_mean[feature] += self.momentum * (mean - self.running_mean[feature]);
+            self.running_variance[feature] +=
+                self.momentum * (variance - self.running_variance[feature]);
+        }
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: self.scale.clone(),
+            biases: self.shift.clone(),
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        set_affine(&mut self.scale, &mut self.shift, parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        LayerData::new("batch_norm")
+            .with_setting("momentum", self.momentum)
+            .with_setting("epsilon", self.epsilon)
+            .with_tensor("weight", vec![self.len()], self.scale.clone())
+            .with_tensor("bias", vec![self.len()], self.shift.clone())
+            .with_tensor("running_mean", vec![self.len()], self.running_mean.clone())
+            .with_tensor(
+                "running_var",
+                vec![self.len()],
+                self.running_variance.clone(),
+            )
+    }
+    fn randomize_with(
+        &mut self,
+        _weights_range: (f64, f64),
+        _biases_range: (f64, f64),
+        _rng: &mut dyn RngCore,
+    ) {
+        //Random scales and shifts would undo the normalization, so the layer goes back to a scale of 1, a shift of 0 and fresh running statistics, keeping its momentum and epsilon
+        *self = BatchNorm {
+            momentum: self.momentum,
+            epsilon: self.epsilon,
+            ..BatchNorm::new(self.len())
+        };
+    }
+}
+
+/// Layer normalization: normalizes each point's inputs by their own mean and variance, then applies a learnable scale and shift (its [`LayerParameters`] weights and biases, starting at 1 and 0).
+///
+/// Unlike [`BatchNorm`] it works the same while training and afterwards, and doesn't depend on the batch size. The network's activation function is not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerNorm {
+    scale: Vec<f64>,
+    shift: Vec<f64>,
+    epsilon: f64,
+}
+
+impl LayerNorm {
+    pub fn new(len: usize) -> LayerNorm {
+        /*!
+        Creates a layer normalization layer for the given amount of inputs.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(
+            vec![
+                Box::new(Dense::new(4, 2)),
+                Box::new(LayerNorm::new(4)),
+                Box::new(Dense::new(2, 4)),
+            ],
+            Activation::Tanh,
+        )
+        .unwrap();
+        nn.randomize((-1.0, 1.0), (-1.0, 1.0));
+        let errors = nn.gradient_check((&[0.5, -0.2], &[0.3, 0.7]), &Loss::Huber(0.1), 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // A single point's outputs have a mean of 0 and a variance of 1 before the scale and shift
+        let norm = LayerNorm::new(3);
+        let out = norm.forward(&[1.0, 2.0, 6.0], &Activation::Identity);
+        assert!(out.iter().sum::<f64>().abs() < 1e-12);
+        assert!((out.iter().map(|x| x * x).sum::<f64>() / 3.0 - 1.0).abs() < 1e-4);
+
+        let mut buf: Vec<u8> = vec![];
+        nn.write_to(&mut buf).unwrap();
+        let loaded = Network::read_from(buf.as_slice(), |x| x.tanh()).unwrap();
+        assert_eq!(loaded.pulse(vec![0.1, 0.9]), nn.pulse(vec![0.1, 0.9]));
+        ```
+        */
+        LayerNorm {
+            scale: vec![1.0; len],
+            shift: vec![0.0; len],
+            epsilon: EPSILON,
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<LayerNorm> {
+        let (_, scale) = data.tensor("weight")?;
+        let (_, shift) = data.tensor("bias")?;
+        if shift.len() != scale.len() {
+            return None;
+        }
+        Some(LayerNorm {
+            scale: scale.clone(),
+            shift: shift.clone(),
+            epsilon: data.setting("epsilon")?,
+        })
+    }
+    pub fn len(&self) -> usize {
+        self.scale.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scale.is_empty()
+    }
+    fn normalize(&self, input: &[f64]) -> (Vec<f64>, f64) {
+        let (mean, variance) = mean_and_variance(input);
+        let inverse_std = 1.0 / (variance + self.epsilon).sqrt();
+        (
+            input.iter().map(|x| (x - mean) * inverse_std).collect(),
+            inverse_std,
+        )
+    }
+}
+
+impl Layer for LayerNorm {
+    fn input_len(&self) -> usize {
+        self.len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.len()]
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        self.normalize(input)
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, x)| x * self.scale[i] + self.shift[i])
+            .collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let (normalized, inverse_std) = self.normalize(input);
+        let normalized_grad: Vec<f64> = output_grad
+            .iter()
+            .zip(self.scale.iter())
+            .map(|(grad, scale)| grad * scale)
+            .collect();
+        (
+            normalized_backward(&normalized, &normalized_grad, inverse_std),
+            affine_gradient(&normalized, output_grad),
+        )
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: self.scale.clone(),
+            biases: self.shift.clone(),
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        set_affine(&mut self.scale, &mut self.shift, parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        LayerData::new("layer_norm")
+            .with_setting("epsilon", self.epsilon)
+            .with_tensor("weight", vec![self.len()], self.scale.clone())
+            .with_tensor("bias", vec![self.len()], self.shift.clone())
+    }
+    fn randomize_with(
+        &mut self,
+        _weights_range: (f64, f64),
+        _biases_range: (f64, f64),
+        _rng: &mut dyn RngCore,
+    ) {
+        //Training should start from each point's inputs as they were normalized, so the scale and shift go back to 1 and 0 rather than random values
+        *self = LayerNorm {
+            epsilon: self.epsilon,
+            ..LayerNorm::new(self.len())
+        };
+    }
+}
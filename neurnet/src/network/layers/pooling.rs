@@ -0,0 +1,361 @@
+use super::super::activation::Activation;
+use super::convolution::Window;
+use super::{set_no_parameters, Layer, LayerData, LayerParameters};
+
+/// The window and dimensionality shared by the pooling layers.
+#[derive(Debug, Clone, PartialEq)]
+struct Pool {
+    window: Window,
+    one_d: bool,
+}
+
+impl Pool {
+    fn new(input_shape: &[usize], pool: &[usize]) -> Pool {
+        let (input, kernel) = match (input_shape, pool) {
+            ([channels, length], [kernel]) => ((*channels, 1, *length), (1, *kernel)),
+            ([channels, height, width], [kernel_height, kernel_width]) => (
+                (*channels, *height, *width),
+                (*kernel_height, *kernel_width),
+            ),
+            _ => panic!("Pooling takes a (channels, length) input with one pool size, or a (channels, height, width) input with two"),
+        };
+        let mut window = Window::new(input, kernel);
+        window.stride = kernel;
+        window.assert_valid();
+        Pool {
+            window,
+            one_d: input_shape.len() == 2,
+        }
+    }
+    fn with_stride(mut self, stride: &[usize]) -> Pool {
+        self.window.stride = match (self.one_d, stride) {
+            (true, [stride]) => (1, *stride),
+            (false, [stride_height, stride_width]) => (*stride_height, *stride_width),
+            _ => panic!("There must be one stride for each dimension of the pool"),
+        };
+        self.window.assert_valid();
+        self
+    }
+    fn from_data(data: &LayerData) -> Option<Pool> {
+        let setting = |name: &str| data.usize_setting(name);
+        let one_d = data.setting("length").is_some();
+        let window = match one_d {
+            true => Window {
+                channels: setting("channels")?,
+                height: 1,
+                width: setting("length")?,
+                kernel: (1, setting("kernel")?),
+                stride: (1, setting("stride")?),
+                padding: (0, 0),
+            },
+            false => Window {
+                channels: setting("channels")?,
+                height: setting("height")?,
+                width: setting("width")?,
+                kernel: (setting("kernel_height")?, setting("kernel_width")?),
+                stride: (setting("stride_height")?, setting("stride_width")?),
+                padding: (0, 0),
+            },
+        };
+        if !window.is_valid() {
+            return None;
+        }
+        Some(Pool { window, one_d })
+    }
+    fn serialize(&self, kind: &str) -> LayerData {
+        let w = &self.window;
+        let data = LayerData::new(kind).with_setting("channels", w.channels as f64);
+        match self.one_d {
+            true => data
+                .with_setting("length", w.width as f64)
+                .with_setting("kernel", w.kernel.1 as f64)
+                .with_setting("stride", w.stride.1 as f64),
+            false => data
+                .with_setting("height", w.height as f64)
+                .with_setting("width", w.width as f64)
+                .with_setting("kernel_height", w.kernel.0 as f64)
+                .with_setting("kernel_width", w.kernel.1 as f64)
+                .with_setting("stride_height", w.stride.0 as f64)
+                .with_setting("stride_width", w.stride.1 as f64),
+        }
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        match self.one_d {
+            true => vec![self.window.channels, self.window.out_width()],
+            false => vec![
+                self.window.channels,
+                self.window.out_height(),
+                self.window.out_width(),
+            ],
+        }
+    }
+    fn windows(&self) -> Vec<Vec<usize>> {
+        //The input indices under every output's window, in output order
+        let w = &self.window;
+        let mut buf = vec![];
+        for channel in 0..w.channels {
+            for position in 0..w.positions() {
+                buf.push(
+                    (0..w.kernel_len())
+                        .filter_map(|offset| w.input_index(channel, position, offset))
+                        .collect(),
+                );
+            }
+        }
+        buf
+    }
+}
+
+/// Max pooling over a (channels, length) or (channels, height, width) input: every output is the largest input under its window, taken separately for each channel. By default the windows don't overlap. The network's activation function is not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaxPool {
+    pool: Pool,
+}
+
+impl MaxPool {
+    pub fn new(input_shape: &[usize], pool: &[usize]) -> MaxPool {
+        /*!
+        Creates a max pooling layer whose stride is the pool size. The input shape is (channels, length) with one pool size, or (channels, height, width) with a pool height and width.
+        ```
+        # use neurnet::*;
+        let pool = MaxPool::new(&[1, 2, 4], &[2, 2]);
+        assert_eq!(pool.output_shape(), vec![1, 1, 2]);
+        assert_eq!(
+            pool.forward(&[1.0, 5.0, 2.0, 0.0, 3.0, 4.0, 8.0, 7.0], &Activation::Identity),
+            vec![5.0, 8.0]
+        );
+        // Overlapping windows with a stride of 1
+        let pool = MaxPool::new(&[1, 4], &[2]).with_stride(&[1]);
+        assert_eq!(pool.forward(&[1.0, 3.0, 2.0, 0.0], &Activation::Identity), vec![3.0, 3.0, 2.0]);
+        ```
+        # Panics
+        <ul>
+        <li> If the input shape doesn't have 2 or 3 dimensions, or there isn't a pool size for each dimension after the channels.
+        <li> If the pool doesn't fit in the input, or a pool size is 0.
+        </ul>
+        */
+        MaxPool {
+            pool: Pool::new(input_shape, pool),
+        }
+    }
+    pub fn with_stride(self, stride: &[usize]) -> MaxPool {
+        //! Sets how far the window moves between outputs, with a stride for each dimension of the pool.
+        //! # Panics
+        //! <ul>
+        //! <li> If the number of strides doesn't match the pool, or a stride is 0.
+        //! </ul>
+        MaxPool {
+            pool: self.pool.with_stride(stride),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<MaxPool> {
+        Some(MaxPool {
+            pool: Pool::from_data(data)?,
+        })
+    }
+    fn argmaxes(&self, input: &[f64]) -> Vec<usize> {
+        self.pool
+            .windows()
+            .into_iter()
+            .map(|window| {
+                window
+                    .into_iter()
+                    .reduce(|best, i| if input[i] > input[best] { i } else { best })
+                    .unwrap()
+            })
+            .collect()
+    }
+}
+
+impl Layer for MaxPool {
+    fn input_len(&self) -> usize {
+        self.pool.window.input_len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        self.pool.output_shape()
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        self.argmaxes(input).into_iter().map(|i| input[i]).collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let mut input_grad = vec![0.0; input.len()];
+        for (i, grad) in self.argmaxes(input).into_iter().zip(output_grad.iter()) {
+            input_grad[i] += grad;
+        }
+        (input_grad, LayerParameters::default())
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters::default()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        set_no_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        self.pool.serialize("max_pool")
+    }
+}
+
+/// Average pooling over a (channels, length) or (channels, height, width) input: every output is the mean of the inputs under its window, taken separately for each channel. By default the windows don't overlap. The network's activation function is not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvgPool {
+    pool: Pool,
+}
+
+impl AvgPool {
+    pub fn new(input_shape: &[usize], pool: &[usize]) -> AvgPool {
+        /*!
+        Creates an average pooling layer whose stride is the pool size, with shapes given like [`MaxPool::new`].
+        ```
+        # use neurnet::*;
+        let pool = AvgPool::new(&[2, 4], &[2]);
+        assert_eq!(pool.output_shape(), vec![2, 2]);
+        assert_eq!(
+            pool.forward(&[1.0, 3.0, 2.0, 0.0, 4.0, 4.0, -1.0, 1.0], &Activation::Identity),
+            vec![2.0, 1.0, 4.0, 0.0]
+        );
+        ```
+        # Panics
+        <ul>
+        <li> If the input shape doesn't have 2 or 3 dimensions, or there isn't a pool size for each dimension after the channels.
+        <li> If the pool doesn't fit in the input, or a pool size is 0.
+        </ul>
+        */
+        AvgPool {
+            pool: Pool::new(input_shape, pool),
+        }
+    }
+    pub fn with_stride(self, stride: &[usize]) -> AvgPool {
+        //! Sets how far the window moves between outputs, with a stride for each dimension of the pool.
+        //! # Panics
+        //! <ul>
+        //! <li> If the number of strides doesn't match the pool, or a stride is 0.
+        //! </ul>
+        AvgPool {
+            pool: self.pool.with_stride(stride),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<AvgPool> {
+        Some(AvgPool {
+            pool: Pool::from_data(data)?,
+        })
+    }
+}
+
+impl Layer for AvgPool {
+    fn input_len(&self) -> usize {
+        self.pool.window.input_len()
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        self.pool.output_shape()
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        self.pool
+            .windows()
+            .into_iter()
+            .map(|window| window.iter().map(|i| input[*i]).sum::<f64>() / window.len() as f64)
+            .collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let mut input_grad = vec![0.0; input.len()];
+        for (window, grad) in self.pool.windows().into_iter().zip(output_grad.iter()) {
+            for i in window.iter() {
+                input_grad[*i] += grad / window.len() as f64;
+            }
+        }
+        (input_grad, LayerParameters::default())
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters::default()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        set_no_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        self.pool.serialize("avg_pool")
+    }
+}
+
+/// Averages each channel of a (channels, ...) input down to a single value, so the output has one value per channel no matter how long or large the input is. The network's activation function is not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalAveragePool {
+    channels: usize,
+    size: usize,
+}
+
+impl GlobalAveragePool {
+    pub fn new(input_shape: &[usize]) -> GlobalAveragePool {
+        /*!
+        Creates a global average pooling layer for an input whose first dimension is the channels, such as (channels, length) or (channels, height, width).
+        ```
+        # use neurnet::*;
+        let pool = GlobalAveragePool::new(&[2, 3]);
+        assert_eq!(pool.forward(&[1.0, 2.0, 3.0, 0.0, 0.0, 6.0], &Activation::Identity), vec![2.0, 2.0]);
+        ```
+        # Panics
+        <ul>
+        <li> If the input shape is empty.
+        </ul>
+        */
+        let (channels, spatial) = input_shape
+            .split_first()
+            .expect("The input shape must start with the channels");
+        GlobalAveragePool {
+            channels: *channels,
+            size: spatial.iter().product(),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<GlobalAveragePool> {
+        Some(GlobalAveragePool {
+            channels: data.usize_setting("channels")?,
+            size: data.usize_setting("size")?,
+        })
+    }
+}
+
+impl Layer for GlobalAveragePool {
+    fn input_len(&self) -> usize {
+        self.channels * self.size
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.channels]
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        input
+            .chunks(self.size.max(1))
+            .take(self.channels)
+            .map(|channel| channel.iter().sum::<f64>() / self.size as f64)
+            .collect()
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let input_grad = (0..input.len())
+            .map(|i| output_grad[i / self.size] / self.size as f64)
+            .collect();
+        (input_grad, LayerParameters::default())
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters::default()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        set_no_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        LayerData::new("global_average_pool")
+            .with_setting("channels", self.channels as f64)
+            .with_setting("size", self.size as f64)
+    }
+}
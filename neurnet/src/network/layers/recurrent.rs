@@ -0,0 +1,557 @@
+use super::super::activation::Activation;
+use super::{Layer, LayerData, LayerParameters};
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+fn product(weights: &[f64], vector: &[f64]) -> Vec<f64> {
+    //Multiplies a row-major matrix with as many columns as the vector has values by the vector
+    weights
+        .chunks(vector.len().max(1))
+        .map(|row| row.iter().zip(vector.iter()).map(|(w, x)| w * x).sum())
+        .collect()
+}
+
+/// The weights shared by the recurrent layers: for each of their gates, a weighted sum of the step's input plus a bias, and a weighted sum of the previous hidden state.
+#[derive(Debug, Clone, PartialEq)]
+struct GateWeights {
+    input_len: usize,
+    hidden_len: usize,
+    input_weights: Vec<f64>,     //[gate * hidden_len + neuron] [input]
+    recurrent_weights: Vec<f64>, //[gate * hidden_len + neuron] [hidden neuron]
+    biases: Vec<f64>,
+}
+
+impl GateWeights {
+    fn new(gates: usize, hidden_len: usize, input_len: usize) -> GateWeights {
+        GateWeights {
+            input_len,
+            hidden_len,
+            input_weights: vec![1.0; gates * hidden_len * input_len],
+            recurrent_weights: vec![1.0; gates * hidden_len * hidden_len],
+            biases: vec![0.0; gates * hidden_len],
+        }
+    }
+    fn from_data(data: &LayerData, gates: usize) -> Option<GateWeights> {
+        let (input_shape, input_weights) = data.tensor("weight_ih")?;
+        let (recurrent_shape, recurrent_weights) = data.tensor("weight_hh")?;
+        let (bias_shape, biases) = data.tensor("bias")?;
+        match (
+            input_shape.as_slice(),
+            recurrent_shape.as_slice(),
+            bias_shape.as_slice(),
+        ) {
+            ([rows, input_len], [recurrent_rows, hidden_len], [bias_len])
+                if rows == recurrent_rows
+                    && rows == bias_len
+                    && *rows == gates * hidden_len
+                    && *hidden_len > 0 =>
+            {
+                Some(GateWeights {
+                    input_len: *input_len,
+                    hidden_len: *hidden_len,
+                    input_weights: input_weights.clone(),
+                    recurrent_weights: recurrent_weights.clone(),
+                    biases: biases.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+    fn serialize(&self, kind: &str) -> LayerData {
+        LayerData::new(kind)
+            .with_tensor(
+                "weight_ih",
+                vec![self.biases.len(), self.input_len],
+                self.input_weights.clone(),
+            )
+            .with_tensor(
+                "weight_hh",
+                vec![self.biases.len(), self.hidden_len],
+                self.recurrent_weights.clone(),
+            )
+            .with_tensor("bias", vec![self.biases.len()], self.biases.clone())
+    }
+    fn sums(&self, input: &[f64], hidden: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        //Returns the input's weighted sums plus the biases, and the hidden state's weighted sums
+        let input_sums = product(&self.input_weights, input)
+            .into_iter()
+            .zip(self.biases.iter())
+            .map(|(sum, bias)| sum + bias)
+            .collect();
+        let hidden_sums = match hidden.is_empty() {
+            true => vec![0.0; self.biases.len()],
+            false => product(&self.recurrent_weights, hidden),
+        };
+        (input_sums, hidden_sums)
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        hidden: &[f64],
+        input_sums_grad: &[f64],
+        hidden_sums_grad: &[f64],
+    ) -> (Vec<f64>, Vec<f64>, LayerParameters) {
+        //Returns the gradient with respect to the input, the hidden state and the weights
+        let mut input_grad = vec![0.0; self.input_len];
+        let mut hidden_grad = vec![0.0; self.hidden_len];
+        let mut gradient = LayerParameters {
+            weights: vec![0.0; self.input_weights.len() + self.recurrent_weights.len()],
+            biases: input_sums_grad.to_vec(),
+            rows: vec![],
+        };
+        let (input_weights_grad, recurrent_weights_grad) =
+            gradient.weights.split_at_mut(self.input_weights.len());
+        for (row, grad) in input_sums_grad.iter().enumerate() {
+            for (i, x) in input.iter().enumerate() {
+                input_weights_grad[row * self.input_len + i] += grad * x;
+                input_grad[i] += grad * self.input_weights[row * self.input_len + i];
+            }
+        }
+        for (row, grad) in hidden_sums_grad.iter().enumerate() {
+            for (i, h) in hidden.iter().enumerate() {
+                recurrent_weights_grad[row * self.hidden_len + i] += grad * h;
+                hidden_grad[i] += grad * self.recurrent_weights[row * self.hidden_len + i];
+            }
+        }
+        (input_grad, hidden_grad, gradient)
+    }
+    fn parameters(&self) -> LayerParameters {
+        LayerParameters {
+            weights: [
+                self.input_weights.as_slice(),
+                self.recurrent_weights.as_slice(),
+            ]
+            .concat(),
+            biases: self.biases.clone(),
+            rows: vec![],
+        }
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        if parameters.weights.len() != self.input_weights.len() + self.recurrent_weights.len()
+            || parameters.biases.len() != self.biases.len()
+        {
+            return None;
+        }
+        let (input_weights, recurrent_weights) =
+            parameters.weights.split_at(self.input_weights.len());
+        self.input_weights.copy_from_slice(input_weights);
+        self.recurrent_weights.copy_from_slice(recurrent_weights);
+        self.biases.copy_from_slice(&parameters.biases);
+        Some(())
+    }
+}
+
+/// An Elman recurrent layer: every step, each neuron takes a weighted sum of the step's input and of the layer's outputs from the previous step, plus its bias, and runs it through tanh. Its state is its previous outputs.
+///
+/// Recurrent layers run one step of a sequence at a time through [`Network::pulse_step`](crate::Network::pulse_step), and are trained on whole sequences with [`Network::train_sequences`](crate::Network::train_sequences). [`Network::pulse`](crate::Network::pulse) runs a single step from the initial, all zero state. The network's activation function is not applied.
+///
+/// Its [`LayerParameters`] are the input weights laid out as (neurons, inputs), then the recurrent weights as (neurons, neurons), like PyTorch's `RNN.weight_ih_l0` and `weight_hh_l0`, then one bias per neuron.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rnn {
+    weights: GateWeights,
+}
+
+impl Rnn {
+    pub fn new(layer_size: usize, prev_layer_size: usize) -> Rnn {
+        /*!
+        Creates a recurrent layer with the given amount of neurons, taking the given amount of inputs every step, like [`Dense::new`](super::Dense::new).
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(
+            vec![Box::new(Rnn::new(4, 2)), Box::new(Dense::new(1, 4))],
+            Activation::Identity,
+        )
+        .unwrap();
+        nn.randomize_seeded((-0.5, 0.5), (-0.5, 0.5), 1);
+        let inputs = vec![vec![0.5, -0.1], vec![0.2, 0.3], vec![-0.4, 0.8]];
+        let expected = vec![vec![0.1], vec![0.4], vec![-0.2]];
+        let errors = nn.sequence_gradient_check((&inputs, &expected), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // The first step doesn't depend on any earlier ones
+        assert_eq!(nn.pulse_sequence(&inputs)[0], nn.pulse(inputs[0].clone()));
+        ```
+        */
+        Rnn {
+            weights: GateWeights::new(1, layer_size, prev_layer_size),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Rnn> {
+        Some(Rnn {
+            weights: GateWeights::from_data(data, 1)?,
+        })
+    }
+    fn step(&self, input: &[f64], state: &[f64]) -> Vec<f64> {
+        let (input_sums, hidden_sums) = self.weights.sums(input, state);
+        input_sums
+            .iter()
+            .zip(hidden_sums.iter())
+            .map(|(x, h)| (x + h).tanh())
+            .collect()
+    }
+    pub fn len(&self) -> usize {
+        self.weights.hidden_len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.weights.hidden_len == 0
+    }
+}
+
+impl Layer for Rnn {
+    fn input_len(&self) -> usize {
+        self.weights.input_len
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.len()]
+    }
+    fn state_len(&self) -> usize {
+        self.len()
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        self.step(input, &[])
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let state = vec![0.0; self.state_len()];
+        let (input_grad, _, gradient) =
+            self.backward_step(input, &state, output_grad, &state, activation);
+        (input_grad, gradient)
+    }
+    fn forward_step(
+        &self,
+        input: &[f64],
+        state: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let outputs = self.step(input, state);
+        (outputs.clone(), outputs)
+    }
+    fn backward_step(
+        &self,
+        input: &[f64],
+        state: &[f64],
+        output_grad: &[f64],
+        state_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>, LayerParameters) {
+        let sums_grad: Vec<f64> = self
+            .step(input, state)
+            .iter()
+            .zip(output_grad.iter().zip(state_grad.iter()))
+            .map(|(h, (output_grad, state_grad))| (output_grad + state_grad) * (1.0 - h * h))
+            .collect();
+        self.weights.backward(input, state, &sums_grad, &sums_grad)
+    }
+    fn parameters(&self) -> LayerParameters {
+        self.weights.parameters()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        self.weights.set_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        self.weights.serialize("rnn")
+    }
+}
+
+/// A long short-term memory layer. Every step its input, forget, cell and output gates (in that order) each take weighted sums of the step's input and the previous outputs; the cell keeps a memory that the gates write to and forget, and the outputs are the output gate times tanh of the memory. Its state is its previous outputs followed by its memory.
+///
+/// It runs through a network like [`Rnn`], and its [`LayerParameters`] are laid out the same way with four rows of weights and biases per neuron, one block of neurons per gate, like PyTorch's `LSTM`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lstm {
+    weights: GateWeights,
+}
+
+impl Lstm {
+    pub fn new(layer_size: usize, prev_layer_size: usize) -> Lstm {
+        /*!
+        Creates an LSTM layer with the given amount of neurons, taking the given amount of inputs every step.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(
+            vec![Box::new(Lstm::new(3, 2)), Box::new(Dense::new(1, 3))],
+            Activation::Identity,
+        )
+        .unwrap();
+        // Small parameters, so the gates don't saturate
+        nn.randomize_seeded((-0.5, 0.5), (-0.5, 0.5), 1);
+        assert_eq!(nn.get_layers()[0].state_len(), 6);
+
+        // A single expected output is compared against the last step only
+        let inputs = vec![vec![0.5, -0.1], vec![0.2, 0.3], vec![-0.4, 0.8], vec![0.9, 0.1]];
+        let errors = nn.sequence_gradient_check((&inputs, &[vec![0.3]]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        let mut buf: Vec<u8> = vec![];
+        nn.write_to(&mut buf).unwrap();
+        let loaded = Network::read_from(buf.as_slice(), |x| x).unwrap();
+        assert_eq!(loaded.pulse_sequence(&inputs), nn.pulse_sequence(&inputs));
+        ```
+        */
+        Lstm {
+            weights: GateWeights::new(4, layer_size, prev_layer_size),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Lstm> {
+        Some(Lstm {
+            weights: GateWeights::from_data(data, 4)?,
+        })
+    }
+    fn step(&self, input: &[f64], state: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        //Returns every activated gate and the new memory
+        let len = self.len();
+        let (hidden, _) = state.split_at(state.len().min(len));
+        let (input_sums, hidden_sums) = self.weights.sums(input, hidden);
+        let gates: Vec<f64> = input_sums
+            .iter()
+            .zip(hidden_sums.iter())
+            .enumerate()
+            .map(|(row, (x, h))| match row / len {
+                2 => (x + h).tanh(),
+                _ => sigmoid(x + h),
+            })
+            .collect();
+        let memory = (0..len)
+            .map(|i| {
+                let old_memory = state.get(len + i).copied().unwrap_or(0.0);
+                gates[len + i] * old_memory + gates[i] * gates[2 * len + i]
+            })
+            .collect();
+        (gates, memory)
+    }
+    pub fn len(&self) -> usize {
+        self.weights.hidden_len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.weights.hidden_len == 0
+    }
+}
+
+impl Layer for Lstm {
+    fn input_len(&self) -> usize {
+        self.weights.input_len
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.len()]
+    }
+    fn state_len(&self) -> usize {
+        2 * self.len()
+    }
+    fn forward(&self, input: &[f64], activation: &Activation) -> Vec<f64> {
+        self.forward_step(input, &[], activation).0
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let state = vec![0.0; self.state_len()];
+        let (input_grad, _, gradient) =
+            self.backward_step(input, &state, output_grad, &state, activation);
+        (input_grad, gradient)
+    }
+    fn forward_step(
+        &self,
+        input: &[f64],
+        state: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let len = self.len();
+        let (gates, memory) = self.step(input, state);
+        let outputs: Vec<f64> = (0..len)
+            .map(|i| gates[3 * len + i] * memory[i].tanh())
+            .collect();
+        let state = [outputs.as_slice(), memory.as_slice()].concat();
+        (outputs, state)
+    }
+    fn backward_step(
+        &self,
+        input: &[f64],
+        state: &[f64],
+        output_grad: &[f64],
+        state_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>, LayerParameters) {
+        let len = self.len();
+        let (gates, memory) = self.step(input, state);
+        let mut sums_grad = vec![0.0; 4 * len];
+        let mut old_memory_grad = vec![0.0; len];
+        for i in 0..len {
+            let (input_gate, forget_gate, cell_gate, output_gate) = (
+                gates[i],
+                gates[len + i],
+                gates[2 * len + i],
+                gates[3 * len + i],
+            );
+            let output_grad = output_grad[i] + state_grad[i];
+            let memory_tanh = memory[i].tanh();
+            let memory_grad =
+                state_grad[len + i] + output_grad * output_gate * (1.0 - memory_tanh.powi(2));
+            sums_grad[i] = memory_grad * cell_gate * input_gate * (1.0 - input_gate);
+            sums_grad[len + i] = memory_grad * state[len + i] * forget_gate * (1.0 - forget_gate);
+            sums_grad[2 * len + i] = memory_grad * input_gate * (1.0 - cell_gate.powi(2));
+            sums_grad[3 * len + i] = output_grad * memory_tanh * output_gate * (1.0 - output_gate);
+            old_memory_grad[i] = memory_grad * forget_gate;
+        }
+        let (input_grad, mut hidden_grad, gradient) =
+            self.weights
+                .backward(input, &state[..len], &sums_grad, &sums_grad);
+        hidden_grad.extend(old_memory_grad);
+        (input_grad, hidden_grad, gradient)
+    }
+    fn parameters(&self) -> LayerParameters {
+        self.weights.parameters()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        self.weights.set_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        self.weights.serialize("lstm")
+    }
+}
+
+/// A gated recurrent unit layer. Every step its reset and update gates take weighted sums of the step's input and the previous outputs; the candidate outputs are tanh of the input's weighted sum plus the reset gate times the previous outputs' weighted sum, and the update gate mixes the previous outputs with the candidates. Its state is its previous outputs.
+///
+/// It runs through a network like [`Rnn`], and its [`LayerParameters`] are laid out the same way with three blocks of neurons: the reset gate, the update gate and the candidates, like PyTorch's `GRU` (which has a second set of candidate biases that this layer folds into the first).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gru {
+    weights: GateWeights,
+}
+
+impl Gru {
+    pub fn new(layer_size: usize, prev_layer_size: usize) -> Gru {
+        /*!
+        Creates a GRU layer with the given amount of neurons, taking the given amount of inputs every step.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(
+            vec![Box::new(Gru::new(3, 2)), Box::new(Gru::new(2, 3)), Box::new(Dense::new(1, 2))],
+            Activation::Identity,
+        )
+        .unwrap();
+        nn.randomize_seeded((-0.5, 0.5), (-0.5, 0.5), 1);
+        let inputs = vec![vec![0.5, -0.1], vec![0.2, 0.3], vec![-0.4, 0.8]];
+        let expected = vec![vec![0.1], vec![0.4], vec![-0.2]];
+        let errors = nn.sequence_gradient_check((&inputs, &expected), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+        ```
+        */
+        Gru {
+            weights: GateWeights::new(3, layer_size, prev_layer_size),
+        }
+    }
+    pub(crate) fn from_data(data: &LayerData) -> Option<Gru> {
+        Some(Gru {
+            weights: GateWeights::from_data(data, 3)?,
+        })
+    }
+    fn step(&self, input: &[f64], state: &[f64]) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        //Returns the activated gates and candidates, the hidden state's weighted sums, and the new outputs
+        let len = self.len();
+        let (input_sums, hidden_sums) = self.weights.sums(input, state);
+        let mut gates: Vec<f64> = (0..2 * len)
+            .map(|row| sigmoid(input_sums[row] + hidden_sums[row]))
+            .collect();
+        for i in 0..len {
+            gates.push((input_sums[2 * len + i] + gates[i] * hidden_sums[2 * len + i]).tanh());
+        }
+        let outputs = (0..len)
+            .map(|i| {
+                let update = gates[len + i];
+                let old_output = state.get(i).copied().unwrap_or(0.0);
+                (1.0 - update) * gates[2 * len + i] + update * old_output
+            })
+            .collect();
+        (gates, hidden_sums, outputs)
+    }
+    pub fn len(&self) -> usize {
+        self.weights.hidden_len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.weights.hidden_len == 0
+    }
+}
+
+impl Layer for Gru {
+    fn input_len(&self) -> usize {
+        self.weights.input_len
+    }
+    fn output_shape(&self) -> Vec<usize> {
+        vec![self.len()]
+    }
+    fn state_len(&self) -> usize {
+        self.len()
+    }
+    fn forward(&self, input: &[f64], _activation: &Activation) -> Vec<f64> {
+        self.step(input, &[]).2
+    }
+    fn backward(
+        &self,
+        input: &[f64],
+        output_grad: &[f64],
+        activation: &Activation,
+    ) -> (Vec<f64>, LayerParameters) {
+        let state = vec![0.0; self.state_len()];
+        let (input_grad, _, gradient) =
+            self.backward_step(input, &state, output_grad, &state, activation);
+        (input_grad, gradient)
+    }
+    fn forward_step(
+        &self,
+        input: &[f64],
+        state: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let outputs = self.step(input, state).2;
+        (outputs.clone(), outputs)
+    }
+    fn backward_step(
+        &self,
+        input: &[f64],
+        state: &[f64],
+        output_grad: &[f64],
+        state_grad: &[f64],
+        _activation: &Activation,
+    ) -> (Vec<f64>, Vec<f64>, LayerParameters) {
+        let len = self.len();
+        let (gates, hidden_sums, _) = self.step(input, state);
+        let mut input_sums_grad = vec![0.0; 3 * len];
+        let mut hidden_sums_grad = vec![0.0; 3 * len];
+        let mut direct_grad = vec![0.0; len];
+        for i in 0..len {
+            let (reset, update, candidate) = (gates[i], gates[len + i], gates[2 * len + i]);
+            let output_grad = output_grad[i] + state_grad[i];
+            let candidate_grad = output_grad * (1.0 - update) * (1.0 - candidate.powi(2));
+            let reset_grad = candidate_grad * hidden_sums[2 * len + i] * reset * (1.0 - reset);
+            let update_grad = output_grad * (state[i] - candidate) * update * (1.0 - update);
+            input_sums_grad[i] = reset_grad;
+            hidden_sums_grad[i] = reset_grad;
+            input_sums_grad[len + i] = update_grad;
+            hidden_sums_grad[len + i] = update_grad;
+            input_sums_grad[2 * len + i] = candidate_grad;
+            hidden_sums_grad[2 * len + i] = candidate_grad * reset;
+            direct_grad[i] = output_grad * update;
+        }
+        let (input_grad, mut hidden_grad, gradient) =
+            self.weights
+                .backward(input, state, &input_sums_grad, &hidden_sums_grad);
+        for (grad, direct) in hidden_grad.iter_mut().zip(direct_grad) {
+            *grad += direct;
+        }
+        (input_grad, hidden_grad, gradient)
+    }
+    fn parameters(&self) -> LayerParameters {
+        self.weights.parameters()
+    }
+    fn set_parameters(&mut self, parameters: &LayerParameters) -> Option<()> {
+        self.weights.set_parameters(parameters)
+    }
+    fn serialize(&self) -> LayerData {
+        self.weights.serialize("gru")
+    }
+}
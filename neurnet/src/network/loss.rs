@@ -0,0 +1,95 @@
+/// A loss function, measuring how far a network's outputs are from the expected outputs of a single data point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Loss {
+    /// The mean of the squared errors of every output.
+    MeanSquaredError,
+    /// The mean of the absolute errors of every output.
+    MeanAbsoluteError,
+    /// The mean Huber loss of every output: squared for errors smaller than the given delta, linear beyond it.
+    Huber(f64),
+    /// The mean cross-entropy of every output, each treated as the probability of an independent yes/no class.
+    BinaryCrossEntropy,
+    /// The cross-entropy of the outputs treated as probabilities of mutually exclusive classes, for one-hot expected outputs.
+    CategoricalCrossEntropy,
+}
+
+/// Probabilities are clipped to this distance from 0 and 1 so the cross-entropy losses stay finite.
+const EPSILON: f64 = 1e-15;
+
+impl Loss {
+    pub fn cost(&self, outputs: &[f64], expected_outputs: &[f64]) -> f64 {
+        /*!
+        The loss of the outputs compared to the expected outputs.
+        ```
+        # use neurnet::*;
+        assert_eq!(Loss::MeanSquaredError.cost(&[1.0, 2.0], &[0.0, 0.0]), 2.5);
+        assert_eq!(Loss::Huber(1.0).cost(&[0.5, 3.0], &[0.0, 0.0]), (0.125 + 2.5) / 2.0);
+        ```
+        */
+        let n = outputs.len() as f64;
+        let pairs = outputs.iter().zip(expected_outputs.iter());
+        match self {
+            Loss::MeanSquaredError => pairs.map(|(o, t)| (o - t) * (o - t)).sum::<f64>() / n,
+            Loss::MeanAbsoluteError => pairs.map(|(o, t)| (o - t).abs()).sum::<f64>() / n,
+            Loss::Huber(delta) => {
+                pairs
+                    .map(|(o, t)| {
+                        let e = (o - t).abs();
+                        if e <= *delta {
+                            0.5 * e * e
+                        } else {
+                            delta * (e - 0.5 * delta)
+                        }
+                    })
+                    .sum::<f64>()
+                    / n
+            }
+            Loss::BinaryCrossEntropy => {
+                -pairs
+                    .map(|(o, t)| {
+                        let p = o.clamp(EPSILON, 1.0 - EPSILON);
+                        t * p.ln() + (1.0 - t) * (1.0 - p).ln()
+                    })
+                    .sum::<f64>()
+                    / n
+            }
+            Loss::CategoricalCrossEntropy => -pairs
+                .map(|(o, t)| t * o.clamp(EPSILON, 1.0 - EPSILON).ln())
+                .sum::<f64>(),
+        }
+    }
+    pub fn derivative(&self, outputs: &[f64], expected_outputs: &[f64]) -> Vec<f64> {
+        /*!
+        The derivative of the loss with respect to each output. The absolute error's derivative is taken as 0 where an output equals its expected output.
+        ```
+        # use neurnet::*;
+        assert_eq!(Loss::MeanAbsoluteError.derivative(&[1.0, 2.0], &[0.0, 2.0]), vec![0.5, 0.0]);
+        ```
+        */
+        let n = outputs.len() as f64;
+        let clipped = |o: f64| o <= EPSILON || o >= 1.0 - EPSILON;
+        outputs
+            .iter()
+            .zip(expected_outputs.iter())
+            .map(|(o, t)| match self {
+                Loss::MeanSquaredError => 2.0 * (o - t) / n,
+                Loss::MeanAbsoluteError => (o - t).signum() * (o != t) as u8 as f64 / n,
+                Loss::Huber(delta) => (o - t).clamp(-delta, *delta) / n,
+                //Clipped probabilities don't change the loss
+                Loss::BinaryCrossEntropy | Loss::CategoricalCrossEntropy if clipped(*o) => 0.0,
+                Loss::BinaryCrossEntropy => (o - t) / (o * (1.0 - o)) / n,
+                Loss::CategoricalCrossEntropy => -t / o,
+            })
+            .collect()
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Loss::MeanSquaredError => "mean_squared_error",
+            Loss::MeanAbsoluteError => "mean_absolute_error",
+            Loss::Huber(_) => "huber",
+            Loss::BinaryCrossEntropy => "binary_cross_entropy",
+            Loss::CategoricalCrossEntropy => "categorical_cross_entropy",
+        }
+    }
+}
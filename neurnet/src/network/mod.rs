@@ -1,15 +1,73 @@
-use super::files::{parse_neur_file, write_neur_file};
+use super::files::{parse_neur, parse_neur_file, write_neur, write_neur_file, NeurData, SaveMode};
+use rand::{rngs::StdRng, SeedableRng};
+use std::io::{Read, Write};
+pub mod activation;
+pub mod graph;
+pub mod layers;
+pub mod loss;
+pub mod neat;
+mod npy;
+mod onnx;
+pub mod safetensors;
+pub mod scaler;
 pub mod training;
+use activation::Activation;
+use layers::{Dense, Layer, LayerData};
+use scaler::Scaler;
+use training::dropout::{Dropout, DropoutMasks};
+use training::regularization::Regularization;
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "NetworkFields"))]
 pub struct Network {
-    activation_fn: Box<dyn Fn(f64) -> f64>,
+    activation: Activation,
     shape: Vec<usize>,
-    layers: Vec<Layer>,
+    layers: Vec<Box<dyn Layer>>,
+    regularization: Vec<Regularization>,
+    dropout: Vec<Option<Dropout>>,
+    input_scaler: Option<Scaler>,
+    output_scaler: Option<Scaler>,
 }
-pub struct Layer {
-    weights: Vec<Vec<f64>>, //[neuron in this layer] [connecting neuron in prev layer]
-    biases: Vec<f64>,
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct NetworkFields {
+    activation: Activation,
+    shape: Vec<usize>,
+    layers: Vec<Box<dyn Layer>>,
+    regularization: Vec<Regularization>,
+    dropout: Vec<Option<Dropout>>,
+    input_scaler: Option<Scaler>,
+    output_scaler: Option<Scaler>,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<NetworkFields> for Network {
+    type Error = String;
+    fn try_from(data: NetworkFields) -> Result<Network, String> {
+        //Goes through the same checks as building the network from its layers and loading it from a .neur file
+        let invalid = || "the fields don't describe a valid network".to_string();
+        let mut network = Network::from_layers(data.layers, data.activation).ok_or_else(invalid)?;
+        let valid_scaler = |scaler: &Option<Scaler>, len: usize| match scaler {
+            Some(scaler) => {
+                scaler.len() == len
+                    && Scaler::new(scaler.center().clone(), scaler.scale().clone()).is_some()
+            }
+            None => true,
+        };
+        if network.shape != data.shape
+            || data.regularization.len() != network.layers.len()
+            || data.dropout.len() != network.layers.len()
+            || !valid_scaler(&data.input_scaler, network.shape[0])
+            || !valid_scaler(&data.output_scaler, *network.shape.last().unwrap())
+        {
+            return Err(invalid());
+        }
+        network.regularization = data.regularization;
+        network.dropout = data.dropout;
+        network.input_scaler = data.input_scaler;
+        network.output_scaler = data.output_scaler;
+        Ok(network)
+    }
 }
-
 impl Network {
     pub fn new<AF: Fn(f64) -> f64 + 'static>(
         shape: Vec<usize>,
@@ -19,6 +77,7 @@ impl Network {
     ) -> Network {
         //! Generates a new Neural Network. Shape defines the number of layers and the number of neurons in each layer. For example, if a network is defined to have a shape of [1, 2, 3], then the network will have one neuron in its first (input) layer, two neurons in its 2nd (hidden) layer, and three neurons in its 3rd (output) layer. The weight and bias ranges are the ranges in which the networks weights and ranges will be randomly generated upon initialization.
         //! ```
+        //! # use neurnet::*;
         //! let mut nn = Network::new(
         //!     vec![1, 5, 4, 5, 4, 6, 2],
         //!     |x| if x > 0.0 { x } else { 0.01 * x },
@@ -26,58 +85,107 @@ impl Network {
         //!     (-5.0, 5.0),
         //! );
         //! ```
-        let mut layers: Vec<Layer> = vec![];
+        Network::with_activation(
+            shape,
+            Activation::custom(activation_fn),
+            weights_range,
+            biases_range,
+        )
+    }
+    pub fn with_activation(
+        shape: Vec<usize>,
+        activation: Activation,
+        weights_range: (f64, f64),
+        biases_range: (f64, f64),
+    ) -> Network {
+        //! Generates a new Neural Network just like [`Network::new`], but with a named [`Activation`] instead of a closure. Networks built this way can be serialized with the `serde` feature.
+        //! ```
+        //! # use neurnet::*;
+        //! let nn = Network::with_activation(
+        //!     vec![1, 5, 2],
+        //!     Activation::LeakyRelu(0.01),
+        //!     (-2.0, 2.0),
+        //!     (-5.0, 5.0),
+        //! );
+        //! ```
+        let mut layers: Vec<Box<dyn Layer>> = vec![];
         for i in 1..shape.len() {
             //1..shape.len() bcs the input layer shouldn't be an actual layer
             layers.push({
-                let mut layer = Layer::new(*&shape[i], *&shape[i - 1]);
+                let mut layer = Dense::new(shape[i], shape[i - 1]);
                 layer.randomize(weights_range, biases_range);
-                layer
+                Box::new(layer)
             })
         }
         Network {
-            activation_fn: Box::new(activation_fn),
+            activation,
             shape,
+            regularization: vec![Regularization::default(); layers.len()],
+            dropout: vec![None; layers.len()],
             layers,
+            input_scaler: None,
+            output_scaler: None,
         }
     }
-    /*pub fn save_safe(&self, name: &str) {
-        //! Saves a network as {name}.neur, except if the file already exists save_safe will keep adding on numbers to the file name until the network gets successfully saved.
-        //! ```
-        //! let network1 = Network::new(
-        //!   vec![1, 2, 1],
-        //!   |x| if x > 0.0 { x } else { 0.01 * x },
-        //!   (-2.0, 2.0),
-        //!   (-5.0, 5.0),
-        //! );
-        //! network1.save_safe(&"network");
-        //! ```
-        match self.save(&{
-            let mut string = String::from(name);
-            string.push_str(".neur");
-            string
-        }) {
-            Some(_) => (),
-            None => {
-                let mut i: usize = 0;
-                loop {
-                    match self.save(&{
-                        let mut string = String::from(name);
-                        string.push_str(&i.to_string());
-                        string.push_str(".neur");
-                        string
-                    }) {
-                        Some(_) => break,
-                        None => (),
-                    }
-                    i += 1;
-                }
+    pub fn from_layers(layers: Vec<Box<dyn Layer>>, activation: Activation) -> Option<Network> {
+        /*!
+        Builds a network out of any kinds of [`Layer`]s, in order from the first layer after the inputs. The network's input length is the first layer's [`Layer::input_len`]. Returns None if there are no layers, or if a layer's input length doesn't match the previous layer's output length.
+        ```
+        # use neurnet::*;
+        let mut hidden = Dense::new(3, 2);
+        hidden.randomize((-1.0, 1.0), (-1.0, 1.0));
+        let nn = Network::from_layers(vec![Box::new(hidden), Box::new(Dense::new(1, 3))], Activation::Tanh).unwrap();
+        assert_eq!(nn.get_shape(), &vec![2, 3, 1]);
+        assert!(nn.get_layers()[0].downcast_ref::<Dense>().is_some());
+
+        // Layers are saved with their kind, so any network round trips through a .neur file
+        let mut buf: Vec<u8> = vec![];
+        nn.write_to(&mut buf).unwrap();
+        let loaded = Network::read_from(buf.as_slice(), |x| x.tanh()).unwrap();
+        assert_eq!(loaded.pulse(vec![0.5, -0.5]), nn.pulse(vec![0.5, -0.5]));
+
+        assert!(Network::from_layers(vec![Box::new(Dense::new(3, 2)), Box::new(Dense::new(1, 2))], Activation::Tanh).is_none());
+        ```
+        */
+        let first = layers.first()?;
+        let mut shape = vec![first.input_len()];
+        for layer in layers.iter() {
+            if layer.input_len() != *shape.last().unwrap() {
+                return None;
             }
+            shape.push(layer.output_len());
         }
-    }*/
+        Some(Network {
+            activation,
+            shape,
+            regularization: vec![Regularization::default(); layers.len()],
+            dropout: vec![None; layers.len()],
+            layers,
+            input_scaler: None,
+            output_scaler: None,
+        })
+    }
+    pub fn add_layer(&mut self, layer: Box<dyn Layer>) {
+        //! Adds a layer after the current last layer, which becomes the new output layer. A fitted output scaler no longer fits the outputs, so it is removed.
+        //! # Panics
+        //! <ul>
+        //! <li> If the layer's input length is not equal to the amount of output neurons.
+        //! </ul>
+        assert_eq!(
+            layer.input_len(),
+            *self.shape.last().unwrap(),
+            "Layer input length must match the previous layer's output length"
+        );
+        self.shape.push(layer.output_len());
+        self.layers.push(layer);
+        self.regularization.push(Regularization::default());
+        self.dropout.push(None);
+        self.output_scaler = None;
+    }
     pub fn save(&self, path: &str) -> Option<()> {
-        //! Saves the network to the path specified. Will return None if saving fails for any reason, including if the path specified already exists.
-        //! ```
+        //! Saves the network to the path specified, replacing any file that is already there. The save is atomic, so a crash mid-save never leaves a half written file behind. Will return None if saving fails for any reason. Use [`Network::save_with_mode`] to refuse to overwrite or to pick a fresh numbered file name instead.
+        //! ```no_run
+        //! # use neurnet::*;
         //! let network1 = Network::new(
         //!   vec![1, 2, 1],
         //!   |x| if x > 0.0 { x } else { 0.01 * x },
@@ -86,27 +194,50 @@ impl Network {
         //! );
         //! network1.save(&"network.neur");
         //! ```
-        let mut data: (Vec<usize>, Vec<Vec<Vec<f64>>>, Vec<Vec<f64>>) =
-            (self.get_shape().clone(), vec![], vec![]);
-        let mut layer_i = 0;
-        for layer in self.layers.iter() {
-            data.1.push(vec![]);
-            data.2.push(vec![]);
-            for neuron_i in 0..layer.len() {
-                data.1[layer_i].push(vec![]);
-                for prev_neuron_i in 0..layer.prev_layer_len() {
-                    data.1[layer_i][neuron_i]
-                        .push(*self.get_weight(layer_i, neuron_i, prev_neuron_i).unwrap());
-                }
-                data.2[layer_i].push(*self.get_bias(layer_i, neuron_i).unwrap());
-            }
-            layer_i += 1;
-        }
-        write_neur_file(path, data)
+        self.save_with_mode(path, SaveMode::Overwrite)?;
+        Some(())
+    }
+    pub fn save_with_mode(&self, path: &str, mode: SaveMode) -> Option<String> {
+        //! Atomically saves the network to the path specified, with the [`SaveMode`] deciding what happens if the path already exists. Returns the path the network was actually saved to (which only differs from the path given with [`SaveMode::Numbered`]), or None if saving failed.
+        //! ```
+        //! # use neurnet::*;
+        //! let dir = std::env::temp_dir().join(format!("neurnet_save_mode_{}", std::process::id()));
+        //! std::fs::create_dir_all(&dir).unwrap();
+        //! let path = dir.join("network.neur").to_string_lossy().into_owned();
+        //!
+        //! let big = Network::new(vec![3, 8, 8, 2], |x| x, (-2.0, 2.0), (-5.0, 5.0));
+        //! let small = Network::new(vec![1, 1], |x| x, (-2.0, 2.0), (-5.0, 5.0));
+        //! big.save_with_mode(&path, SaveMode::CreateNew).unwrap();
+        //! assert!(small.save_with_mode(&path, SaveMode::CreateNew).is_none());
+        //!
+        //! // Overwriting with a smaller network leaves no trailing data from the old file.
+        //! small.save_with_mode(&path, SaveMode::Overwrite).unwrap();
+        //! let loaded = Network::load(&path, |x| x).unwrap();
+        //! assert_eq!(loaded.pulse(vec![1.0]), small.pulse(vec![1.0]));
+        //!
+        //! let numbered = small.save_with_mode(&path, SaveMode::Numbered).unwrap();
+        //! assert!(numbered.ends_with("network0.neur"));
+        //! std::fs::remove_dir_all(&dir).unwrap();
+        //! ```
+        write_neur_file(path, mode, self.to_neur_data())
+    }
+    pub fn write_to<W: Write>(&self, writer: W) -> Option<()> {
+        //! Writes the network in the .neur format to any writer, such as an in-memory buffer or a socket. Returns None if writing fails.
+        //! ```
+        //! # use neurnet::*;
+        //! let network1 = Network::new(vec![1, 2, 1], |x| x, (-2.0, 2.0), (-5.0, 5.0));
+        //! let mut buf: Vec<u8> = vec![];
+        //! network1.write_to(&mut buf).unwrap();
+        //!
+        //! let network2 = Network::read_from(buf.as_slice(), |x| x).unwrap();
+        //! assert_eq!(network1.pulse(vec![0.5]), network2.pulse(vec![0.5]));
+        //! ```
+        write_neur(writer, self.to_neur_data())
     }
     pub fn load<AF: Fn(f64) -> f64 + 'static>(path: &str, activation_fn: AF) -> Option<Network> {
         //! Loads a network from the path specified. Since the activation function isn't currently saved to file it has to be specified in the loading fn. Returns None if loading faild.
-        //! ```
+        //! ```no_run
+        //! # use neurnet::*;
         //! let network1 = Network::new(
         //!   vec![1, 2, 1],
         //!   |x| if x > 0.0 { x } else { 0.01 * x },
@@ -120,27 +251,111 @@ impl Network {
         //!   |x| if x > 0.0 { x } else { 0.01 * x },
         //! ).unwrap();
         //! ```
-        let data = parse_neur_file(path)?;
-        let mut network = Network::new(data.0.clone(), activation_fn, (0.0, 0.0), (0.0, 0.0));
-        let mut layer_i = 0;
-        for layer in network.get_layers_mut().iter_mut() {
-            for neuron_i in 0..layer.len() {
-                for prev_neuron_i in 0..layer.prev_layer_len() {
-                    layer.set_weight(
-                        neuron_i,
-                        prev_neuron_i,
-                        data.1[layer_i][neuron_i][prev_neuron_i],
-                    );
+        Network::from_neur_data(parse_neur_file(path)?, Activation::custom(activation_fn))
+    }
+    pub fn read_from<R: Read, AF: Fn(f64) -> f64 + 'static>(
+        reader: R,
+        activation_fn: AF,
+    ) -> Option<Network> {
+        //! Reads a network in the .neur format from any reader. Like [`Network::load`], the activation function has to be specified. Returns None if the data is not a valid network.
+        Network::from_neur_data(parse_neur(reader)?, Activation::custom(activation_fn))
+    }
+    fn to_neur_data(&self) -> NeurData {
+        let mut data: NeurData = (self.get_shape().clone(), vec![], vec![], vec![], vec![]);
+        let dense_layers: Option<Vec<&Dense>> = match self.has_dropout() {
+            true => None,
+            false => self
+                .layers
+                .iter()
+                .map(|layer| layer.downcast_ref::<Dense>())
+                .collect(),
+        };
+        match dense_layers {
+            //Networks of only dense layers without dropout are saved in the original format
+            Some(dense_layers) => {
+                for layer in dense_layers {
+                    data.1.push(layer.weights().clone());
+                    data.2.push(layer.biases().clone());
                 }
-                layer.set_bias(neuron_i, data.2[layer_i][neuron_i]);
             }
-            layer_i += 1;
+            None => {
+                for (layer, dropout) in self.layers.iter().zip(self.dropout.iter()) {
+                    let mut layer = layer.serialize();
+                    if let Some(dropout) = dropout {
+                        let (name, chance) = dropout.setting();
+                        layer = layer.with_setting(name, chance);
+                    }
+                    data.4.push((layer.kind, layer.settings, layer.tensors));
+                }
+            }
+        }
+        for scaler in [&self.input_scaler, &self.output_scaler] {
+            data.3.push(match scaler {
+                Some(scaler) => vec![scaler.center().clone(), scaler.scale().clone()],
+                None => vec![],
+            });
+        }
+        data
+    }
+    fn from_neur_data(data: NeurData, activation: Activation) -> Option<Network> {
+        let (shape, weights, biases, scalers, layer_records) = data;
+        let mut dropout: Vec<Option<Dropout>> = vec![];
+        let layers: Vec<Box<dyn Layer>> = if layer_records.is_empty() {
+            if weights.len() != biases.len() {
+                return None;
+            }
+            let mut layers: Vec<Box<dyn Layer>> = vec![];
+            for (weights, biases) in weights.into_iter().zip(biases) {
+                layers.push(Box::new(Dense::from_weights(weights, biases)?));
+            }
+            layers
+        } else {
+            let mut layers: Vec<Box<dyn Layer>> = vec![];
+            for (kind, mut settings, tensors) in layer_records {
+                dropout.push(Dropout::take_setting(&mut settings));
+                let layer = LayerData {
+                    kind,
+                    settings,
+                    tensors,
+                };
+                layers.push(layer.to_layer()?);
+            }
+            layers
+        };
+        let mut network = Network::from_layers(layers, activation)?;
+        if network.shape != shape {
+            return None;
+        }
+        if !dropout.is_empty() {
+            network.dropout = dropout;
+        }
+        let mut scalers = scalers.into_iter().map(|mut scaler| match scaler.len() {
+            0 => Some(None),
+            2 => {
+                let scale = scaler.pop()?;
+                Some(Some(Scaler::new(scaler.pop()?, scale)?))
+            }
+            _ => None,
+        });
+        network.input_scaler = scalers.next().unwrap_or(Some(None))?;
+        network.output_scaler = scalers.next().unwrap_or(Some(None))?;
+        if network
+            .input_scaler
+            .as_ref()
+            .is_some_and(|scaler| scaler.len() != network.shape[0])
+            || network
+                .output_scaler
+                .as_ref()
+                .is_some_and(|scaler| scaler.len() != *network.shape.last().unwrap())
+        {
+            return None;
         }
         Some(network)
     }
     pub fn pulse(&self, input: Vec<f64>) -> Vec<f64> {
         //! Is the function for running/passing data through a network. The input is a vector of all the floats to pass to the input neuron, and the output is the values of all the output neurons.
         //! ```
+        //! # use neurnet::*;
         //! let mut nn = Network::new(
         //!     vec![1, 5, 4, 5, 4, 6, 2],
         //!     |x| if x > 0.0 { x } else { 0.01 * x },
@@ -153,14 +368,28 @@ impl Network {
         //! <ul>
         //! <li> If the input vector's length is not equal to the amount of input neurons.
         //! </ul>
+        self.pulse_masked(input, None)
+    }
+    fn pulse_masked(&self, input: Vec<f64>, masks: Option<&DropoutMasks>) -> Vec<f64> {
         if input.len() != self.shape[0] {
             panic!("Network was passed more inputs than there are neurons in the first layer of the network");
         }
-        let mut layer_output = input;
-        for layer in self.layers.iter() {
-            layer_output = layer.pulse(layer_output, &self.activation_fn);
+        let mut layer_output = match &self.input_scaler {
+            Some(scaler) => scaler.transform(&input),
+            None => input,
+        };
+        for (layer_i, layer) in self.layers.iter().enumerate() {
+            layer_output = layer.forward(&layer_output, &self.activation);
+            self.apply_dropout(
+                layer_i,
+                &mut layer_output,
+                masks.map(|masks| &masks[layer_i]),
+            );
+        }
+        match &self.output_scaler {
+            Some(scaler) => scaler.inverse_transform(&layer_output),
+            None => layer_output,
         }
-        layer_output
     }
     pub fn set_weight(
         &mut self,
@@ -173,10 +402,9 @@ impl Network {
         //! # Panics
         //! <ul>
         //! <li> Attempting to mutate the weight to or from a non-existant neuron.
+        //! <li> If the layer is not a [`Dense`] layer.
         //! </ul>
-        self.layers
-            .get_mut(layer)
-            .unwrap()
+        self.dense_mut(layer)
             .set_weight(neuron, prev_layer_neuron, weight);
     }
     pub fn get_weight(
@@ -185,8 +413,10 @@ impl Network {
         neuron: usize,
         prev_layer_neuron: usize,
     ) -> Option<&f64> {
+        //! Returns None if the weight doesn't exist or the layer is not a [`Dense`] layer.
         self.layers
             .get(layer)?
+            .downcast_ref::<Dense>()?
             .get_weight(neuron, prev_layer_neuron)
     }
     pub fn set_bias(&mut self, layer: usize, neuron: usize, bias: f64) {
@@ -194,93 +424,123 @@ impl Network {
         //! # Panics
         //! <ul>
         //! <li> Attempting to mutate the bias to or from a non-existant neuron.
+        //! <li> If the layer is not a [`Dense`] layer.
         //! </ul>
-        self.layers.get_mut(layer).unwrap().set_bias(neuron, bias);
+        self.dense_mut(layer).set_bias(neuron, bias);
     }
     pub fn get_bias(&self, layer: usize, neuron: usize) -> Option<&f64> {
-        self.layers.get(layer)?.get_bias(neuron)
+        self.layers
+            .get(layer)?
+            .downcast_ref::<Dense>()?
+            .get_bias(neuron)
     }
-    pub fn get_shape(&self) -> &Vec<usize> {
-        &self.shape
+    fn dense_mut(&mut self, layer: usize) -> &mut Dense {
+        self.layers
+            .get_mut(layer)
+            .expect("A viable layer ID")
+            .downcast_mut::<Dense>()
+            .expect("A dense layer")
     }
-    pub fn get_layers(&self) -> &Vec<Layer> {
-        &self.layers
+    pub fn get_activation(&self) -> &Activation {
+        &self.activation
     }
-    pub fn get_layers_mut(&mut self) -> &mut Vec<Layer> {
-        &mut self.layers
+    pub fn set_activation(&mut self, activation: Activation) {
+        self.activation = activation;
     }
-    pub fn randomize(&mut self, weights_range: (f64, f64), biases_range: (f64, f64)) {
-        //! Randomizes the entire network.
-        for layer in self.layers.iter_mut() {
-            layer.randomize(weights_range, biases_range);
-        }
-    }
-}
+    pub fn set_input_scaler(&mut self, scaler: Option<Scaler>) {
+        /*!
+        Sets the scaler that [`Network::pulse`] runs raw inputs through before the first layer. It is saved with the network, so a loaded network takes the same raw inputs. Since training also goes through `pulse`, the network can be trained on the unscaled dataset.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::gen_from_fn(
+            |x| vec![0.5 * x[0]],
+            (-200..200).map(|x| vec![x as f64]).collect(),
+            vec![],
+        );
+        let mut nn = Network::new(vec![1, 3, 1], |x| x.tanh(), (-1.0, 1.0), (-1.0, 1.0));
+        nn.set_input_scaler(Some(Scaler::fit_inputs(ScalerKind::Standard, &ds)));
+        nn.set_output_scaler(Some(Scaler::fit_outputs(ScalerKind::Standard, &ds)));
 
-impl Layer {
-    pub fn new(layer_size: usize, prev_layer_size: usize) -> Layer {
-        Layer {
-            weights: vec![vec![1.0; prev_layer_size]; layer_size],
-            biases: vec![0.0; layer_size],
+        let mut buf: Vec<u8> = vec![];
+        nn.write_to(&mut buf).unwrap();
+        let loaded = Network::read_from(buf.as_slice(), |x| x.tanh()).unwrap();
+        assert_eq!(loaded.get_input_scaler(), nn.get_input_scaler());
+        assert_eq!(loaded.pulse(vec![150.0]), nn.pulse(vec![150.0]));
+        ```
+        # Panics
+        <ul>
+        <li> If the scaler's length is not equal to the amount of input neurons.
+        </ul>
+        */
+        if let Some(scaler) = &scaler {
+            assert_eq!(
+                scaler.len(),
+                self.shape[0],
+                "Scaler length must match the input layer"
+            );
         }
+        self.input_scaler = scaler;
     }
-    pub fn pulse(&self, input: Vec<f64>, activation_fn: &Box<dyn Fn(f64) -> f64>) -> Vec<f64> {
-        let mut output_buf: Vec<f64> = vec![];
-        for neuron_indx in 0..self.biases.len() {
-            //For every neuron in layer
-            output_buf.push((activation_fn)(
-                {
-                    //Sum the weighted inputs
-                    let mut sum: f64 = 0.0;
-                    for input_indx in 0..input.len() {
-                        //for every input
-                        sum += input[input_indx]
-                            * *(self.get_weight(neuron_indx, input_indx).unwrap());
-                    }
-                    sum
-                } + self.get_bias(neuron_indx).unwrap(),
-            ));
+    pub fn get_input_scaler(&self) -> Option<&Scaler> {
+        self.input_scaler.as_ref()
+    }
+    pub fn set_output_scaler(&mut self, scaler: Option<Scaler>) {
+        /*!
+        Sets the scaler whose [`Scaler::inverse_transform`] [`Network::pulse`] applies to the last layer's outputs, so a network can learn scaled targets while still returning values in their original range. It is saved with the network.
+        # Panics
+        <ul>
+        <li> If the scaler's length is not equal to the amount of output neurons.
+        </ul>
+        */
+        if let Some(scaler) = &scaler {
+            assert_eq!(
+                scaler.len(),
+                *self.shape.last().unwrap(),
+                "Scaler length must match the output layer"
+            );
         }
-        output_buf
+        self.output_scaler = scaler;
     }
-    pub fn set_weight(&mut self, neuron: usize, prev_layer_neuron: usize, weight: f64) {
-        *(self
-            .weights
-            .get_mut(neuron)
-            .expect("A viable neuron ID")
-            .get_mut(prev_layer_neuron)
-            .expect("A viable ID to a neuron in the previous layer")) = weight;
+    pub fn get_output_scaler(&self) -> Option<&Scaler> {
+        self.output_scaler.as_ref()
     }
-    pub fn get_weight(&self, neuron: usize, prev_layer_neuron: usize) -> Option<&f64> {
-        Some(self.weights.get(neuron)?.get(prev_layer_neuron)?)
+    pub fn get_shape(&self) -> &Vec<usize> {
+        &self.shape
     }
-    pub fn set_bias(&mut self, neuron: usize, bias: f64) {
-        *(self.biases.get_mut(neuron).expect("A valid neuron ID")) = bias;
+    pub fn get_layers(&self) -> &[Box<dyn Layer>] {
+        &self.layers
     }
-    pub fn get_bias(&self, neuron: usize) -> Option<&f64> {
-        Some(self.biases.get(neuron)?)
+    pub fn get_layers_mut(&mut self) -> &mut [Box<dyn Layer>] {
+        //! Gives mutable access to the layers, such as to set a [`Dense`] layer's weights through `downcast_mut`. A layer must not be replaced by one with different input or output lengths.
+        &mut self.layers
     }
     pub fn randomize(&mut self, weights_range: (f64, f64), biases_range: (f64, f64)) {
-        fn rand_float(range: (f64, f64)) -> f64 {
-            use rand::random;
-            let mut buf: f64 = random();
-            buf *= range.1 - range.0;
-            buf += range.0;
-            buf
-        }
-        for neuron in self.weights.iter_mut() {
-            for weight in neuron.iter_mut() {
-                *weight = rand_float(weights_range);
-            }
-        }
-        for bias in self.biases.iter_mut() {
-            *bias = rand_float(biases_range);
+        //! Randomizes the entire network.
+        for layer in self.layers.iter_mut() {
+            layer.randomize(weights_range, biases_range);
         }
     }
-    pub fn len(&self) -> usize {
-        self.biases.len()
-    }
-    pub fn prev_layer_len(&self) -> usize {
-        self.weights.get(0).unwrap().len()
+    pub fn randomize_seeded(
+        &mut self,
+        weights_range: (f64, f64),
+        biases_range: (f64, f64),
+        seed: u64,
+    ) {
+        /*!
+        Randomizes the entire network like [`Network::randomize`]. The same seed always gives the same weights and biases, which keeps examples and tests from depending on a random draw.
+        ```
+        # use neurnet::*;
+        let mut a = Network::from_layers(vec![Box::new(Rnn::new(3, 2)), Box::new(Dense::new(1, 3))], Activation::Tanh).unwrap();
+        let mut b = a.clone();
+        a.randomize_seeded((-0.5, 0.5), (-0.1, 0.1), 7);
+        b.randomize_seeded((-0.5, 0.5), (-0.1, 0.1), 7);
+        assert_eq!(a.get_layers()[0].parameters(), b.get_layers()[0].parameters());
+        assert!(a.get_layers()[1].parameters().weights.iter().all(|w| w.abs() <= 0.5));
+        ```
+        */
+        let mut rng = StdRng::seed_from_u64(seed);
+        for layer in self.layers.iter_mut() {
+            layer.randomize_with(weights_range, biases_range, &mut rng);
+        }
     }
 }
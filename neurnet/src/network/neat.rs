@@ -0,0 +1,925 @@
+use super::super::files::{
+    parse_genome, parse_genome_file, write_genome, write_genome_file, GenomeData, SaveMode,
+};
+use super::activation::Activation;
+use super::graph::Graph;
+use super::layers::{Dense, Layer, LayerParameters};
+use super::training::evolution::gaussian;
+use rand::Rng;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// What a [`NodeGene`] is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeKind {
+    Input,
+    Hidden,
+    Output,
+}
+
+/// A node of a [`Genome`]. Every node but the inputs adds its bias to the weighted sum of its enabled incoming connections, and applies the activation function to it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeGene {
+    pub id: usize,
+    pub kind: NodeKind,
+    pub bias: f64,
+}
+
+/// A weighted connection between two nodes of a [`Genome`]. Its innovation number is shared by every connection between the same two nodes in a [`Neat`] population, which is what crossover and the compatibility distance line genomes up by.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionGene {
+    pub innovation: usize,
+    pub from: usize,
+    pub to: usize,
+    pub weight: f64,
+    /// Disabled connections are kept in the genome, so crossover can turn them back on, but carry no signal.
+    pub enabled: bool,
+}
+
+/// The description of a feedforward network whose topology evolves, as in NeuroEvolution of Augmenting Topologies (NEAT): a list of nodes and the connections between them. Mutations add connections and split connections with new nodes, but never make a cycle.
+///
+/// A genome is run by converting it to a [`Graph`] with [`Genome::to_graph`], which can then be pulsed and saved like any other graph. The genome itself is saved with [`Genome::save`], so it can be bred further later.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "GenomeFields"))]
+pub struct Genome {
+    inputs: usize,
+    outputs: usize,
+    nodes: Vec<NodeGene>,
+    connections: Vec<ConnectionGene>, //Sorted by innovation number
+}
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct GenomeFields {
+    inputs: usize,
+    outputs: usize,
+    nodes: Vec<NodeGene>,
+    connections: Vec<ConnectionGene>,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<GenomeFields> for Genome {
+    type Error = String;
+    fn try_from(data: GenomeFields) -> Result<Genome, String> {
+        let genome = Genome {
+            inputs: data.inputs,
+            outputs: data.outputs,
+            nodes: data.nodes,
+            connections: data.connections,
+        };
+        match genome.is_valid() {
+            true => Ok(genome),
+            false => Err("the genes don't describe a valid genome".to_string()),
+        }
+    }
+}
+
+/// The options used by [`Neat`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NeatOptions {
+    /// Genomes whose compatibility distance to a species' representative is below this join the species.
+    pub compatibility_threshold: f64,
+    /// How much connections past the end of the other genome's innovation numbers count towards the compatibility distance.
+    pub excess_coefficient: f64,
+    /// How much connections missing from the other genome, within its innovation numbers, count towards the compatibility distance.
+    pub disjoint_coefficient: f64,
+    /// How much the average weight difference of the connections both genomes have counts towards the compatibility distance.
+    pub weight_coefficient: f64,
+    /// The chance a child's weights and biases are mutated.
+    pub weight_mutation_rate: f64,
+    /// The chance each mutated weight or bias is replaced by a new random value rather than nudged.
+    pub weight_replace_rate: f64,
+    /// The standard deviation of the Gaussian noise a nudge adds.
+    pub weight_perturbation: f64,
+    /// The chance a child gets a new connection between two nodes that weren't connected.
+    pub add_connection_rate: f64,
+    /// The chance a child gets a new node, splitting one of its connections in two.
+    pub add_node_rate: f64,
+    /// The chance a child is bred from two parents of its species rather than copied from one.
+    pub crossover_rate: f64,
+    /// The fraction of each species, fittest first, allowed to be parents.
+    pub survival_rate: f64,
+    /// How many of the fittest genomes of each species are carried into the next generation unchanged.
+    pub elitism: usize,
+    /// How many generations a species may go without improving its best fitness before it is dropped. The species with the fittest genome is never dropped.
+    pub stagnation_limit: usize,
+}
+impl Default for NeatOptions {
+    fn default() -> NeatOptions {
+        NeatOptions {
+            compatibility_threshold: 3.0,
+            excess_coefficient: 1.0,
+            disjoint_coefficient: 1.0,
+            weight_coefficient: 0.4,
+            weight_mutation_rate: 0.8,
+            weight_replace_rate: 0.1,
+            weight_perturbation: 0.5,
+            add_connection_rate: 0.1,
+            add_node_rate: 0.05,
+            crossover_rate: 0.75,
+            survival_rate: 0.2,
+            elitism: 1,
+            stagnation_limit: 15,
+        }
+    }
+}
+
+/// Hands out innovation numbers and node ids, so the same structural change gets the same numbers in every genome of a population.
+#[derive(Debug, Clone)]
+struct Innovations {
+    connections: HashMap<(usize, usize), usize>,
+    splits: HashMap<usize, usize>, //The node made by splitting the connection with the innovation number
+    next_node: usize,
+}
+
+impl Innovations {
+    fn new(inputs: usize, outputs: usize) -> Innovations {
+        let mut innovations = Innovations {
+            connections: HashMap::new(),
+            splits: HashMap::new(),
+            next_node: inputs + outputs,
+        };
+        for from in 0..inputs {
+            for to in inputs..inputs + outputs {
+                innovations.connection(from, to);
+            }
+        }
+        innovations
+    }
+    fn connection(&mut self, from: usize, to: usize) -> usize {
+        let next = self.connections.len();
+        *self.connections.entry((from, to)).or_insert(next)
+    }
+    fn split(&mut self, innovation: usize, genome: &Genome) -> usize {
+        //A genome that split the connection before, and had it turned back on by crossover, needs a fresh node
+        match self.splits.get(&innovation) {
+            Some(id) if genome.node(*id).is_none() => *id,
+            known => {
+                let id = self.next_node;
+                self.next_node += 1;
+                if known.is_none() {
+                    self.splits.insert(innovation, id);
+                }
+                id
+            }
+        }
+    }
+}
+
+impl Genome {
+    pub fn new(inputs: usize, outputs: usize) -> Genome {
+        /*!
+        Creates a genome with every input connected to every output and no hidden nodes, the starting point of NEAT. The inputs get the node ids `0..inputs` and the outputs the ids after them, and every weight and bias starts at zero.
+        ```
+        # use neurnet::*;
+        let genome = Genome::new(2, 1);
+        assert_eq!(genome.nodes().len(), 3);
+        assert_eq!(genome.connections().len(), 2);
+        assert_eq!(genome.to_graph(Activation::Sigmoid).pulse(vec![1.0, -1.0]), vec![0.5]);
+        ```
+        # Panics
+        <ul>
+        <li> If there are no inputs or no outputs.
+        </ul>
+        */
+        assert!(
+            inputs > 0 && outputs > 0,
+            "A genome needs at least one input and one output"
+        );
+        let nodes = (0..inputs + outputs)
+            .map(|id| NodeGene {
+                id,
+                kind: if id < inputs {
+                    NodeKind::Input
+                } else {
+                    NodeKind::Output
+                },
+                bias: 0.0,
+            })
+            .collect();
+        let connections = (0..inputs * outputs)
+            .map(|innovation| ConnectionGene {
+                innovation,
+                from: innovation / outputs,
+                to: inputs + innovation % outputs,
+                weight: 0.0,
+                enabled: true,
+            })
+            .collect();
+        Genome {
+            inputs,
+            outputs,
+            nodes,
+            connections,
+        }
+    }
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+    pub fn nodes(&self) -> &[NodeGene] {
+        &self.nodes
+    }
+    pub fn connections(&self) -> &[ConnectionGene] {
+        //! The genome's connections, in order of innovation number.
+        &self.connections
+    }
+    pub fn node(&self, id: usize) -> Option<&NodeGene> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+    pub fn compatibility(&self, other: &Genome, options: &NeatOptions) -> f64 {
+        /*!
+        The compatibility distance between two genomes, which [`Neat`] groups genomes into species by: the excess and disjoint connections, each divided by the connection count of the larger genome if it has at least 20, plus the average weight difference of the matching connections, weighed by the coefficients in the options.
+        ```
+        # use neurnet::*;
+        let options = NeatOptions::default();
+        let genome = Genome::new(2, 1);
+        assert_eq!(genome.compatibility(&genome, &options), 0.0);
+        ```
+        */
+        let others: HashMap<usize, &ConnectionGene> = other
+            .connections
+            .iter()
+            .map(|gene| (gene.innovation, gene))
+            .collect();
+        let last = |genome: &Genome| genome.connections.last().map(|gene| gene.innovation);
+        let (own_last, other_last) = (last(self), last(other));
+        let (mut excess, mut disjoint, mut matching, mut weight_difference) = (0, 0, 0, 0.0);
+        for gene in self.connections.iter() {
+            match others.get(&gene.innovation) {
+                Some(other) => {
+                    matching += 1;
+                    weight_difference += (gene.weight - other.weight).abs();
+                }
+                None if Some(gene.innovation) > other_last => excess += 1,
+                None => disjoint += 1,
+            }
+        }
+        for gene in other.connections.iter() {
+            if self
+                .connections
+                .binary_search_by_key(&gene.innovation, |gene| gene.innovation)
+                .is_err()
+            {
+                match Some(gene.innovation) > own_last {
+                    true => excess += 1,
+                    false => disjoint += 1,
+                }
+            }
+        }
+        //As in the NEAT paper, small genomes' counts aren't divided
+        let size = match self.connections.len().max(other.connections.len()) {
+            len if len < 20 => 1.0,
+            len => len as f64,
+        };
+        let average_difference = match matching {
+            0 => 0.0,
+            _ => weight_difference / matching as f64,
+        };
+        options.excess_coefficient * excess as f64 / size
+            + options.disjoint_coefficient * disjoint as f64 / size
+            + options.weight_coefficient * average_difference
+    }
+    pub fn to_graph(&self, activation: Activation) -> Graph {
+        /*!
+        Builds a [`Graph`] that runs the genome: an input node for each of its inputs, and a [`Dense`] layer with a single neuron for each of its other nodes, taking the nodes it has enabled connections from. The graph takes the genome's inputs and returns its outputs, in order of node id, and can be saved with [`Graph::save`].
+        ```
+        # use neurnet::*;
+        let mut neat = Neat::new(2, 1, 20, Activation::Sigmoid, NeatOptions::default());
+        neat.evolve(|graph| -graph.pulse(vec![1.0, 0.0])[0], 10);
+        let (genome, _) = neat.best();
+        let graph = genome.to_graph(Activation::Sigmoid);
+        assert_eq!(graph.input_lens(), vec![1, 1]);
+        assert_eq!(graph.output_lens(), vec![1]);
+
+        let mut buf: Vec<u8> = vec![];
+        graph.write_to(&mut buf).unwrap();
+        let loaded = Graph::read_from(buf.as_slice(), |x| 1.0 / (1.0 + (-x).exp())).unwrap();
+        assert_eq!(loaded.pulse(vec![0.3, 0.7]), graph.pulse(vec![0.3, 0.7]));
+        ```
+        */
+        let mut graph = Graph::new(activation);
+        let mut graph_nodes: HashMap<usize, usize> = HashMap::new();
+        for id in 0..self.inputs {
+            graph_nodes.insert(id, graph.input(1));
+        }
+        for id in self.order() {
+            let node = self.node(id).unwrap();
+            if node.kind == NodeKind::Input {
+                continue;
+            }
+            let incoming: Vec<&ConnectionGene> = self
+                .connections
+                .iter()
+                .filter(|gene| gene.enabled && gene.to == id)
+                .collect();
+            //A node without incoming connections still needs an input, which it ignores
+            let (source, weights) = match incoming.as_slice() {
+                [] => (graph_nodes[&0], vec![0.0]),
+                [gene] => (graph_nodes[&gene.from], vec![gene.weight]),
+                _ => (
+                    graph.concatenate(
+                        &incoming
+                            .iter()
+                            .map(|gene| graph_nodes[&gene.from])
+                            .collect::<Vec<usize>>(),
+                    ),
+                    incoming.iter().map(|gene| gene.weight).collect(),
+                ),
+            };
+            let mut layer = Dense::new(1, weights.len());
+            layer.set_parameters(&LayerParameters {
+                weights,
+                biases: vec![node.bias],
+                rows: vec![],
+            });
+            graph_nodes.insert(id, graph.layer(Box::new(layer), source));
+        }
+        graph.set_outputs(
+            &(self.inputs..self.inputs + self.outputs)
+                .map(|id| graph_nodes[&id])
+                .collect::<Vec<usize>>(),
+        );
+        graph
+    }
+    pub fn save(&self, path: &str) -> Option<()> {
+        //! Saves the genome to the path specified, replacing any file that is already there, like [`Graph::save`]. Will return None if saving fails for any reason.
+        self.save_with_mode(path, SaveMode::Overwrite)?;
+        Some(())
+    }
+    pub fn save_with_mode(&self, path: &str, mode: SaveMode) -> Option<String> {
+        //! Atomically saves the genome to the path specified, with the [`SaveMode`] deciding what happens if the path already exists. Returns the path the genome was actually saved to, or None if saving failed.
+        write_genome_file(path, mode, self.to_genome_data())
+    }
+    pub fn write_to<W: Write>(&self, writer: W) -> Option<()> {
+        /*!
+        Writes the genome to any writer, so it can be stored and bred further later. The format is made of lists like a .neur file: the amount of inputs and outputs, every node as its id, kind and bias, and every connection as its innovation number, the nodes it goes from and to, its weight and whether it is enabled.
+        ```
+        # use neurnet::*;
+        let mut neat = Neat::new(2, 1, 20, Activation::Sigmoid, NeatOptions::default());
+        neat.evolve(|graph| -graph.pulse(vec![1.0, 0.0])[0], 5);
+        let (genome, _) = neat.best();
+
+        let mut buf: Vec<u8> = vec![];
+        genome.write_to(&mut buf).unwrap();
+        assert_eq!(&Genome::read_from(buf.as_slice()).unwrap(), genome);
+
+        // Connections have to point at nodes of the genome
+        assert!(Genome::read_from("[1,1],\n[[0,input,0],[1,output,0]],\n[[0,0,2,1,1]]".as_bytes()).is_none());
+        ```
+        */
+        write_genome(writer, self.to_genome_data())
+    }
+    pub fn load(path: &str) -> Option<Genome> {
+        //! Loads a genome from the path specified. Returns None if loading failed.
+        Genome::from_genome_data(parse_genome_file(path)?)
+    }
+    pub fn read_from<R: Read>(reader: R) -> Option<Genome> {
+        //! Reads a genome from any reader. Returns None if the data is not a valid genome.
+        Genome::from_genome_data(parse_genome(reader)?)
+    }
+    fn to_genome_data(&self) -> GenomeData {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let kind = match node.kind {
+                    NodeKind::Input => "input",
+                    NodeKind::Hidden => "hidden",
+                    NodeKind::Output => "output",
+                };
+                (node.id, kind.to_string(), node.bias)
+            })
+            .collect();
+        let connections = self
+            .connections
+            .iter()
+            .map(|gene| {
+                (
+                    gene.innovation,
+                    gene.from,
+                    gene.to,
+                    gene.weight,
+                    gene.enabled,
+                )
+            })
+            .collect();
+        ([self.inputs, self.outputs], nodes, connections)
+    }
+    fn from_genome_data(data: GenomeData) -> Option<Genome> {
+        let ([inputs, outputs], node_records, connection_records) = data;
+        let mut nodes: Vec<NodeGene> = vec![];
+        for (id, kind, bias) in node_records {
+            let kind = match kind.as_str() {
+                "input" => NodeKind::Input,
+                "hidden" => NodeKind::Hidden,
+                "output" => NodeKind::Output,
+                _ => return None,
+            };
+            nodes.push(NodeGene { id, kind, bias });
+        }
+        let connections = connection_records
+            .into_iter()
+            .map(|(innovation, from, to, weight, enabled)| ConnectionGene {
+                innovation,
+                from,
+                to,
+                weight,
+                enabled,
+            })
+            .collect();
+        let genome = Genome {
+            inputs,
+            outputs,
+            nodes,
+            connections,
+        };
+        match genome.is_valid() {
+            true => Some(genome),
+            false => None,
+        }
+    }
+    fn is_valid(&self) -> bool {
+        //Holds for every genome built by Genome::new and mutated or bred by Neat
+        let io_len = match self.inputs.checked_add(self.outputs) {
+            Some(len) if self.inputs > 0 && self.outputs > 0 => len,
+            _ => return false,
+        };
+        let mut kinds: HashMap<usize, NodeKind> = HashMap::new();
+        for node in self.nodes.iter() {
+            let kind = match node.id {
+                id if id < self.inputs => NodeKind::Input,
+                id if id < io_len => NodeKind::Output,
+                _ => NodeKind::Hidden,
+            };
+            if node.kind != kind || kinds.insert(node.id, kind).is_some() {
+                return false;
+            }
+        }
+        let connected = |gene: &ConnectionGene| {
+            kinds.contains_key(&gene.from)
+                && kinds
+                    .get(&gene.to)
+                    .is_some_and(|kind| *kind != NodeKind::Input)
+                && gene.from != gene.to
+        };
+        (0..io_len).all(|id| kinds.contains_key(&id))
+            && self.connections.iter().all(connected)
+            && self
+                .connections
+                .windows(2)
+                .all(|pair| pair[0].innovation < pair[1].innovation)
+            && self.order().len() == self.nodes.len()
+    }
+    fn order(&self) -> Vec<usize> {
+        //The node ids in an order where every connection goes forwards
+        let mut incoming: HashMap<usize, usize> =
+            self.nodes.iter().map(|node| (node.id, 0)).collect();
+        for gene in self.connections.iter() {
+            *incoming.get_mut(&gene.to).unwrap() += 1;
+        }
+        let mut ready: Vec<usize> = self
+            .nodes
+            .iter()
+            .map(|node| node.id)
+            .filter(|id| incoming[id] == 0)
+            .rev()
+            .collect();
+        let mut order = vec![];
+        while let Some(id) = ready.pop() {
+            order.push(id);
+            for gene in self.connections.iter().filter(|gene| gene.from == id) {
+                let count = incoming.get_mut(&gene.to).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(gene.to);
+                }
+            }
+        }
+        order
+    }
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        //Whether there is a path between the nodes, counting disabled connections, which crossover can turn back on
+        let mut stack = vec![from];
+        let mut seen = vec![from];
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+            for gene in self.connections.iter().filter(|gene| gene.from == id) {
+                if !seen.contains(&gene.to) {
+                    seen.push(gene.to);
+                    stack.push(gene.to);
+                }
+            }
+        }
+        false
+    }
+    fn push_connection(
+        &mut self,
+        innovations: &mut Innovations,
+        from: usize,
+        to: usize,
+        weight: f64,
+    ) {
+        let innovation = innovations.connection(from, to);
+        self.connections.push(ConnectionGene {
+            innovation,
+            from,
+            to,
+            weight,
+            enabled: true,
+        });
+        self.connections.sort_by_key(|gene| gene.innovation);
+    }
+    fn add_connection(&mut self, innovations: &mut Innovations, rng: &mut impl Rng) {
+        let candidates: Vec<(usize, usize)> = self
+            .nodes
+            .iter()
+            .flat_map(|from| self.nodes.iter().map(move |to| (from, to)))
+            .filter(|(from, to)| {
+                to.kind != NodeKind::Input
+                    && from.id != to.id
+                    && !self
+                        .connections
+                        .iter()
+                        .any(|gene| gene.from == from.id && gene.to == to.id)
+                    && !self.reaches(to.id, from.id)
+            })
+            .map(|(from, to)| (from.id, to.id))
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+        let (from, to) = candidates[rng.gen_range(0..candidates.len())];
+        self.push_connection(innovations, from, to, gaussian(rng));
+    }
+    fn add_node(&mut self, innovations: &mut Innovations, rng: &mut impl Rng) {
+        let enabled: Vec<usize> = (0..self.connections.len())
+            .filter(|i| self.connections[*i].enabled)
+            .collect();
+        if enabled.is_empty() {
+            return;
+        }
+        let split = &mut self.connections[enabled[rng.gen_range(0..enabled.len())]];
+        split.enabled = false;
+        let (innovation, from, to, weight) = (split.innovation, split.from, split.to, split.weight);
+        let id = innovations.split(innovation, self);
+        self.nodes.push(NodeGene {
+            id,
+            kind: NodeKind::Hidden,
+            bias: 0.0,
+        });
+        //The new node starts out passing the old connection's signal on, apart from the activation
+        self.push_connection(innovations, from, id, 1.0);
+        self.push_connection(innovations, id, to, weight);
+    }
+    fn mutate(&mut self, innovations: &mut Innovations, options: &NeatOptions, rng: &mut impl Rng) {
+        if rng.gen::<f64>() < options.weight_mutation_rate {
+            let biases = self
+                .nodes
+                .iter_mut()
+                .filter(|node| node.kind != NodeKind::Input)
+                .map(|node| &mut node.bias);
+            for gene in self
+                .connections
+                .iter_mut()
+                .map(|gene| &mut gene.weight)
+                .chain(biases)
+            {
+                if rng.gen::<f64>() < options.weight_replace_rate {
+                    *gene = gaussian(rng);
+                } else {
+                    *gene += options.weight_perturbation * gaussian(rng);
+                }
+            }
+        }
+        if rng.gen::<f64>() < options.add_connection_rate {
+            self.add_connection(innovations, rng);
+        }
+        if rng.gen::<f64>() < options.add_node_rate {
+            self.add_node(innovations, rng);
+        }
+    }
+    fn crossover(&self, other: &Genome, rng: &mut impl Rng) -> Genome {
+        //The child gets the fitter parent's (self's) topology, with matching genes taken from either parent at random
+        let others: HashMap<usize, &ConnectionGene> = other
+            .connections
+            .iter()
+            .map(|gene| (gene.innovation, gene))
+            .collect();
+        let connections = self
+            .connections
+            .iter()
+            .map(|gene| match others.get(&gene.innovation) {
+                Some(other) => {
+                    let mut child = if rng.gen::<bool>() {
+                        (*other).clone()
+                    } else {
+                        gene.clone()
+                    };
+                    //As in the NEAT paper, a gene disabled in either parent is usually disabled in the child
+                    if !gene.enabled || !other.enabled {
+                        child.enabled = rng.gen::<f64>() < 0.25;
+                    }
+                    child
+                }
+                None => gene.clone(),
+            })
+            .collect();
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| match other.node(node.id) {
+                Some(other) if rng.gen::<bool>() => NodeGene {
+                    bias: other.bias,
+                    ..node.clone()
+                },
+                _ => node.clone(),
+            })
+            .collect();
+        Genome {
+            inputs: self.inputs,
+            outputs: self.outputs,
+            nodes,
+            connections,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Species {
+    representative: Genome,
+    members: Vec<usize>,
+    best_fitness: f64,
+    stagnant: usize,
+}
+
+/// Evolves a population of [`Genome`]s with NEAT, starting from minimal genomes and growing their topologies. Genomes are scored by a fitness function that gets each one as a [`Graph`], grouped into species of similar genomes so new structure gets time to be tuned before it has to compete, and bred within their species, which get offspring in proportion to their average fitness.
+pub struct Neat {
+    genomes: Vec<Genome>,
+    fitness: Vec<f64>,
+    species: Vec<Species>,
+    innovations: Innovations,
+    activation: Activation,
+    options: NeatOptions,
+    generation: usize,
+}
+
+impl Neat {
+    pub fn new(
+        inputs: usize,
+        outputs: usize,
+        size: usize,
+        activation: Activation,
+        options: NeatOptions,
+    ) -> Neat {
+        /*!
+        Creates a population of the given size of minimal genomes, every input connected to every output, with random weights. The activation is the one every node of the genomes' graphs uses.
+        ```
+        # use neurnet::*;
+        let xor = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)];
+        let fitness = |graph: &Graph| -> f64 {
+            4.0 - xor.iter().map(|(inputs, output)| (graph.pulse(inputs.to_vec())[0] - output).powi(2)).sum::<f64>()
+        };
+        let solved = |graph: &Graph| xor.iter().all(|(inputs, output)| (graph.pulse(inputs.to_vec())[0] - output).abs() < 0.5);
+
+        let mut neat = Neat::new(2, 1, 150, Activation::Sigmoid, NeatOptions::default());
+        for _ in 0..300 {
+            neat.step(fitness);
+            if solved(&neat.best().0.to_graph(Activation::Sigmoid)) {
+                break;
+            }
+        }
+        let (genome, _) = neat.best();
+        assert!(solved(&genome.to_graph(Activation::Sigmoid)));
+        // XOR can't be solved without hidden nodes, so NEAT had to add some
+        assert!(genome.nodes().iter().any(|node| node.kind == NodeKind::Hidden));
+        // Every genome belongs to exactly one species
+        let mut members: Vec<usize> = neat.species().concat();
+        members.sort();
+        assert_eq!(members, (0..150).collect::<Vec<usize>>());
+        ```
+        # Panics
+        <ul>
+        <li> If the size is zero, or there are no inputs or no outputs.
+        </ul>
+        */
+        assert!(size > 0, "The population needs at least one genome");
+        let mut rng = rand::thread_rng();
+        let genomes = (0..size)
+            .map(|_| {
+                let mut genome = Genome::new(inputs, outputs);
+                for gene in genome.connections.iter_mut() {
+                    gene.weight = gaussian(&mut rng);
+                }
+                genome
+            })
+            .collect();
+        Neat {
+            genomes,
+            fitness: vec![],
+            species: vec![],
+            innovations: Innovations::new(inputs, outputs),
+            activation,
+            options,
+            generation: 0,
+        }
+    }
+    pub fn genomes(&self) -> &[Genome] {
+        &self.genomes
+    }
+    pub fn fitness(&self) -> &[f64] {
+        //! The fitness of every genome in the population, in the same order, or nothing if the population hasn't been scored yet.
+        &self.fitness
+    }
+    pub fn species(&self) -> Vec<Vec<usize>> {
+        //! The indices of the genomes in each species, as of the last time the population was scored.
+        self.species
+            .iter()
+            .map(|species| species.members.clone())
+            .collect()
+    }
+    pub fn generation(&self) -> usize {
+        //! How many generations have been bred.
+        self.generation
+    }
+    pub fn get_activation(&self) -> &Activation {
+        &self.activation
+    }
+    pub fn get_options(&self) -> &NeatOptions {
+        &self.options
+    }
+    pub fn set_options(&mut self, options: NeatOptions) {
+        self.options = options;
+    }
+    pub fn best(&self) -> (&Genome, f64) {
+        //! The fittest genome of the current population and its fitness, or the first genome and negative infinity if the population hasn't been scored yet.
+        match self.ranking(0..self.fitness.len()).first() {
+            Some(index) => (&self.genomes[*index], self.fitness[*index]),
+            None => (&self.genomes[0], f64::NEG_INFINITY),
+        }
+    }
+    pub fn evaluate<F: FnMut(&Graph) -> f64>(&mut self, mut fitness: F) {
+        //! Scores every genome of the current population with the fitness function, which gets the genome's [`Genome::to_graph`], and sorts the population into species. Higher fitness is fitter, and a NaN fitness counts as the least fit possible.
+        self.fitness = self
+            .genomes
+            .iter()
+            .map(|genome| {
+                let fitness = fitness(&genome.to_graph(self.activation.clone()));
+                if fitness.is_nan() {
+                    f64::NEG_INFINITY
+                } else {
+                    fitness
+                }
+            })
+            .collect();
+        self.speciate();
+    }
+    pub fn step<F: FnMut(&Graph) -> f64>(&mut self, mut fitness: F) -> f64 {
+        //! Breeds the next generation from the current one, scoring the current one first if it hasn't been, and then scores the new generation. Returns the new generation's best fitness.
+        if self.fitness.len() != self.genomes.len() {
+            self.evaluate(&mut fitness);
+        }
+        self.genomes = self.breed();
+        self.generation += 1;
+        self.evaluate(&mut fitness);
+        self.best().1
+    }
+    pub fn evolve<F: FnMut(&Graph) -> f64>(&mut self, mut fitness: F, generations: usize) -> f64 {
+        //! Breeds the given number of generations with [`Neat::step`], and returns the last generation's best fitness.
+        let mut best = f64::NEG_INFINITY;
+        for _ in 0..generations {
+            best = self.step(&mut fitness);
+        }
+        best
+    }
+    fn ranking(&self, members: impl Iterator<Item = usize>) -> Vec<usize> {
+        //The indices of the scored genomes, fittest first
+        let mut ranking: Vec<usize> = members.collect();
+        ranking.sort_by(|a, b| self.fitness[*b].total_cmp(&self.fitness[*a]));
+        ranking
+    }
+    fn speciate(&mut self) {
+        let mut rng = rand::thread_rng();
+        for species in self.species.iter_mut() {
+            species.members.clear();
+        }
+        for (index, genome) in self.genomes.iter().enumerate() {
+            let threshold = self.options.compatibility_threshold;
+            match self.species.iter_mut().find(|species| {
+                genome.compatibility(&species.representative, &self.options) < threshold
+            }) {
+                Some(species) => species.members.push(index),
+                None => self.species.push(Species {
+                    representative: genome.clone(),
+                    members: vec![index],
+                    best_fitness: f64::NEG_INFINITY,
+                    stagnant: 0,
+                }),
+            }
+        }
+        self.species.retain(|species| !species.members.is_empty());
+        for species in self.species.iter_mut() {
+            let best = species
+                .members
+                .iter()
+                .map(|index| self.fitness[*index])
+                .fold(f64::NEG_INFINITY, f64::max);
+            if best > species.best_fitness {
+                species.best_fitness = best;
+                species.stagnant = 0;
+            } else {
+                species.stagnant += 1;
+            }
+            let representative = species.members[rng.gen_range(0..species.members.len())];
+            species.representative = self.genomes[representative].clone();
+        }
+    }
+    fn breed(&mut self) -> Vec<Genome> {
+        let mut rng = rand::thread_rng();
+        let options = self.options.clone();
+        let best = self.ranking(0..self.fitness.len())[0];
+        let species: Vec<Vec<usize>> = self
+            .species
+            .iter()
+            .filter(|species| {
+                species.stagnant < options.stagnation_limit || species.members.contains(&best)
+            })
+            .map(|species| self.ranking(species.members.iter().copied()))
+            .collect();
+
+        //Every species gets offspring in proportion to its average fitness, measured from the least fit genome
+        let least = self
+            .fitness
+            .iter()
+            .copied()
+            .filter(|fitness| fitness.is_finite())
+            .fold(f64::INFINITY, f64::min);
+        let shares: Vec<f64> = species
+            .iter()
+            .map(|members| {
+                members
+                    .iter()
+                    .map(|index| (self.fitness[*index] - least).max(0.0))
+                    .sum::<f64>()
+                    / members.len() as f64
+            })
+            .collect();
+        let total: f64 = shares.iter().sum();
+        let size = self.genomes.len();
+        let quotas: Vec<f64> = shares
+            .iter()
+            .map(|share| match total > 0.0 && total.is_finite() {
+                true => share / total * size as f64,
+                false => size as f64 / species.len() as f64,
+            })
+            .collect();
+        let mut counts: Vec<usize> = quotas.iter().map(|quota| quota.floor() as usize).collect();
+        let mut remainders: Vec<usize> = (0..species.len()).collect();
+        remainders.sort_by(|a, b| {
+            (quotas[*b] - quotas[*b].floor()).total_cmp(&(quotas[*a] - quotas[*a].floor()))
+        });
+        for index in remainders
+            .iter()
+            .cycle()
+            .take(size - counts.iter().sum::<usize>())
+        {
+            counts[*index] += 1;
+        }
+
+        let mut next = vec![];
+        for (members, count) in species.iter().zip(counts) {
+            let elites = options.elitism.min(count).min(members.len());
+            next.extend(
+                members[..elites]
+                    .iter()
+                    .map(|index| self.genomes[*index].clone()),
+            );
+            let parents = &members[..((members.len() as f64 * options.survival_rate).ceil()
+                as usize)
+                .clamp(1, members.len())];
+            for _ in elites..count {
+                let mother = rng.gen_range(0..parents.len());
+                let mut child = match rng.gen::<f64>() < options.crossover_rate {
+                    true => {
+                        //Parents are ranked fittest first, so the earlier one is the fitter
+                        let father = rng.gen_range(0..parents.len());
+                        let (fitter, other) = (mother.min(father), mother.max(father));
+                        self.genomes[parents[fitter]]
+                            .crossover(&self.genomes[parents[other]], &mut rng)
+                    }
+                    false => self.genomes[parents[mother]].clone(),
+                };
+                child.mutate(&mut self.innovations, &options, &mut rng);
+                next.push(child);
+            }
+        }
+        next
+    }
+}
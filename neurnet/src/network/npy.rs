@@ -0,0 +1,94 @@
+use super::super::files::{open_file, write_atomic, SaveMode};
+use super::super::numpy::{read_npz, write_npy, write_npz, NpyArray};
+use super::layers::Dense;
+use super::Network;
+use std::io::{BufReader, Read, Write};
+
+impl Dense {
+    fn weights_array(&self) -> NpyArray {
+        (
+            vec![self.len(), self.prev_layer_len()],
+            self.weights().iter().flatten().copied().collect(),
+        )
+    }
+    fn biases_array(&self) -> NpyArray {
+        (vec![self.len()], self.biases().clone())
+    }
+    pub fn write_weights_npy<W: Write>(&self, writer: W) -> Option<()> {
+        //! Writes the layer's weights as a 2D `.npy` array of shape (neurons in this layer, neurons in the previous layer), the same layout as PyTorch's `Linear.weight`.
+        write_npy(writer, &self.weights_array())
+    }
+    pub fn write_biases_npy<W: Write>(&self, writer: W) -> Option<()> {
+        //! Writes the layer's biases as a 1D `.npy` array.
+        write_npy(writer, &self.biases_array())
+    }
+    pub fn save_npy(&self, weights_path: &str, biases_path: &str) -> Option<()> {
+        //! Saves the layer's weights and biases as two `.npy` files. See [`Dense::write_weights_npy`] for the layout of the weights.
+        write_atomic(weights_path, SaveMode::Overwrite, |file| {
+            self.write_weights_npy(file)
+        })?;
+        write_atomic(biases_path, SaveMode::Overwrite, |file| {
+            self.write_biases_npy(file)
+        })?;
+        Some(())
+    }
+}
+
+impl Network {
+    pub fn save_npz(&self, path: &str) -> Option<()> {
+        /*!
+        Saves every layer's tensors into one `.npz` archive, which `numpy.load` opens as a dict of arrays named `layers.{i}.{tensor}`, where layer i is `get_layers()[i]` and the tensors are the ones in its [`Layer::serialize`](super::layers::Layer::serialize). For a [`Dense`] layer they are `layers.{i}.weight` and `layers.{i}.bias`, with the weights laid out as described in [`Dense::write_weights_npy`].
+        ```no_run
+        # use neurnet::*;
+        let nn = Network::new(vec![2, 3, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        nn.save_npz("weights.npz").unwrap();
+        ```
+        */
+        write_atomic(path, SaveMode::Overwrite, |file| self.write_npz(file))?;
+        Some(())
+    }
+    pub fn write_npz<W: Write>(&self, writer: W) -> Option<()> {
+        //! Writes the network's tensors as an `.npz` archive to any writer. See [`Network::save_npz`].
+        let arrays: Vec<(String, NpyArray)> = self
+            .layer_tensors()
+            .into_iter()
+            .map(|(name, shape, values)| (name, (shape, values)))
+            .collect();
+        write_npz(writer, &arrays)
+    }
+    pub fn load_npz_weights(&mut self, path: &str) -> Option<()> {
+        /*!
+        Loads the tensors of every layer from an `.npz` archive laid out like the ones [`Network::save_npz`] writes, such as one made with `numpy.savez` from a network trained elsewhere. The network must already have the same shape. Returns None, leaving the network unchanged, if the archive can't be read (compressed archives aren't supported) or an array is missing or the wrong shape.
+        ```
+        # use neurnet::*;
+        let trained = Network::new(vec![2, 3, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        let mut buf: Vec<u8> = vec![];
+        trained.write_npz(&mut buf).unwrap();
+
+        let mut nn = Network::new(vec![2, 3, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        nn.read_npz_weights(buf.as_slice()).unwrap();
+        assert_eq!(nn.pulse(vec![0.2, 0.7]), trained.pulse(vec![0.2, 0.7]));
+
+        let mut wrong_shape = Network::new(vec![2, 4, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        assert!(wrong_shape.read_npz_weights(buf.as_slice()).is_none());
+        ```
+        */
+        self.read_npz_weights(BufReader::new(open_file(path)?))
+    }
+    pub fn read_npz_weights<R: Read>(&mut self, reader: R) -> Option<()> {
+        //! Reads the tensors of every layer from an `.npz` archive in any reader. See [`Network::load_npz_weights`].
+        let arrays = read_npz(reader)?;
+        let find = |name: String, shape: Vec<usize>| -> Option<Vec<f64>> {
+            let (_, (array_shape, values)) = arrays.iter().find(|(n, _)| *n == name)?;
+            if *array_shape != shape {
+                return None;
+            }
+            Some(values.clone())
+        };
+        let mut loaded: Vec<Vec<f64>> = vec![];
+        for (name, shape, _) in self.layer_tensors() {
+            loaded.push(find(name, shape)?);
+        }
+        self.set_layer_tensors(loaded)
+    }
+}
@@ -0,0 +1,153 @@
+use super::super::files::{open_file, write_atomic, SaveMode};
+use super::super::onnx::{read_onnx, write_onnx, OnnxLayer, OnnxScaler};
+use super::activation::Activation;
+use super::layers::{Dense, Layer};
+use super::scaler::Scaler;
+use super::training::dropout::Dropout;
+use super::Network;
+use std::io::{BufReader, Read, Write};
+
+impl Activation {
+    fn onnx_op(&self) -> Option<(&'static str, Option<f32>)> {
+        Some(match self {
+            Activation::Identity => ("Identity", None),
+            Activation::Sigmoid => ("Sigmoid", None),
+            Activation::Tanh => ("Tanh", None),
+            Activation::Relu => ("Relu", None),
+            Activation::LeakyRelu(slope) => ("LeakyRelu", Some(*slope as f32)),
+            Activation::Elu(alpha) => ("Elu", Some(*alpha as f32)),
+            Activation::Softplus => ("Softplus", None),
+            Activation::Custom(_) => return None,
+        })
+    }
+    fn from_onnx_op(op: &str, alpha: Option<f32>) -> Option<Activation> {
+        Some(match op {
+            "Identity" => Activation::Identity,
+            "Sigmoid" => Activation::Sigmoid,
+            "Tanh" => Activation::Tanh,
+            "Relu" => Activation::Relu,
+            //The ONNX defaults for alpha
+            "LeakyRelu" => Activation::LeakyRelu(alpha.unwrap_or(0.01) as f64),
+            "Elu" => Activation::Elu(alpha.unwrap_or(1.0) as f64),
+            "Softplus" => Activation::Softplus,
+            _ => return None,
+        })
+    }
+}
+
+impl Network {
+    pub fn export_onnx(&self, path: &str) -> Option<()> {
+        /*!
+        Exports the network as an ONNX model, so it can be run by other runtimes. Each layer becomes a Gemm node followed by a node for the activation function, with the weights stored as 32 bit floats. The model's input is named `input` and has the shape (batch size, input neurons), and its output is named `output`. An input scaler becomes Sub and Div nodes before the first layer, and an output scaler Mul and Add nodes after the last one, so the model takes and returns the same raw values as [`Network::pulse`]. Standard [`Dropout`] is folded into the weights of the layer after it, or into the output scaler after the last layer. Returns None if the network uses a `Custom` activation, which has no ONNX equivalent, if it has layers other than [`Dense`] ones, or if writing fails.
+        ```no_run
+        # use neurnet::*;
+        let nn = Network::with_activation(vec![4, 8, 3], Activation::Relu, (-1.0, 1.0), (-1.0, 1.0));
+        nn.export_onnx("network.onnx").unwrap();
+        ```
+        */
+        write_atomic(path, SaveMode::Overwrite, |file| self.write_onnx(file))?;
+        Some(())
+    }
+    pub fn write_onnx<W: Write>(&self, writer: W) -> Option<()> {
+        //! Writes the network as an ONNX model to any writer. See [`Network::export_onnx`].
+        let (op, alpha) = self.activation.onnx_op()?;
+        //The keep chance of standard dropout scales a layer's outputs, so it is folded into the weights of the next layer
+        let keep = |layer: usize| match self.dropout[layer] {
+            Some(Dropout::Standard(chance)) => 1.0 - chance,
+            _ => 1.0,
+        };
+        let layers: Vec<OnnxLayer> = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                let layer = layer.downcast_ref::<Dense>()?;
+                let mut weights = layer.weights().clone();
+                if i > 0 && keep(i - 1) != 1.0 {
+                    for weight in weights.iter_mut().flatten() {
+                        *weight *= keep(i - 1);
+                    }
+                }
+                Some((weights, layer.biases().clone(), op.to_string(), alpha))
+            })
+            .collect::<Option<Vec<OnnxLayer>>>()?;
+        let scaler = |scaler: &Option<Scaler>| {
+            scaler
+                .as_ref()
+                .map(|scaler| (scaler.center().clone(), scaler.scale().clone()))
+        };
+        //and the last layer's into the output scaler
+        let last = keep(self.layers.len() - 1);
+        let mut output_scaler = scaler(&self.output_scaler);
+        if last != 1.0 {
+            let outputs = self.shape[self.shape.len() - 1];
+            let (_, scale) =
+                output_scaler.get_or_insert_with(|| (vec![0.0; outputs], vec![1.0; outputs]));
+            for x in scale.iter_mut() {
+                *x *= last;
+            }
+        }
+        write_onnx(
+            writer,
+            self.shape[0],
+            &(scaler(&self.input_scaler), layers, output_scaler),
+        )
+    }
+    pub fn import_onnx(path: &str) -> Option<Network> {
+        /*!
+        Imports an ONNX model made of Gemm nodes, each followed by an activation node, such as the ones written by [`Network::export_onnx`]. Since a network has one activation function, every layer must use the same one. Returns None if the model holds anything else.
+        ```
+        # use neurnet::*;
+        let nn = Network::with_activation(vec![3, 5, 2], Activation::LeakyRelu(0.1), (-1.0, 1.0), (-1.0, 1.0));
+        let mut buf: Vec<u8> = vec![];
+        nn.write_onnx(&mut buf).unwrap();
+
+        let imported = Network::read_onnx(buf.as_slice()).unwrap();
+        assert_eq!(imported.get_shape(), nn.get_shape());
+        let input = vec![0.3, -1.2, 0.8];
+        for (a, b) in imported.pulse(input.clone()).iter().zip(nn.pulse(input)) {
+            // The weights are stored as 32 bit floats
+            assert!((a - b).abs() < 1e-5);
+        }
+
+        // Scalers are exported too, so the model takes and returns raw values
+        let ds = DataSet::gen_from_fn(|x| vec![0.5 * x[0]], (-200..200).map(|x| vec![x as f64]).collect(), vec![]);
+        let mut nn = Network::with_activation(vec![1, 3, 1], Activation::Tanh, (-1.0, 1.0), (-1.0, 1.0));
+        nn.set_input_scaler(Some(Scaler::fit_inputs(ScalerKind::Standard, &ds)));
+        nn.set_output_scaler(Some(Scaler::fit_outputs(ScalerKind::Standard, &ds)));
+        let mut buf: Vec<u8> = vec![];
+        nn.write_onnx(&mut buf).unwrap();
+        let imported = Network::read_onnx(buf.as_slice()).unwrap();
+        assert!(imported.get_input_scaler().is_some() && imported.get_output_scaler().is_some());
+        let (a, b) = (imported.pulse(vec![150.0])[0], nn.pulse(vec![150.0])[0]);
+        assert!((a - b).abs() < 1e-3 * b.abs().max(1.0));
+        ```
+        */
+        Network::read_onnx(BufReader::new(open_file(path)?))
+    }
+    pub fn read_onnx<R: Read>(reader: R) -> Option<Network> {
+        //! Reads an ONNX model from any reader. See [`Network::import_onnx`].
+        let (input_scaler, layers, output_scaler) = read_onnx(reader)?;
+        let first = layers.first()?;
+        let activation = Activation::from_onnx_op(&first.2, first.3)?;
+        if layers
+            .iter()
+            .any(|layer| (&layer.2, layer.3) != (&first.2, first.3))
+        {
+            return None;
+        }
+        let mut dense_layers: Vec<Box<dyn Layer>> = vec![];
+        for (weights, biases, _, _) in layers {
+            dense_layers.push(Box::new(Dense::from_weights(weights, biases)?));
+        }
+        let mut network = Network::from_layers(dense_layers, activation)?;
+        let scaler = |scaler: Option<OnnxScaler>, len: usize| match scaler {
+            Some((center, scale)) if center.len() == len => Some(Some(Scaler::new(center, scale)?)),
+            Some(_) => None,
+            None => Some(None),
+        };
+        network.input_scaler = scaler(input_scaler, network.shape[0])?;
+        network.output_scaler = scaler(output_scaler, *network.shape.last()?)?;
+        Some(network)
+    }
+}
@@ -0,0 +1,362 @@
+use super::super::files::{write_atomic, SaveMode};
+use super::Network;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+
+/// The reasons loading safetensors weights into a [`Network`] can fail.
+#[derive(Debug)]
+pub enum SafetensorsError {
+    Io(io::Error),
+    /// The file isn't valid safetensors data.
+    InvalidHeader(String),
+    UnsupportedDtype {
+        name: String,
+        dtype: String,
+    },
+    MissingTensor(String),
+    /// A tensor's shape doesn't agree with the network's shape.
+    ShapeMismatch {
+        name: String,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+}
+impl fmt::Display for SafetensorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SafetensorsError::Io(err) => write!(f, "io error: {}", err),
+            SafetensorsError::InvalidHeader(reason) => write!(f, "invalid header: {}", reason),
+            SafetensorsError::UnsupportedDtype { name, dtype } => {
+                write!(f, "tensor {} has unsupported dtype {}", name, dtype)
+            }
+            SafetensorsError::MissingTensor(name) => write!(f, "missing tensor {}", name),
+            SafetensorsError::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "tensor {} has shape {:?} but the network needs {:?}",
+                name, found, expected
+            ),
+        }
+    }
+}
+impl std::error::Error for SafetensorsError {}
+impl From<io::Error> for SafetensorsError {
+    fn from(err: io::Error) -> SafetensorsError {
+        SafetensorsError::Io(err)
+    }
+}
+
+enum Json {
+    Null,
+    Bool,
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// A small JSON parser, enough for safetensors headers.
+fn parse_json(text: &str) -> Option<Json> {
+    fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    }
+    fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+        let mut buf = String::new();
+        loop {
+            match chars.next()? {
+                '"' => return Some(buf),
+                '\\' => match chars.next()? {
+                    'n' => buf.push('\n'),
+                    't' => buf.push('\t'),
+                    'r' => buf.push('\r'),
+                    'b' => buf.push('\u{8}'),
+                    'f' => buf.push('\u{c}'),
+                    'u' => {
+                        let hex: String = (0..4).filter_map(|_| chars.next()).collect();
+                        buf.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    c => buf.push(c),
+                },
+                c => buf.push(c),
+            }
+        }
+    }
+    fn parse_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<Json> {
+        skip_whitespace(chars);
+        let value = match chars.peek()? {
+            '{' => {
+                chars.next();
+                let mut members = vec![];
+                skip_whitespace(chars);
+                if chars.peek() == Some(&'}') {
+                    chars.next();
+                    return Some(Json::Object(members));
+                }
+                loop {
+                    skip_whitespace(chars);
+                    if chars.next()? != '"' {
+                        return None;
+                    }
+                    let key = parse_string(chars)?;
+                    skip_whitespace(chars);
+                    if chars.next()? != ':' {
+                        return None;
+                    }
+                    members.push((key, parse_value(chars)?));
+                    skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => (),
+                        '}' => break,
+                        _ => return None,
+                    }
+                }
+                Json::Object(members)
+            }
+            '[' => {
+                chars.next();
+                let mut items = vec![];
+                skip_whitespace(chars);
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                    return Some(Json::Array(items));
+                }
+                loop {
+                    items.push(parse_value(chars)?);
+                    skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => (),
+                        ']' => break,
+                        _ => return None,
+                    }
+                }
+                Json::Array(items)
+            }
+            '"' => {
+                chars.next();
+                Json::String(parse_string(chars)?)
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = chars.peek() {
+                    if c.is_alphanumeric() || "+-.".contains(*c) {
+                        word.push(*c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                match word.as_str() {
+                    "null" => Json::Null,
+                    "true" | "false" => Json::Bool,
+                    _ => Json::Number(word.parse().ok()?),
+                }
+            }
+        };
+        Some(value)
+    }
+    let mut chars = text.chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Some(value),
+        Some(_) => None,
+    }
+}
+
+fn json_string(string: &str) -> String {
+    let mut buf = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+    buf
+}
+
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1F) as i32;
+    let fraction = (bits & 0x3FF) as f64;
+    sign * match exponent {
+        0 => fraction * 2f64.powi(-24),
+        0x1F if fraction == 0.0 => f64::INFINITY,
+        0x1F => f64::NAN,
+        _ => (1.0 + fraction / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+impl Network {
+    pub fn save_safetensors(&self, path: &str) -> Option<()> {
+        /*!
+        Saves every layer's tensors in the safetensors format, as 64 bit floats. The tensors are named `layers.{i}.{tensor}`, where layer i is `get_layers()[i]` and the tensors are the ones in its [`Layer::serialize`](super::layers::Layer::serialize). A [`Dense`](super::layers::Dense) layer has `layers.{i}.weight`, with the shape (neurons in the layer, neurons in the previous layer), and `layers.{i}.bias`.
+        ```no_run
+        # use neurnet::*;
+        let nn = Network::new(vec![2, 3, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        nn.save_safetensors("model.safetensors").unwrap();
+        ```
+        */
+        write_atomic(path, SaveMode::Overwrite, |file| {
+            self.write_safetensors(file)
+        })?;
+        Some(())
+    }
+    pub fn write_safetensors<W: Write>(&self, mut writer: W) -> Option<()> {
+        //! Writes every layer's tensors in the safetensors format to any writer. See [`Network::save_safetensors`].
+        let mut data: Vec<u8> = vec![];
+        let mut entries: Vec<String> = vec![format!(
+            "\"__metadata__\":{{\"producer\":\"neurnet\",\"activation\":{}}}",
+            json_string(self.activation.name())
+        )];
+        for (name, shape, values) in self.layer_tensors() {
+            let begin = data.len();
+            for value in values {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            entries.push(format!(
+                "{}:{{\"dtype\":\"F64\",\"shape\":[{}],\"data_offsets\":[{},{}]}}",
+                json_string(&name),
+                shape
+                    .iter()
+                    .map(|dim| dim.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+                begin,
+                data.len()
+            ));
+        }
+        let mut header = format!("{{{}}}", entries.join(","));
+        //Pad so the tensor data is 8 byte aligned
+        while header.len() % 8 != 0 {
+            header.push(' ');
+        }
+        writer
+            .write_all(&(header.len() as u64).to_le_bytes())
+            .ok()?;
+        writer.write_all(header.as_bytes()).ok()?;
+        writer.write_all(&data).ok()?;
+        writer.flush().ok()
+    }
+    pub fn load_safetensors(&mut self, path: &str) -> Result<(), SafetensorsError> {
+        /*!
+        Loads every layer's tensors from a safetensors file named like the ones [`Network::save_safetensors`] writes. Each tensor's shape is checked against the layer's, and nothing in the network changes unless every tensor is found and fits. F64, F32, F16 and BF16 tensors can be loaded.
+        ```
+        # use neurnet::*;
+        let trained = Network::new(vec![2, 3, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        let mut buf: Vec<u8> = vec![];
+        trained.write_safetensors(&mut buf).unwrap();
+
+        let mut nn = Network::new(vec![2, 3, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        nn.read_safetensors(buf.as_slice()).unwrap();
+        assert_eq!(nn.pulse(vec![0.2, 0.7]), trained.pulse(vec![0.2, 0.7]));
+
+        let mut wrong_shape = Network::new(vec![2, 4, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        match wrong_shape.read_safetensors(buf.as_slice()) {
+            Err(SafetensorsError::ShapeMismatch { name, expected, found }) => {
+                assert_eq!(name, "layers.0.weight");
+                assert_eq!(expected, vec![4, 2]);
+                assert_eq!(found, vec![3, 2]);
+            }
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+        ```
+        */
+        self.read_safetensors(BufReader::new(File::open(path)?))
+    }
+    pub fn read_safetensors<R: Read>(&mut self, mut reader: R) -> Result<(), SafetensorsError> {
+        //! Reads every layer's tensors in the safetensors format from any reader. See [`Network::load_safetensors`].
+        let invalid = |reason: &str| SafetensorsError::InvalidHeader(reason.to_string());
+        let mut len_buf = [0u8; 8];
+        reader.read_exact(&mut len_buf)?;
+        let header_len = u64::from_le_bytes(len_buf);
+        if header_len > 100_000_000 {
+            return Err(invalid("header is too large"));
+        }
+        let mut header = vec![0u8; header_len as usize];
+        reader.read_exact(&mut header)?;
+        let mut data: Vec<u8> = vec![];
+        reader.read_to_end(&mut data)?;
+
+        let header = std::str::from_utf8(&header).map_err(|_| invalid("header is not utf-8"))?;
+        let entries = match parse_json(header) {
+            Some(Json::Object(entries)) => entries,
+            _ => return Err(invalid("header is not a JSON object")),
+        };
+
+        let mut loaded: Vec<Vec<f64>> = vec![];
+        for (name, expected, _) in self.layer_tensors() {
+            let info = match entries.iter().find(|(key, _)| *key == name) {
+                Some((_, Json::Object(info))) => info,
+                Some(_) => return Err(invalid("tensor info is not an object")),
+                None => return Err(SafetensorsError::MissingTensor(name)),
+            };
+            let field = |key: &str| info.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            let numbers = |key: &str| -> Result<Vec<usize>, SafetensorsError> {
+                match field(key) {
+                    Some(Json::Array(items)) => items
+                        .iter()
+                        .map(|item| match item {
+                            Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+                            _ => Err(invalid("expected a list of whole numbers")),
+                        })
+                        .collect(),
+                    _ => Err(invalid("expected a list of whole numbers")),
+                }
+            };
+            let dtype = match field("dtype") {
+                Some(Json::String(dtype)) => dtype.clone(),
+                _ => return Err(invalid("tensor has no dtype")),
+            };
+            let shape = numbers("shape")?;
+            let offsets = numbers("data_offsets")?;
+            if shape != expected {
+                return Err(SafetensorsError::ShapeMismatch {
+                    name,
+                    expected,
+                    found: shape,
+                });
+            }
+            let bytes = match offsets.as_slice() {
+                [begin, end] if begin <= end => data
+                    .get(*begin..*end)
+                    .ok_or_else(|| invalid("data offsets are out of bounds"))?,
+                _ => return Err(invalid("data offsets are malformed")),
+            };
+            let element_size = match dtype.as_str() {
+                "F64" => 8,
+                "F32" => 4,
+                "F16" | "BF16" => 2,
+                _ => return Err(SafetensorsError::UnsupportedDtype { name, dtype }),
+            };
+            if bytes.len() != expected.iter().product::<usize>() * element_size {
+                return Err(invalid("data length doesn't match the shape"));
+            }
+            loaded.push(
+                bytes
+                    .chunks_exact(element_size)
+                    .map(|b| match dtype.as_str() {
+                        "F64" => {
+                            f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+                        }
+                        "F32" => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                        "F16" => f16_to_f64(u16::from_le_bytes([b[0], b[1]])),
+                        _ => f32::from_le_bytes([0, 0, b[0], b[1]]) as f64,
+                    })
+                    .collect(),
+            );
+        }
+
+        self.set_layer_tensors(loaded)
+            .ok_or_else(|| invalid("tensors don't describe valid layers"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use super::training::data::{DataSet, DataSplit, NetworkFood};
+
+/// The kinds of scaling a [`Scaler`] can be fitted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScalerKind {
+    /// Shifts each value to a mean of 0 and scales it to a standard deviation of 1.
+    Standard,
+    /// Maps each value's range onto 0..1.
+    MinMax,
+    /// Shifts each value to a median of 0 and scales it by its interquartile range, so outliers barely affect it.
+    Robust,
+}
+
+/// A fitted preprocessing step that maps each value to `(value - center) / scale`.
+///
+/// Scalers can be attached to a network with [`Network::set_input_scaler`](super::Network::set_input_scaler) and [`Network::set_output_scaler`](super::Network::set_output_scaler), which saves them along with the network.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scaler {
+    center: Vec<f64>,
+    scale: Vec<f64>,
+}
+impl Scaler {
+    pub fn new(center: Vec<f64>, scale: Vec<f64>) -> Option<Scaler> {
+        //! Creates a scaler from already known centers and scales, one of each per value. Returns None if the lengths differ or any scale is zero or not finite.
+        if center.len() != scale.len() || scale.iter().any(|s| *s == 0.0 || !s.is_finite()) {
+            return None;
+        }
+        Some(Scaler { center, scale })
+    }
+    pub fn fit_inputs(kind: ScalerKind, food: &impl NetworkFood) -> Scaler {
+        /*!
+        Fits a scaler to the inputs of the training points, so it can be used to preprocess a network's inputs.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::gen_from_fn(
+            |x| vec![0.5 * x[0]],
+            (-200..200).map(|x| vec![x as f64]).collect(),
+            vec![],
+        );
+        let scaler = Scaler::fit_inputs(ScalerKind::MinMax, &ds);
+        assert_eq!(scaler.transform(&[-200.0]), vec![0.0]);
+        assert_eq!(scaler.transform(&[199.0]), vec![1.0]);
+        ```
+        */
+        Scaler::fit(kind, food, false)
+    }
+    pub fn fit_outputs(kind: ScalerKind, food: &impl NetworkFood) -> Scaler {
+        //! Fits a scaler to the expected outputs of the training points, so the network can be trained on scaled targets and its outputs mapped back with [`Scaler::inverse_transform`].
+        Scaler::fit(kind, food, true)
+    }
+    fn fit(kind: ScalerKind, food: &impl NetworkFood, outputs: bool) -> Scaler {
+        let mut columns: Vec<Vec<f64>> = vec![];
+        for point in food.points(DataSplit::Training) {
+            let values = if outputs { &point.1 } else { &point.0 };
+            if columns.is_empty() {
+                columns = vec![vec![]; values.len()];
+            }
+            for (column, value) in columns.iter_mut().zip(values.iter()) {
+                column.push(*value);
+            }
+        }
+
+        let mut center: Vec<f64> = vec![];
+        let mut scale: Vec<f64> = vec![];
+        for mut column in columns {
+            let (c, s) = match kind {
+                ScalerKind::Standard => {
+                    let mean = column.iter().sum::<f64>() / column.len() as f64;
+                    let variance = column.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>()
+                        / column.len() as f64;
+                    (mean, variance.sqrt())
+                }
+                ScalerKind::MinMax => {
+                    let min = column.iter().cloned().fold(f64::INFINITY, f64::min);
+                    let max = column.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                    (min, max - min)
+                }
+                ScalerKind::Robust => {
+                    column.sort_by(|a, b| a.total_cmp(b));
+                    (
+                        quantile(&column, 0.5),
+                        quantile(&column, 0.75) - quantile(&column, 0.25),
+                    )
+                }
+            };
+            center.push(c);
+            //A constant value can't be scaled, so it is only shifted
+            scale.push(if s == 0.0 || !s.is_finite() { 1.0 } else { s });
+        }
+        Scaler { center, scale }
+    }
+    pub fn transform(&self, values: &[f64]) -> Vec<f64> {
+        //! Scales the values. Extra values past the scaler's length are passed through unchanged.
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, x)| match (self.center.get(i), self.scale.get(i)) {
+                (Some(c), Some(s)) => (x - c) / s,
+                _ => *x,
+            })
+            .collect()
+    }
+    pub fn inverse_transform(&self, values: &[f64]) -> Vec<f64> {
+        /*!
+        Undoes [`Scaler::transform`], mapping scaled values back to the original range.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::new(vec![(vec![1.0], vec![10.0]), (vec![2.0], vec![30.0])], vec![]);
+        let scaler = Scaler::fit_outputs(ScalerKind::Standard, &ds);
+        assert_eq!(scaler.transform(&[30.0]), vec![1.0]);
+        assert_eq!(scaler.inverse_transform(&[-1.0]), vec![10.0]);
+        ```
+        */
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, x)| match (self.center.get(i), self.scale.get(i)) {
+                (Some(c), Some(s)) => x * s + c,
+                _ => *x,
+            })
+            .collect()
+    }
+    pub fn center(&self) -> &Vec<f64> {
+        &self.center
+    }
+    pub fn scale(&self) -> &Vec<f64> {
+        &self.scale
+    }
+    pub fn len(&self) -> usize {
+        self.center.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.center.is_empty()
+    }
+}
+
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let position = q * (sorted.len() - 1) as f64;
+    let (low, high) = (position.floor() as usize, position.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+impl DataSet {
+    pub fn transform_inputs(&mut self, scaler: &Scaler) {
+        //! Scales the inputs of every point in the dataset with the scaler.
+        self.map_points(|inputs, _| {
+            let scaled = scaler.transform(inputs);
+            inputs.copy_from_slice(&scaled);
+        });
+    }
+    pub fn transform_outputs(&mut self, scaler: &Scaler) {
+        //! Scales the expected outputs of every point in the dataset with the scaler.
+        self.map_points(|_, outputs| {
+            let scaled = scaler.transform(outputs);
+            outputs.copy_from_slice(&scaled);
+        });
+    }
+}
@@ -0,0 +1,320 @@
+use super::super::layers::{Layer, LayerParameters};
+use super::super::loss::Loss;
+use super::dropout::DropoutMasks;
+use super::Network;
+
+/// The gradient of a loss with respect to one layer's parameters, laid out like the layer's [`Layer::parameters`](super::Layer::parameters).
+pub type LayerGradient = LayerParameters;
+
+impl Network {
+    fn batch_trace(
+        &self,
+        inputs: &[&[f64]],
+        masks: Option<&[DropoutMasks]>,
+        training: bool,
+    ) -> Vec<Vec<Vec<f64>>> {
+        //Returns every layer's inputs for the whole batch, plus the final outputs
+        let mut activations: Vec<Vec<Vec<f64>>> = vec![inputs
+            .iter()
+            .map(|input| match &self.input_scaler {
+                Some(scaler) => scaler.transform(input),
+                None => input.to_vec(),
+            })
+            .collect()];
+        for (layer_i, layer) in self.layers.iter().enumerate() {
+            let inputs = activations.last().unwrap();
+            let mut outputs = match training {
+                true => layer.forward_batch(inputs, &self.activation),
+                false => inputs
+                    .iter()
+                    .map(|input| layer.forward(input, &self.activation))
+                    .collect(),
+            };
+            for (point, output) in outputs.iter_mut().enumerate() {
+                self.apply_dropout(layer_i, output, masks.map(|masks| &masks[point][layer_i]));
+            }
+            activations.push(outputs);
+        }
+        activations
+    }
+    pub fn point_loss(&self, inputs: &[f64], expected_outputs: &[f64], loss: &Loss) -> f64 {
+        //! Runs the inputs through the network and returns the loss compared to the expected outputs, plus the [`Network::regularization_cost`].
+        loss.cost(&self.pulse(inputs.to_vec()), expected_outputs) + self.regularization_cost()
+    }
+    pub fn gradient(
+        &self,
+        inputs: &[f64],
+        expected_outputs: &[f64],
+        loss: &Loss,
+    ) -> Vec<LayerGradient> {
+        /*!
+        Computes the exact gradient of the loss for a single data point with respect to every weight and bias, using backpropagation. Input and output scalers and dropout are taken into account just like in [`Network::pulse`], as is each layer's [`Regularization`](super::regularization::Regularization).
+        ```
+        # use neurnet::*;
+        let mut nn = Network::with_activation(vec![1, 1], Activation::Identity, (0.0, 0.0), (0.0, 0.0));
+        nn.set_weight(0, 0, 0, 2.0);
+        // cost = (2x - y)², so d/dw = 2(2x - y)x and d/db = 2(2x - y)
+        let gradient = nn.gradient(&[3.0], &[1.0], &Loss::MeanSquaredError);
+        assert_eq!(gradient[0].weights, vec![30.0]);
+        assert_eq!(gradient[0].biases, vec![10.0]);
+        ```
+        */
+        let (mut gradients, _) =
+            self.batch_loss_gradient(&[(inputs, expected_outputs)], loss, None, false);
+        self.add_regularization_gradient(&mut gradients);
+        gradients
+    }
+    pub fn batch_loss(&self, points: &[(&[f64], &[f64])], loss: &Loss) -> f64 {
+        //! The average loss over a batch of points the way [`Network::train_with_loss`] sees it, with layers like [`BatchNorm`](super::super::layers::BatchNorm) using the batch's statistics, plus the [`Network::regularization_cost`].
+        let inputs: Vec<&[f64]> = points.iter().map(|(inputs, _)| *inputs).collect();
+        let outputs = self.batch_trace(&inputs, None, true).pop().unwrap();
+        let total: f64 = outputs
+            .iter()
+            .zip(points.iter())
+            .map(|(outputs, (_, expected_outputs))| {
+                let outputs = match &self.output_scaler {
+                    Some(scaler) => scaler.inverse_transform(outputs),
+                    None => outputs.clone(),
+                };
+                loss.cost(&outputs, expected_outputs)
+            })
+            .sum();
+        total / points.len() as f64 + self.regularization_cost()
+    }
+    pub fn batch_gradient(&self, points: &[(&[f64], &[f64])], loss: &Loss) -> Vec<LayerGradient> {
+        //! Computes the exact gradient of [`Network::batch_loss`] with respect to every parameter. For networks whose layers don't depend on the batch this is the average of every point's [`Network::gradient`].
+        let (mut gradients, _) = self.batch_loss_gradient(points, loss, None, true);
+        self.add_regularization_gradient(&mut gradients);
+        gradients
+    }
+    pub(crate) fn batch_loss_gradient(
+        &self,
+        points: &[(&[f64], &[f64])],
+        loss: &Loss,
+        masks: Option<&[DropoutMasks]>,
+        training: bool,
+    ) -> (Vec<LayerGradient>, Vec<Vec<Vec<f64>>>) {
+        //Returns the gradient of the batch's average loss, along with every layer's inputs
+        let inputs: Vec<&[f64]> = points.iter().map(|(inputs, _)| *inputs).collect();
+        let activations = self.batch_trace(&inputs, masks, training);
+        let mut output_grads: Vec<Vec<f64>> = activations
+            .last()
+            .unwrap()
+            .iter()
+            .zip(points.iter())
+            .map(|(outputs, (_, expected_outputs))| {
+                self.output_gradient(outputs, expected_outputs, loss, points.len())
+            })
+            .collect();
+
+        let mut gradients: Vec<LayerGradient> = vec![];
+        for (layer_i, layer) in self.layers.iter().enumerate().rev() {
+            for (point, output_grad) in output_grads.iter_mut().enumerate() {
+                let mask = masks.map(|masks| &masks[point][layer_i]);
+                for (neuron, grad) in output_grad.iter_mut().enumerate() {
+                    *grad *= self.dropout_multiplier(layer_i, neuron, mask);
+                }
+            }
+            let (input_grads, gradient) = match training {
+                true => {
+                    layer.backward_batch(&activations[layer_i], &output_grads, &self.activation)
+                }
+                //Without batch statistics every point goes through the layer on its own
+                false => {
+                    let mut input_grads: Vec<Vec<f64>> = vec![];
+                    let mut total = layer.zero_gradient();
+                    for (input, output_grad) in activations[layer_i].iter().zip(output_grads.iter())
+                    {
+                        let (input_grad, gradient) =
+                            layer.backward(input, output_grad, &self.activation);
+                        input_grads.push(input_grad);
+                        total.add(&gradient);
+                    }
+                    (input_grads, total)
+                }
+            };
+            gradients.push(gradient);
+            output_grads = input_grads;
+        }
+        gradients.reverse();
+        (gradients, activations)
+    }
+    pub(crate) fn output_gradient(
+        &self,
+        outputs: &[f64],
+        expected_outputs: &[f64],
+        loss: &Loss,
+        count: usize,
+    ) -> Vec<f64> {
+        //The gradient of the loss with respect to the last layer's (scaled) outputs, divided by the number of outputs the loss is averaged over
+        let outputs = match &self.output_scaler {
+            Some(scaler) => scaler.inverse_transform(outputs),
+            None => outputs.to_vec(),
+        };
+        let mut output_grad = loss.derivative(&outputs, expected_outputs);
+        if let Some(scaler) = &self.output_scaler {
+            for (grad, scale) in output_grad.iter_mut().zip(scaler.scale().iter()) {
+                *grad *= scale;
+            }
+        }
+        for grad in output_grad.iter_mut() {
+            *grad /= count as f64;
+        }
+        output_grad
+    }
+    pub fn gradient_check(&self, point: (&[f64], &[f64]), loss: &Loss, epsilon: f64) -> Vec<f64> {
+        /*!
+        Compares the gradient from [`Network::gradient`] against central differences for every parameter of every layer, and returns the largest relative error in each layer. Errors around 1e-7 or smaller mean the gradient is right.
+        ```
+        # use neurnet::*;
+        let activations = vec![
+            Activation::Identity,
+            Activation::Sigmoid,
+            Activation::Tanh,
+            Activation::Relu,
+            Activation::LeakyRelu(0.1),
+            Activation::Elu(1.0),
+            Activation::Softplus,
+            Activation::custom(|x| x.sin()),
+        ];
+        let losses = vec![
+            Loss::MeanSquaredError,
+            Loss::MeanAbsoluteError,
+            Loss::Huber(0.1),
+            Loss::BinaryCrossEntropy,
+            Loss::CategoricalCrossEntropy,
+        ];
+        for activation in activations {
+            // Small positive weights keep every neuron away from the kinks of relu and friends
+            let nn = Network::with_activation(vec![3, 4, 2], activation.clone(), (0.05, 0.2), (0.05, 0.1));
+            for loss in losses.iter() {
+                let errors = nn.gradient_check((&[0.3, 0.6, 0.9], &[1.0, 0.0]), loss, 1e-6);
+                assert_eq!(errors.len(), 2);
+                for error in errors {
+                    assert!(error < 1e-4, "{:?} with {:?}: {}", activation, loss, error);
+                }
+            }
+        }
+
+        // Scalers are part of the gradient too
+        let ds = DataSet::new(vec![(vec![10.0, -4.0, 2.0], vec![3.0, 50.0]), (vec![30.0, 4.0, 1.0], vec![1.0, 90.0])], vec![]);
+        let mut nn = Network::with_activation(vec![3, 4, 2], Activation::Tanh, (-1.0, 1.0), (-1.0, 1.0));
+        nn.set_input_scaler(Some(Scaler::fit_inputs(ScalerKind::Standard, &ds)));
+        nn.set_output_scaler(Some(Scaler::fit_outputs(ScalerKind::Standard, &ds)));
+        let errors = nn.gradient_check((&[20.0, 1.0, 1.5], &[2.0, 60.0]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+        ```
+        */
+        let (inputs, expected_outputs) = point;
+        self.check_gradients(
+            &self.gradient(inputs, expected_outputs, loss),
+            &|network| network.point_loss(inputs, expected_outputs, loss),
+            epsilon,
+        )
+    }
+    pub fn batch_gradient_check(
+        &self,
+        points: &[(&[f64], &[f64])],
+        loss: &Loss,
+        epsilon: f64,
+    ) -> Vec<f64> {
+        /*!
+        Like [`Network::gradient_check`], but compares [`Network::batch_gradient`] against central differences of [`Network::batch_loss`], which covers layers that normalize over the batch.
+        ```
+        # use neurnet::*;
+        let nn = Network::with_activation(vec![2, 3, 1], Activation::Tanh, (-1.0, 1.0), (-1.0, 1.0));
+        let points: Vec<(&[f64], &[f64])> = vec![(&[0.1, 0.2], &[0.5]), (&[-0.7, 0.4], &[-0.5])];
+        let errors = nn.batch_gradient_check(&points, &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // Without batch dependent layers, the batch gradient is the average of the point gradients
+        let batch = nn.batch_gradient(&points, &Loss::MeanSquaredError);
+        let first = nn.gradient(points[0].0, points[0].1, &Loss::MeanSquaredError);
+        let second = nn.gradient(points[1].0, points[1].1, &Loss::MeanSquaredError);
+        for i in 0..3 {
+            assert!((batch[1].weights[i] - (first[1].weights[i] + second[1].weights[i]) / 2.0).abs() < 1e-12);
+        }
+        ```
+        */
+        self.check_gradients(
+            &self.batch_gradient(points, loss),
+            &|network| network.batch_loss(points, loss),
+            epsilon,
+        )
+    }
+    pub(crate) fn check_gradients(
+        &self,
+        gradients: &[LayerGradient],
+        loss_of: &dyn Fn(&Network) -> f64,
+        epsilon: f64,
+    ) -> Vec<f64> {
+        let layers: Vec<&dyn Layer> = self.layers.iter().map(|layer| &**layer).collect();
+        check_layer_gradients(
+            self,
+            &layers,
+            gradients,
+            &|network: &mut Network, layer, parameters| {
+                network.layers[layer].set_parameters(parameters);
+            },
+            loss_of,
+            epsilon,
+        )
+    }
+    pub(crate) fn set_parameter(&mut self, layer: usize, bias: bool, index: usize, value: f64) {
+        //Sets one of the weights (or biases) in a layer's parameters
+        let layer = &mut self.layers[layer];
+        let mut parameters = layer.parameters();
+        match bias {
+            false => parameters.weights[index] = value,
+            true => parameters.biases[index] = value,
+        }
+        layer.set_parameters(&parameters);
+    }
+}
+
+pub(crate) fn check_layer_gradients<M: Clone>(
+    model: &M,
+    layers: &[&dyn Layer],
+    gradients: &[LayerGradient],
+    set_parameters: &dyn Fn(&mut M, usize, &LayerParameters),
+    loss_of: &dyn Fn(&M) -> f64,
+    epsilon: f64,
+) -> Vec<f64> {
+    //Nudges every parameter of every layer of a copy of the model both ways, and returns the largest relative error of each layer's gradient against the central differences
+    let relative_error = |analytic: f64, numeric: f64| {
+        //Gradients that should be zero come out of central differences as rounding noise, so tiny gradients are compared against a floor instead of themselves
+        let scale = analytic.abs().max(numeric.abs()).max(1e-5);
+        (analytic - numeric).abs() / scale
+    };
+
+    let mut model = model.clone();
+    let mut max_errors: Vec<f64> = vec![];
+    for (layer_i, (layer, gradient)) in layers.iter().zip(gradients.iter()).enumerate() {
+        let mut max_error: f64 = 0.0;
+        let parameters = layer.parameters();
+        let gradient = gradient.to_dense(parameters.weights.len());
+        for bias in [false, true] {
+            let (analytics, values) = match bias {
+                false => (&gradient.weights, &parameters.weights),
+                true => (&gradient.biases, &parameters.biases),
+            };
+            for (i, (analytic, value)) in analytics.iter().zip(values.iter()).enumerate() {
+                let mut loss_at = |x: f64| {
+                    let mut nudged = parameters.clone();
+                    match bias {
+                        false => nudged.weights[i] = x,
+                        true => nudged.biases[i] = x,
+                    }
+                    set_parameters(&mut model, layer_i, &nudged);
+                    loss_of(&model)
+                };
+                let numeric =
+                    (loss_at(value + epsilon) - loss_at(value - epsilon)) / (2.0 * epsilon);
+                set_parameters(&mut model, layer_i, &parameters);
+                max_error = max_error.max(relative_error(*analytic, numeric));
+            }
+        }
+        max_errors.push(max_error);
+    }
+    max_errors
+}
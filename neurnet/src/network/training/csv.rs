@@ -0,0 +1,319 @@
+use super::super::super::files::{
+    read_csv_file, read_csv_records, write_csv_file, write_csv_records, SaveMode,
+};
+use super::data::{DataSet, DataSplit, NetworkFood};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::io::{Read, Write};
+
+/// Refers to a column of a CSV file, either by its position or by its name in the header row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+impl From<usize> for CsvColumn {
+    fn from(index: usize) -> CsvColumn {
+        CsvColumn::Index(index)
+    }
+}
+impl From<&str> for CsvColumn {
+    fn from(name: &str) -> CsvColumn {
+        CsvColumn::Name(name.to_string())
+    }
+}
+
+/// Decides which rows of a CSV file become training data and which become testing data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CsvSplit {
+    /// Every row is a training point.
+    AllTraining,
+    /// Every row is a testing point.
+    AllTesting,
+    /// The last fraction of the rows (in file order) are testing points.
+    TestFraction(f64),
+    /// A random fraction of the rows are testing points. The same seed always picks the same rows.
+    ShuffledTestFraction { fraction: f64, seed: u64 },
+    /// Rows whose value in the column equals `testing_value` are testing points, every other row is a training point. The column itself is never used as an input or output.
+    Column {
+        column: CsvColumn,
+        testing_value: String,
+    },
+}
+
+/// The options used by [`DataSet::from_csv`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CsvOptions {
+    /// Whether the first row holds column names rather than data.
+    pub has_header: bool,
+    pub delimiter: char,
+    /// The columns used as inputs. If empty, every column that isn't an output (or the split column) is an input.
+    pub inputs: Vec<CsvColumn>,
+    /// The columns used as expected outputs. If empty, the last column is the only output.
+    pub outputs: Vec<CsvColumn>,
+    /// Columns holding categories rather than numbers. Each one is one-hot encoded into one value per distinct category, ordered alphabetically.
+    pub categorical: Vec<CsvColumn>,
+    pub split: CsvSplit,
+}
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            has_header: true,
+            delimiter: ',',
+            inputs: vec![],
+            outputs: vec![],
+            categorical: vec![],
+            split: CsvSplit::AllTraining,
+        }
+    }
+}
+
+impl CsvColumn {
+    fn resolve(&self, header: Option<&Vec<String>>, width: usize) -> Option<usize> {
+        match self {
+            CsvColumn::Index(i) if *i < width => Some(*i),
+            CsvColumn::Index(_) => None,
+            CsvColumn::Name(name) => header?.iter().position(|column| column.trim() == name),
+        }
+    }
+}
+
+enum Encoding {
+    Number,
+    OneHot(Vec<String>),
+}
+
+impl DataSet {
+    pub fn from_csv(path: &str, options: &CsvOptions) -> Option<DataSet> {
+        /*!
+        Loads a dataset from a CSV file, with the options deciding which columns are inputs and outputs, which ones get one-hot encoded and how rows are split between training and testing data. Returns None if the file can't be read, a column doesn't exist or a value isn't a number.
+        ```no_run
+        # use neurnet::*;
+        let ds = DataSet::from_csv(
+            "iris.csv",
+            &CsvOptions {
+                outputs: vec!["species".into()],
+                categorical: vec!["species".into()],
+                split: CsvSplit::ShuffledTestFraction { fraction: 0.2, seed: 7 },
+                ..CsvOptions::default()
+            },
+        )
+        .unwrap();
+        ```
+        */
+        DataSet::from_csv_records(read_csv_file(path, options.delimiter)?, options)
+    }
+    pub fn read_csv<R: Read>(reader: R, options: &CsvOptions) -> Option<DataSet> {
+        /*!
+        Reads a dataset from CSV data in any reader. See [`DataSet::from_csv`].
+        ```
+        # use neurnet::*;
+        let csv = "x;colour;y\n1.0;red;2.0\n2.0;blue;4.0\n3.0;red;6.0\n";
+        let ds = DataSet::read_csv(
+            csv.as_bytes(),
+            &CsvOptions {
+                delimiter: ';',
+                categorical: vec!["colour".into()],
+                split: CsvSplit::TestFraction(1.0 / 3.0),
+                ..CsvOptions::default()
+            },
+        )
+        .unwrap();
+        // "blue" sorts before "red", so red is the second one-hot value
+        assert_eq!(ds.grab_training_data()[0], (vec![1.0, 0.0, 1.0], vec![2.0]));
+        assert_eq!(ds.grab_testing_data()[0], (vec![3.0, 0.0, 1.0], vec![6.0]));
+        ```
+        */
+        DataSet::from_csv_records(read_csv_records(reader, options.delimiter)?, options)
+    }
+    fn from_csv_records(mut records: Vec<Vec<String>>, options: &CsvOptions) -> Option<DataSet> {
+        let header = if options.has_header {
+            if records.is_empty() {
+                return None;
+            }
+            Some(records.remove(0))
+        } else {
+            None
+        };
+        let width = match (&header, records.first()) {
+            (Some(header), _) => header.len(),
+            (None, Some(record)) => record.len(),
+            (None, None) => return Some(DataSet::empty()),
+        };
+        if records.iter().any(|record| record.len() != width) {
+            return None;
+        }
+        let resolve_all = |columns: &Vec<CsvColumn>| -> Option<Vec<usize>> {
+            columns
+                .iter()
+                .map(|column| column.resolve(header.as_ref(), width))
+                .collect()
+        };
+
+        let split_column = match &options.split {
+            CsvSplit::Column { column, .. } => Some(column.resolve(header.as_ref(), width)?),
+            _ => None,
+        };
+        let outputs = if options.outputs.is_empty() {
+            vec![width.checked_sub(1)?]
+        } else {
+            resolve_all(&options.outputs)?
+        };
+        let inputs = if options.inputs.is_empty() {
+            (0..width)
+                .filter(|i| !outputs.contains(i) && Some(*i) != split_column)
+                .collect()
+        } else {
+            resolve_all(&options.inputs)?
+        };
+        let categorical = resolve_all(&options.categorical)?;
+
+        let encodings: Vec<Encoding> = (0..width)
+            .map(|column| {
+                if categorical.contains(&column) {
+                    let mut categories: Vec<String> = records
+                        .iter()
+                        .map(|record| record[column].trim().to_string())
+                        .collect();
+                    categories.sort();
+                    categories.dedup();
+                    Encoding::OneHot(categories)
+                } else {
+                    Encoding::Number
+                }
+            })
+            .collect();
+        let encode = |record: &Vec<String>, columns: &Vec<usize>| -> Option<Vec<f64>> {
+            let mut buf: Vec<f64> = vec![];
+            for &column in columns {
+                let value = record[column].trim();
+                match &encodings[column] {
+                    Encoding::Number => buf.push(value.parse::<f64>().ok()?),
+                    Encoding::OneHot(categories) => {
+                        buf.extend(
+                            categories
+                                .iter()
+                                .map(|c| if c == value { 1.0 } else { 0.0 }),
+                        )
+                    }
+                }
+            }
+            Some(buf)
+        };
+
+        let row_count = records.len();
+        let testing_rows: Vec<bool> = match &options.split {
+            CsvSplit::AllTraining => vec![false; row_count],
+            CsvSplit::AllTesting => vec![true; row_count],
+            CsvSplit::TestFraction(fraction) => {
+                let training_count =
+                    row_count.saturating_sub((row_count as f64 * fraction).round() as usize);
+                (0..row_count).map(|i| i >= training_count).collect()
+            }
+            CsvSplit::ShuffledTestFraction { fraction, seed } => {
+                let testing_count = (row_count as f64 * fraction).round() as usize;
+                let mut order: Vec<usize> = (0..row_count).collect();
+                order.shuffle(&mut StdRng::seed_from_u64(*seed));
+                let mut buf = vec![false; row_count];
+                for i in order.into_iter().take(testing_count) {
+                    buf[i] = true;
+                }
+                buf
+            }
+            CsvSplit::Column { testing_value, .. } => {
+                let column = split_column?;
+                records
+                    .iter()
+                    .map(|record| record[column].trim() == testing_value)
+                    .collect()
+            }
+        };
+
+        let mut ds = DataSet::empty();
+        for (record, is_testing) in records.iter().zip(testing_rows) {
+            let point = (encode(record, &inputs)?, encode(record, &outputs)?);
+            if is_testing {
+                ds.push_testing_point(point);
+            } else {
+                ds.push_training_point(point);
+            }
+        }
+        Some(ds)
+    }
+    pub fn to_csv(&self, path: &str) -> Option<()> {
+        //! Saves the dataset as a CSV file with a header row, so it can be inspected in a spreadsheet. The columns are `input_0..`, `output_0..` and a `split` column holding `training`, `testing` or `validation` for each row. It can be loaded back with [`DataSet::from_csv`] by splitting on the `split` column with [`CsvSplit::Column`] (validation rows come back as training points).
+        write_csv_file(path, SaveMode::Overwrite, ',', &self.to_csv_records())?;
+        Some(())
+    }
+    pub fn write_csv<W: Write>(&self, writer: W) -> Option<()> {
+        /*!
+        Writes the dataset as CSV to any writer. See [`DataSet::to_csv`].
+        ```
+        # use neurnet::*;
+        let ds = DataSet::new(
+            vec![(vec![1.0, 2.5], vec![0.5]), (vec![-3.0, 0.125], vec![1.0])],
+            vec![(vec![4.0, 1e-9], vec![0.0])],
+        );
+        let mut buf: Vec<u8> = vec![];
+        ds.write_csv(&mut buf).unwrap();
+        assert!(String::from_utf8(buf.clone()).unwrap().starts_with("input_0,input_1,output_0,split\n"));
+
+        let loaded = DataSet::read_csv(
+            buf.as_slice(),
+            &CsvOptions {
+                outputs: vec!["output_0".into()],
+                split: CsvSplit::Column {
+                    column: "split".into(),
+                    testing_value: "testing".to_string(),
+                },
+                ..CsvOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(loaded.grab_training_data(), ds.grab_training_data());
+        assert_eq!(loaded.grab_testing_data(), ds.grab_testing_data());
+        ```
+        */
+        write_csv_records(writer, ',', &self.to_csv_records())
+    }
+    fn to_csv_records(&self) -> Vec<Vec<String>> {
+        let (input_size, output_size) = (self.input_dim(), self.output_dim());
+        let mut records: Vec<Vec<String>> = vec![];
+        records.push(
+            (0..input_size)
+                .map(|i| format!("input_{}", i))
+                .chain((0..output_size).map(|i| format!("output_{}", i)))
+                .chain(std::iter::once("split".to_string()))
+                .collect(),
+        );
+        let points = [
+            DataSplit::Training,
+            DataSplit::Testing,
+            DataSplit::Validation,
+        ]
+        .into_iter()
+        .flat_map(|split| (0..self.len(split)).map(move |i| (i, split)))
+        .filter_map(|(i, split)| Some((self.point(split, i)?, split)));
+        for (point, split) in points {
+            let split_name = match split {
+                DataSplit::Training => "training",
+                DataSplit::Testing => "testing",
+                DataSplit::Validation => "validation",
+            };
+            records.push(
+                point
+                    .0
+                    .iter()
+                    .chain(point.1.iter())
+                    .map(|value| value.to_string())
+                    .chain(std::iter::once(split_name.to_string()))
+                    .collect(),
+            );
+        }
+        records
+    }
+}
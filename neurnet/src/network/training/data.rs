@@ -1,23 +1,149 @@
-use super::super::super::files::{read_dset_file, write_dset_file};
+use super::super::super::files::{
+    read_dset, read_dset_file, write_dset, write_dset_file, DSetData, SaveMode,
+};
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::ops::Range;
+
+/// A single data point: (inputs, expected outputs). In-memory datasets lend out their data, while datasets read from disk hand out owned copies.
+pub type DataPoint<'a> = (Cow<'a, [f64]>, Cow<'a, [f64]>);
+
 /// The trait used for structs that can be fed to network training methods.
+///
+/// Data is accessed one point (or batch of points) at a time, so an implementation doesn't need to hold its whole dataset in memory. See [`DataSetFile`](super::dset_file::DataSetFile) for one that streams from disk.
 pub trait NetworkFood {
-    /// Method for getting a set of data points (inputs, expected outputs) for the training dataset
-    fn grab_training_data(&self) -> &Vec<(Vec<f64>, Vec<f64>)>;
-    /// Method for getting a set of data points (inputs, expected outputs) for the testing dataset
-    fn grab_testing_data(&self) -> &Vec<(Vec<f64>, Vec<f64>)>;
+    /// The number of data points in the given half of the dataset
+    fn len(&self, split: DataSplit) -> usize;
+    /// Gets the data point at the index in the given half of the dataset, or None if the index is out of range or the point can't be read.
+    fn get(&self, split: DataSplit, i: usize) -> Option<DataPoint<'_>>;
+    /// Whether the given half of the dataset has no data points
+    fn is_empty(&self, split: DataSplit) -> bool {
+        self.len(split) == 0
+    }
+    /// Gets every data point in the range. Implementations can override this to fetch the batch in one go.
+    fn batch(&self, split: DataSplit, range: Range<usize>) -> Vec<DataPoint<'_>> {
+        range.filter_map(|i| self.get(split, i)).collect()
+    }
+    /// Iterates over every data point in the given half of the dataset, in order.
+    fn points(&self, split: DataSplit) -> Points<'_, Self> {
+        Points {
+            food: self,
+            split,
+            next: 0,
+        }
+    }
+}
+
+/// An iterator over the data points in one half of a [`NetworkFood`], created by [`NetworkFood::points`].
+pub struct Points<'a, F: NetworkFood + ?Sized> {
+    food: &'a F,
+    split: DataSplit,
+    next: usize,
+}
+impl<'a, F: NetworkFood + ?Sized> Iterator for Points<'a, F> {
+    type Item = DataPoint<'a>;
+    fn next(&mut self) -> Option<DataPoint<'a>> {
+        while self.next < self.food.len(self.split) {
+            self.next += 1;
+            if let Some(point) = self.food.get(self.split, self.next - 1) {
+                return Some(point);
+            }
+        }
+        None
+    }
+}
+
+/// Picks one part of a dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataSplit {
+    Training,
+    Testing,
+    /// Points held back from training to tune against, kept separate from the testing points so those stay unseen until the end. See [`DataSet::split_validation`].
+    Validation,
 }
 /// The recommended implentation of the NetworkFood trait.
-#[derive(Debug)]
+///
+/// Each half of the dataset keeps its inputs and expected outputs in two contiguous row-major buffers, so every point must have the same number of inputs ([`DataSet::input_dim`]) and expected outputs ([`DataSet::output_dim`]). The dimensions are fixed by [`DataSet::with_dims`] or by the first point added.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "DataSetData"))]
 pub struct DataSet {
-    training_data: Vec<(Vec<f64>, Vec<f64>)>,
-    testing_data: Vec<(Vec<f64>, Vec<f64>)>,
+    dims: Option<(usize, usize)>,
+    training_data: PointBuffer,
+    testing_data: PointBuffer,
+    validation_data: PointBuffer,
+}
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct PointBuffer {
+    len: usize,
+    inputs: Vec<f64>,
+    outputs: Vec<f64>,
+}
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct DataSetData {
+    dims: Option<(usize, usize)>,
+    training_data: PointBuffer,
+    testing_data: PointBuffer,
+    validation_data: PointBuffer,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<DataSetData> for DataSet {
+    type Error = String;
+    fn try_from(data: DataSetData) -> Result<DataSet, String> {
+        //Every buffer has to hold exactly its number of points, with no points at all before the dimensions are fixed
+        let (input_dim, output_dim) = data.dims.unwrap_or((0, 0));
+        for buffer in [
+            &data.training_data,
+            &data.testing_data,
+            &data.validation_data,
+        ] {
+            let consistent = (data.dims.is_some() || buffer.len == 0)
+                && buffer.len.checked_mul(input_dim) == Some(buffer.inputs.len())
+                && buffer.len.checked_mul(output_dim) == Some(buffer.outputs.len());
+            if !consistent {
+                return Err("the points don't match the dataset's dimensions".to_string());
+            }
+        }
+        Ok(DataSet {
+            dims: data.dims,
+            training_data: data.training_data,
+            testing_data: data.testing_data,
+            validation_data: data.validation_data,
+        })
+    }
+}
+impl PointBuffer {
+    fn from_flat(
+        input_dim: usize,
+        output_dim: usize,
+        (inputs, outputs): (Vec<f64>, Vec<f64>),
+    ) -> Option<PointBuffer> {
+        let len = match (input_dim, output_dim) {
+            (0, 0) if inputs.is_empty() && outputs.is_empty() => 0,
+            (0, 0) => return None,
+            (0, _) => outputs.len() / output_dim,
+            _ => inputs.len() / input_dim,
+        };
+        if inputs.len() != len * input_dim || outputs.len() != len * output_dim {
+            return None;
+        }
+        Some(PointBuffer {
+            len,
+            inputs,
+            outputs,
+        })
+    }
 }
 impl NetworkFood for DataSet {
-    fn grab_training_data(&self) -> &Vec<(Vec<f64>, Vec<f64>)> {
-        &self.training_data
+    fn len(&self, split: DataSplit) -> usize {
+        self.grab_data(split).len
     }
-    fn grab_testing_data(&self) -> &Vec<(Vec<f64>, Vec<f64>)> {
-        &self.testing_data
+    fn get(&self, split: DataSplit, i: usize) -> Option<DataPoint<'_>> {
+        let (inputs, outputs) = self.point(split, i)?;
+        Some((Cow::Borrowed(inputs), Cow::Borrowed(outputs)))
     }
 }
 impl DataSet {
@@ -25,63 +151,257 @@ impl DataSet {
         training_data: Vec<(Vec<f64>, Vec<f64>)>,
         testing_data: Vec<(Vec<f64>, Vec<f64>)>,
     ) -> DataSet {
-        //! Creates a dataset based off a vector of the training data points and a vector of the testing data points
+        /*!
+        Creates a dataset based off a vector of the training data points and a vector of the testing data points
+        # Panics
+        Panics if the points don't all have the same number of inputs and expected outputs.
+        */
+        let mut ds = DataSet::empty();
+        for point in training_data {
+            ds.push_training_point(point);
+        }
+        for point in testing_data {
+            ds.push_testing_point(point);
+        }
+        ds
+    }
+    pub fn with_dims(input_dim: usize, output_dim: usize) -> DataSet {
+        /*!
+        Creates an empty dataset that only accepts points with the given number of inputs and expected outputs.
+        ```
+        # use neurnet::*;
+        let mut ds = DataSet::with_dims(2, 1);
+        assert!(ds.push_point(DataSplit::Training, &[1.0, 2.0], &[3.0]).is_some());
+        assert!(ds.push_point(DataSplit::Training, &[1.0], &[3.0]).is_none());
+        assert_eq!(ds.inputs(DataSplit::Training), &[1.0, 2.0]);
+        ```
+        */
+        DataSet {
+            dims: Some((input_dim, output_dim)),
+            ..DataSet::empty()
+        }
+    }
+    pub fn from_buffers(
+        input_dim: usize,
+        output_dim: usize,
+        training_data: (Vec<f64>, Vec<f64>),
+        testing_data: (Vec<f64>, Vec<f64>),
+    ) -> Option<DataSet> {
+        /*!
+        Creates a dataset straight from row-major buffers of (every point's inputs, every point's expected outputs). Returns None if the buffers don't hold the same number of whole points.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::from_buffers(
+            2,
+            1,
+            (vec![0.0, 1.0, 2.0, 3.0], vec![0.5, 1.5]),
+            (vec![], vec![]),
+        )
+        .unwrap();
+        assert_eq!(ds.point(DataSplit::Training, 1), Some((&[2.0, 3.0][..], &[1.5][..])));
+        ```
+        */
+        Some(DataSet {
+            dims: Some((input_dim, output_dim)),
+            training_data: PointBuffer::from_flat(input_dim, output_dim, training_data)?,
+            testing_data: PointBuffer::from_flat(input_dim, output_dim, testing_data)?,
+            validation_data: PointBuffer::default(),
+        })
+    }
+    pub fn input_dim(&self) -> usize {
+        //! The number of inputs in every data point, or 0 if the dimensions haven't been fixed yet.
+        self.dims.map_or(0, |dims| dims.0)
+    }
+    pub fn output_dim(&self) -> usize {
+        //! The number of expected outputs in every data point, or 0 if the dimensions haven't been fixed yet.
+        self.dims.map_or(0, |dims| dims.1)
+    }
+    pub(crate) fn empty_like(&self) -> DataSet {
         DataSet {
-            training_data,
-            testing_data,
+            dims: self.dims,
+            ..DataSet::empty()
+        }
+    }
+    pub(crate) fn map_points<F: FnMut(&mut [f64], &mut [f64])>(&mut self, mut f: F) {
+        let (input_dim, output_dim) = (self.input_dim(), self.output_dim());
+        for data in [
+            &mut self.training_data,
+            &mut self.testing_data,
+            &mut self.validation_data,
+        ] {
+            for i in 0..data.len {
+                f(
+                    &mut data.inputs[i * input_dim..(i + 1) * input_dim],
+                    &mut data.outputs[i * output_dim..(i + 1) * output_dim],
+                );
+            }
+        }
+    }
+    pub(crate) fn accepts(&self, input_dim: usize, output_dim: usize) -> bool {
+        self.dims.is_none_or(|dims| dims == (input_dim, output_dim))
+    }
+    pub fn point(&self, split: DataSplit, i: usize) -> Option<(&[f64], &[f64])> {
+        //! Gets views of the inputs and expected outputs of the data point at the index in the given half of the dataset.
+        let data = self.grab_data(split);
+        if i >= data.len {
+            return None;
+        }
+        let (input_dim, output_dim) = (self.input_dim(), self.output_dim());
+        Some((
+            &data.inputs[i * input_dim..(i + 1) * input_dim],
+            &data.outputs[i * output_dim..(i + 1) * output_dim],
+        ))
+    }
+    pub fn inputs(&self, split: DataSplit) -> &[f64] {
+        //! Gets the inputs of every data point in the given half of the dataset, one point after another.
+        &self.grab_data(split).inputs
+    }
+    pub fn outputs(&self, split: DataSplit) -> &[f64] {
+        //! Gets the expected outputs of every data point in the given half of the dataset, one point after another.
+        &self.grab_data(split).outputs
+    }
+    pub fn grab_training_data(&self) -> Vec<(Vec<f64>, Vec<f64>)> {
+        //! Gets a copy of every data point (inputs, expected outputs) in the training dataset. Prefer [`DataSet::point`] or [`DataSet::inputs`], which don't copy.
+        self.grab_points(DataSplit::Training)
+    }
+    pub fn grab_testing_data(&self) -> Vec<(Vec<f64>, Vec<f64>)> {
+        //! Gets a copy of every data point (inputs, expected outputs) in the testing dataset. Prefer [`DataSet::point`] or [`DataSet::inputs`], which don't copy.
+        self.grab_points(DataSplit::Testing)
+    }
+    fn grab_points(&self, split: DataSplit) -> Vec<(Vec<f64>, Vec<f64>)> {
+        (0..self.grab_data(split).len)
+            .filter_map(|i| self.point(split, i))
+            .map(|(inputs, outputs)| (inputs.to_vec(), outputs.to_vec()))
+            .collect()
+    }
+    fn grab_data(&self, split: DataSplit) -> &PointBuffer {
+        match split {
+            DataSplit::Training => &self.training_data,
+            DataSplit::Testing => &self.testing_data,
+            DataSplit::Validation => &self.validation_data,
         }
     }
     pub fn empty() -> DataSet {
         //! Generates an empty DataSet
         DataSet {
-            training_data: vec![],
-            testing_data: vec![],
+            dims: None,
+            training_data: PointBuffer::default(),
+            testing_data: PointBuffer::default(),
+            validation_data: PointBuffer::default(),
         }
     }
     pub fn save(&self, path: &str) -> Option<()> {
         /*!
-        Saves a dataset to the path, return None if it was not sucessful,
-        ```
+        Atomically saves a dataset to the path, replacing any file that is already there. Return None if it was not sucessful.
+        ```no_run
+        # use neurnet::*;
         let ds = DataSet::empty();
         ds.save("dataset.dset").unwrap();
         ```
          */
-        let training_count: u32 = self.training_data.len() as u32;
-        let testing_count: u32 = self.testing_data.len() as u32;
-
+        self.save_with_mode(path, SaveMode::Overwrite)?;
+        Some(())
+    }
+    pub fn save_with_mode(&self, path: &str, mode: SaveMode) -> Option<String> {
+        //! Atomically saves a dataset to the path, with the [`SaveMode`] deciding what happens if the path already exists. Returns the path that was written to, or None if it was not sucessful.
         write_dset_file(
             path,
-            training_count,
-            testing_count,
-            self.training_data[0].0.len() as u32,
-            self.training_data[0].1.len() as u32,
-            self.training_data.clone(),
-            self.testing_data.clone(),
-        )?;
+            mode,
+            self.input_dim() as u32,
+            self.output_dim() as u32,
+            (&self.training_data.inputs, &self.training_data.outputs),
+            (&self.testing_data.inputs, &self.testing_data.outputs),
+            (&self.validation_data.inputs, &self.validation_data.outputs),
+        )
+    }
+    pub fn write_to<W: Write>(&self, writer: W) -> Option<()> {
+        /*!
+        Writes a dataset in the .dset format to any writer, returns None if it was not sucessful.
+        ```
+        # use neurnet::*;
+        let mut ds = DataSet::new(vec![(vec![1.0], vec![0.5])], vec![(vec![2.0], vec![1.0])]);
+        let mut buf: Vec<u8> = vec![];
+        ds.write_to(&mut buf).unwrap();
 
-        Some(())
+        let ds_read = DataSet::read_from(buf.as_slice()).unwrap();
+        assert_eq!(ds_read.grab_training_data(), ds.grab_training_data());
+        assert_eq!(ds_read.grab_testing_data(), ds.grab_testing_data());
+
+        ds.push_point(DataSplit::Validation, &[3.0], &[1.5]).unwrap();
+        let mut buf: Vec<u8> = vec![];
+        ds.write_to(&mut buf).unwrap();
+        assert_eq!(DataSet::read_from(buf.as_slice()).unwrap(), ds);
+        ```
+         */
+        write_dset(
+            writer,
+            self.input_dim() as u32,
+            self.output_dim() as u32,
+            (&self.training_data.inputs, &self.training_data.outputs),
+            (&self.testing_data.inputs, &self.testing_data.outputs),
+            (&self.validation_data.inputs, &self.validation_data.outputs),
+        )
     }
     pub fn load(path: &str) -> Option<DataSet> {
         /*!
         Loads a dataset from the given path, returns Some(DataSet) if sucessful else None.
-        ```
+        ```no_run
+        # use neurnet::*;
         let ds = DataSet::empty();
         ds.save("dataset.dset").unwrap();
         let ds_loaded = DataSet::load("dataset.dset").unwrap();
         ```
          */
-        let data = read_dset_file(path)?;
-        Some(
-            DataSet { training_data: data.0, testing_data: data.1 }
-        )
+        DataSet::from_dset_data(read_dset_file(path)?)
+    }
+    pub fn read_from<R: Read>(reader: R) -> Option<DataSet> {
+        //! Reads a dataset in the .dset format from any reader, returns Some(DataSet) if sucessful else None.
+        DataSet::from_dset_data(read_dset(reader)?)
+    }
+    fn from_dset_data(data: DSetData) -> Option<DataSet> {
+        let (input_size, output_size, [training_data, testing_data, validation_data]) = data;
+        if (input_size, output_size) == (0, 0) {
+            //Datasets without any points are saved without dimensions
+            return Some(DataSet::empty());
+        }
+        let (input_dim, output_dim) = (input_size as usize, output_size as usize);
+        let mut ds = DataSet::from_buffers(input_dim, output_dim, training_data, testing_data)?;
+        ds.validation_data = PointBuffer::from_flat(input_dim, output_dim, validation_data)?;
+        Some(ds)
+    }
+    pub fn push_point(&mut self, split: DataSplit, inputs: &[f64], outputs: &[f64]) -> Option<()> {
+        //! Adds a data point to the given half of the dataset. Returns None (adding nothing) if its dimensions don't match the dataset's.
+        if !self.accepts(inputs.len(), outputs.len()) {
+            return None;
+        }
+        self.dims = Some((inputs.len(), outputs.len()));
+        let data = match split {
+            DataSplit::Training => &mut self.training_data,
+            DataSplit::Testing => &mut self.testing_data,
+            DataSplit::Validation => &mut self.validation_data,
+        };
+        data.inputs.extend_from_slice(inputs);
+        data.outputs.extend_from_slice(outputs);
+        data.len += 1;
+        Some(())
     }
     pub fn push_training_point(&mut self, data_point: (Vec<f64>, Vec<f64>)) {
-        //! Adds a data point to the training dataset
-        self.training_data.push(data_point);
+        /*!
+        Adds a data point to the training dataset
+        # Panics
+        Panics if the point's dimensions don't match the dataset's. See [`DataSet::push_point`] for a version that doesn't panic.
+        */
+        self.push_point(DataSplit::Training, &data_point.0, &data_point.1)
+            .expect("Data point dimensions don't match the dataset's");
     }
     pub fn push_testing_point(&mut self, data_point: (Vec<f64>, Vec<f64>)) {
-        //! Adds a data point ot the testing dataset
-        self.testing_data.push(data_point);
+        /*!
+        Adds a data point ot the testing dataset
+        # Panics
+        Panics if the point's dimensions don't match the dataset's. See [`DataSet::push_point`] for a version that doesn't panic.
+        */
+        self.push_point(DataSplit::Testing, &data_point.0, &data_point.1)
+            .expect("Data point dimensions don't match the dataset's");
     }
     pub fn gen_from_fn<F: Fn(Vec<f64>) -> Vec<f64>>(
         func: F,
@@ -91,6 +411,7 @@ impl DataSet {
         /*!
         Takes the input half of the training/testing points and a closure that is used to generate the expected outputs for all of the inputs supplied.
         ```
+        # use neurnet::*;
         let training_inputs: Vec<Vec<f64>> = (-1000..1000).map(|x| vec![(x as f64) / 5.0]).collect();
         let testing_inputs: Vec<Vec<f64>> = (-100..100).map(|x| vec![x as f64]).collect();
         let ds = DataSet::gen_from_fn(
@@ -100,23 +421,15 @@ impl DataSet {
         );
         ```
         */
-        let training_data = {
-            let mut buf: Vec<(Vec<f64>, Vec<f64>)> = vec![];
-            for x in training_points {
-                buf.push((x.clone(), func(x)))
-            }
-            buf
-        };
-        let testing_data = {
-            let mut buf: Vec<(Vec<f64>, Vec<f64>)> = vec![];
-            for x in testing_points {
-                buf.push((x.clone(), func(x)))
-            }
-            buf
-        };
-        DataSet {
-            training_data,
-            testing_data,
+        let mut ds = DataSet::empty();
+        for x in training_points {
+            let y = func(x.clone());
+            ds.push_training_point((x, y));
+        }
+        for x in testing_points {
+            let y = func(x.clone());
+            ds.push_testing_point((x, y));
         }
+        ds
     }
 }
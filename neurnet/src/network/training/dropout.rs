@@ -0,0 +1,165 @@
+use super::Network;
+use rand::Rng;
+
+/// Randomly zeroes a layer's outputs during training, set with [`Network::set_dropout`]. The value is the chance each output is dropped.
+///
+/// Dropout only happens in the training methods and [`Network::pulse_with_dropout`]; [`Network::pulse`] is always deterministic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dropout {
+    /// Kept outputs pass through unchanged while training, and every output is scaled by the keep chance when not training.
+    Standard(f64),
+    /// Kept outputs are scaled up by one over the keep chance while training, and outputs pass through unchanged when not training.
+    Inverted(f64),
+}
+
+impl Dropout {
+    pub(crate) fn setting(&self) -> (&'static str, f64) {
+        //How a layer record in a .neur file stores the dropout of its layer
+        match self {
+            Dropout::Standard(chance) => ("standard_dropout", *chance),
+            Dropout::Inverted(chance) => ("inverted_dropout", *chance),
+        }
+    }
+    pub(crate) fn take_setting(settings: &mut Vec<(String, f64)>) -> Option<Dropout> {
+        //Removes the dropout from a layer record's settings, leaving only the layer's own
+        let position = settings
+            .iter()
+            .position(|(name, _)| name == "standard_dropout" || name == "inverted_dropout")?;
+        let (name, chance) = settings.remove(position);
+        Some(match name.as_str() {
+            "standard_dropout" => Dropout::Standard(chance),
+            _ => Dropout::Inverted(chance),
+        })
+    }
+}
+
+/// The multiplier for every output of every layer in one stochastic pass.
+pub(crate) type DropoutMasks = Vec<Vec<f64>>;
+
+impl Network {
+    pub fn set_dropout(&mut self, layer: usize, dropout: Option<Dropout>) {
+        //! Sets the dropout applied to the outputs of one layer, where layer 0 is the first layer after the inputs. Dropout is usually left off the last layer.
+        //! ```
+        //! # use neurnet::*;
+        //! let mut nn = Network::with_activation(vec![1, 1], Activation::Identity, (1.0, 1.0), (0.0, 0.0));
+        //! nn.set_dropout(0, Some(Dropout::Standard(0.25)));
+        //! // Standard dropout scales outputs by the keep chance when not training
+        //! assert_eq!(nn.pulse(vec![2.0]), vec![1.5]);
+        //! nn.set_dropout(0, Some(Dropout::Inverted(0.25)));
+        //! assert_eq!(nn.pulse(vec![2.0]), vec![2.0]);
+        //!
+        //! // Dropout is saved with the network, and folded into the weights of an ONNX export
+        //! nn.set_dropout(0, Some(Dropout::Standard(0.25)));
+        //! let mut buf: Vec<u8> = vec![];
+        //! nn.write_to(&mut buf).unwrap();
+        //! let loaded = Network::read_from(buf.as_slice(), |x| x).unwrap();
+        //! assert_eq!(loaded.get_dropout(0), Some(Dropout::Standard(0.25)));
+        //! assert_eq!(loaded.pulse(vec![2.0]), vec![1.5]);
+        //! let mut buf: Vec<u8> = vec![];
+        //! nn.write_onnx(&mut buf).unwrap();
+        //! assert_eq!(Network::read_onnx(buf.as_slice()).unwrap().pulse(vec![2.0]), vec![1.5]);
+        //! ```
+        //! # Panics
+        //! <ul>
+        //! <li> If the layer doesn't exist.
+        //! </ul>
+        *self.dropout.get_mut(layer).expect("A viable layer ID") = dropout;
+    }
+    pub fn get_dropout(&self, layer: usize) -> Option<Dropout> {
+        *self.dropout.get(layer)?
+    }
+    pub(crate) fn has_dropout(&self) -> bool {
+        self.dropout.iter().any(|dropout| dropout.is_some())
+    }
+    pub(crate) fn dropout_multiplier(
+        &self,
+        layer: usize,
+        neuron: usize,
+        mask: Option<&Vec<f64>>,
+    ) -> f64 {
+        match (mask, self.dropout[layer]) {
+            (Some(mask), _) => mask[neuron],
+            (None, Some(Dropout::Standard(chance))) => 1.0 - chance,
+            (None, _) => 1.0,
+        }
+    }
+    pub(crate) fn apply_dropout(&self, layer: usize, outputs: &mut [f64], mask: Option<&Vec<f64>>) {
+        if mask.is_none() && !matches!(self.dropout[layer], Some(Dropout::Standard(_))) {
+            return;
+        }
+        for (neuron, output) in outputs.iter_mut().enumerate() {
+            *output *= self.dropout_multiplier(layer, neuron, mask);
+        }
+    }
+    pub(crate) fn sample_dropout_masks<R: Rng + ?Sized>(&self, rng: &mut R) -> DropoutMasks {
+        self.layers
+            .iter()
+            .zip(self.dropout.iter())
+            .map(|(layer, dropout)| {
+                (0..layer.output_len())
+                    .map(|_| match *dropout {
+                        None => 1.0,
+                        Some(Dropout::Standard(chance)) | Some(Dropout::Inverted(chance))
+                            if rng.gen::<f64>() < chance =>
+                        {
+                            0.0
+                        }
+                        Some(Dropout::Standard(_)) => 1.0,
+                        Some(Dropout::Inverted(chance)) => 1.0 / (1.0 - chance),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    pub fn pulse_with_dropout<R: Rng + ?Sized>(&self, input: Vec<f64>, rng: &mut R) -> Vec<f64> {
+        //! Runs the input through the network like [`Network::pulse`], but with every layer's dropout active as it is during training.
+        //! # Panics
+        //! <ul>
+        //! <li> If the input vector's length is not equal to the amount of input neurons.
+        //! </ul>
+        let masks = self.sample_dropout_masks(rng);
+        self.pulse_masked(input, Some(&masks))
+    }
+    pub fn pulse_monte_carlo<R: Rng + ?Sized>(
+        &self,
+        input: Vec<f64>,
+        passes: usize,
+        rng: &mut R,
+    ) -> (Vec<f64>, Vec<f64>) {
+        /*!
+        Monte Carlo dropout: runs the input through [`Network::pulse_with_dropout`] the given number of times and returns the mean and variance of each output. The variance estimates how uncertain the network is about that output.
+        ```
+        # use neurnet::*;
+        use rand::{rngs::StdRng, SeedableRng};
+        let mut nn = Network::with_activation(vec![1, 50, 1], Activation::Relu, (0.1, 1.0), (0.0, 0.1));
+        nn.set_dropout(0, Some(Dropout::Inverted(0.5)));
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let (mean, variance) = nn.pulse_monte_carlo(vec![1.0], 200, &mut rng);
+        assert!(variance[0] > 0.0);
+        // Inverted dropout keeps the expected output equal to the deterministic one
+        let expected = nn.pulse(vec![1.0])[0];
+        assert!((mean[0] - expected).abs() < 0.1 * expected);
+        assert_eq!(nn.pulse(vec![1.0]), nn.pulse(vec![1.0]));
+        ```
+        */
+        let outputs: Vec<Vec<f64>> = (0..passes)
+            .map(|_| self.pulse_with_dropout(input.clone(), rng))
+            .collect();
+        let output_count = *self.shape.last().unwrap();
+        let mean: Vec<f64> = (0..output_count)
+            .map(|i| outputs.iter().map(|output| output[i]).sum::<f64>() / passes as f64)
+            .collect();
+        let variance: Vec<f64> = (0..output_count)
+            .map(|i| {
+                outputs
+                    .iter()
+                    .map(|output| (output[i] - mean[i]).powi(2))
+                    .sum::<f64>()
+                    / passes as f64
+            })
+            .collect();
+        (mean, variance)
+    }
+}
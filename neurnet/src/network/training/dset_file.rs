@@ -0,0 +1,169 @@
+use super::super::super::files::{
+    open_file, read_dset_header, DSET_HEADER_LEN, DSET_VALIDATION_COUNT_LEN,
+};
+use super::data::{DataPoint, DataSplit, NetworkFood};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// A [`NetworkFood`] that streams its data points from a .dset file on disk instead of loading them into memory, for datasets too large to fit in RAM.
+///
+/// Points are read as they are asked for, so training reads through the file once per iteration.
+#[derive(Debug)]
+pub struct DataSetFile {
+    reader: RefCell<(BufReader<File>, u64)>, //The reader and its current position in the file
+    training_count: usize,
+    testing_count: usize,
+    validation_count: usize,
+    input_size: usize,
+    output_size: usize,
+}
+
+impl DataSetFile {
+    pub fn open(path: &str) -> Option<DataSetFile> {
+        /*!
+        Opens a .dset file, such as one saved with [`DataSet::save`](super::data::DataSet::save), reading only its header. Returns None if the file can't be opened or its size doesn't match the point counts in its header.
+        ```
+        # use neurnet::*;
+        let path = std::env::temp_dir().join(format!("neurnet_dset_file_{}.dset", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let mut ds = DataSet::gen_from_fn(
+            |x| vec![0.5 * x[0]],
+            (0..100).map(|x| vec![x as f64]).collect(),
+            (0..10).map(|x| vec![x as f64]).collect(),
+        );
+        ds.split_validation(0.2, 0);
+        ds.save(&path).unwrap();
+
+        let file = DataSetFile::open(&path).unwrap();
+        assert_eq!(file.len(DataSplit::Training), 80);
+        assert_eq!(file.get(DataSplit::Validation, 5), ds.get(DataSplit::Validation, 5));
+        let point = file.get(DataSplit::Testing, 3).unwrap();
+        assert_eq!((&point.0[..], &point.1[..]), (&[3.0][..], &[1.5][..]));
+        assert_eq!(file.batch(DataSplit::Training, 40..42).len(), 2);
+
+        let nn = Network::new(vec![1, 2, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+        assert_eq!(nn.test(&file), nn.test(&ds));
+        assert_eq!(nn.validate(&file), nn.validate(&ds));
+
+        // Files with more or less data than their header says are rejected
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.push(0);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(DataSetFile::open(&path).is_none());
+        bytes[0] = 0xff;
+        bytes.pop();
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(DataSetFile::open(&path).is_none());
+        std::fs::remove_file(&path).unwrap();
+        ```
+        */
+        let mut reader = BufReader::new(open_file(path)?);
+        let (training_count, testing_count, input_size, output_size) =
+            read_dset_header(&mut reader)?;
+        let mut file = DataSetFile {
+            reader: RefCell::new((reader, DSET_HEADER_LEN)),
+            training_count: training_count as usize,
+            testing_count: testing_count as usize,
+            validation_count: 0,
+            input_size: input_size as usize,
+            output_size: output_size as usize,
+        };
+        let file_len = file.reader.borrow().0.get_ref().metadata().ok()?.len();
+        //The counts come from the file, so the space they take is checked against its size before any offset is worked out from them
+        let point_len = file.point_len();
+        let points_len = |count: u64| count.checked_mul(point_len);
+        let testing_end = points_len(training_count as u64 + testing_count as u64)?
+            .checked_add(DSET_HEADER_LEN)?;
+        if file_len != testing_end {
+            //Newer files can hold validation points after the testing points
+            let validation_start = testing_end.checked_add(DSET_VALIDATION_COUNT_LEN)?;
+            if file_len < validation_start {
+                return None;
+            }
+            let (reader, position) = file.reader.get_mut();
+            let mut count: [u8; 4] = [0; 4];
+            reader.seek(SeekFrom::Start(testing_end)).ok()?;
+            reader.read_exact(&mut count).ok()?;
+            *position = validation_start;
+            let validation_count = u32::from_be_bytes(count);
+            if file_len != points_len(validation_count as u64)?.checked_add(validation_start)? {
+                return None;
+            }
+            file.validation_count = validation_count as usize;
+        }
+        Some(file)
+    }
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+    pub fn output_size(&self) -> usize {
+        self.output_size
+    }
+    fn point_len(&self) -> u64 {
+        (self.input_size as u64 + self.output_size as u64) * 8
+    }
+    fn offset(&self, split: DataSplit, i: usize) -> u64 {
+        let i = match split {
+            DataSplit::Training => i,
+            DataSplit::Testing => self.training_count + i,
+            DataSplit::Validation => {
+                let i = self.training_count + self.testing_count + i;
+                return DSET_HEADER_LEN + DSET_VALIDATION_COUNT_LEN + i as u64 * self.point_len();
+            }
+        };
+        DSET_HEADER_LEN + i as u64 * self.point_len()
+    }
+    fn read_points(&self, split: DataSplit, range: Range<usize>) -> Option<Vec<DataPoint<'_>>> {
+        if range.end > self.len(split) || range.start > range.end {
+            return None;
+        }
+        let mut reader = self.reader.borrow_mut();
+        let (file, position) = &mut *reader;
+        let offset = self.offset(split, range.start);
+        if *position != offset {
+            //Sequential reads keep using the buffer, anything else needs a seek
+            file.seek(SeekFrom::Start(offset)).ok()?;
+        }
+        let mut bytes = vec![0u8; range.len() * self.point_len() as usize];
+        let read = file.read_exact(&mut bytes);
+        *position = match read {
+            Ok(_) => offset + bytes.len() as u64,
+            Err(_) => u64::MAX,
+        };
+        read.ok()?;
+
+        let values: Vec<f64> = bytes
+            .chunks_exact(8)
+            .map(|b| f64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+            .collect();
+        Some(
+            values
+                .chunks_exact((self.input_size + self.output_size).max(1))
+                .take(range.len())
+                .map(|point| {
+                    let (inputs, outputs) = point.split_at(self.input_size);
+                    (Cow::Owned(inputs.to_vec()), Cow::Owned(outputs.to_vec()))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl NetworkFood for DataSetFile {
+    fn len(&self, split: DataSplit) -> usize {
+        match split {
+            DataSplit::Training => self.training_count,
+            DataSplit::Testing => self.testing_count,
+            DataSplit::Validation => self.validation_count,
+        }
+    }
+    fn get(&self, split: DataSplit, i: usize) -> Option<DataPoint<'_>> {
+        self.read_points(split, i..i + 1)?.pop()
+    }
+    fn batch(&self, split: DataSplit, range: Range<usize>) -> Vec<DataPoint<'_>> {
+        self.read_points(split, range).unwrap_or_default()
+    }
+}
@@ -0,0 +1,444 @@
+use super::super::super::files::{write_csv_file, write_csv_records, SaveMode};
+use super::data::{DataSplit, NetworkFood};
+use super::Network;
+use std::fmt;
+use std::io::Write;
+
+/// How per-class precision, recall and F1 scores are combined into one score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Average {
+    /// The unweighted mean of every class's score.
+    Macro,
+    /// The score of every prediction pooled together, regardless of class.
+    Micro,
+    /// The mean of every class's score, weighted by how many points belong to the class.
+    Weighted,
+}
+
+/// Classification and regression metrics for a network's predictions on a dataset, created by [`Network::evaluate`].
+///
+/// A point's class is the index of its largest value, or for networks with a single output, whether the value is at least 0.5.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationReport {
+    predictions: Vec<Vec<f64>>,
+    targets: Vec<Vec<f64>>,
+    confusion: Vec<Vec<usize>>,
+}
+
+impl Network {
+    pub fn evaluate(&self, food: &impl NetworkFood) -> EvaluationReport {
+        /*!
+        Runs through the entire set of testing data and measures how well the network's outputs match the expected outputs.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::new(
+            vec![],
+            vec![
+                (vec![1.0, 0.0], vec![1.0, 0.0]),
+                (vec![0.0, 1.0], vec![0.0, 1.0]),
+                (vec![0.2, 0.8], vec![1.0, 0.0]),
+            ],
+        );
+        // Passes its inputs straight through, so the inputs are the predictions
+        let mut nn = Network::new(vec![2, 2], |x| x, (0.0, 0.0), (0.0, 0.0));
+        nn.set_weight(0, 0, 0, 1.0);
+        nn.set_weight(0, 1, 1, 1.0);
+
+        let report = nn.evaluate(&ds);
+        assert_eq!(report.confusion_matrix(), &vec![vec![1, 1], vec![0, 1]]);
+        assert!((report.accuracy() - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(report.top_k_accuracy(2), 1.0);
+        assert_eq!(report.precision(Average::Macro), 0.75);
+        assert_eq!(report.recall(Average::Micro), report.accuracy());
+        let printed = report.to_string();
+        assert!(printed.contains("confusion matrix"));
+        assert!(printed.ends_with("  1 1\n  0 1\n"));
+        ```
+        */
+        self.evaluate_split(food, DataSplit::Testing)
+    }
+    pub fn evaluate_split(&self, food: &impl NetworkFood, split: DataSplit) -> EvaluationReport {
+        //! Measures the network on the given part of the dataset. See [`Network::evaluate`].
+        let mut predictions: Vec<Vec<f64>> = vec![];
+        let mut targets: Vec<Vec<f64>> = vec![];
+        for point in food.points(split) {
+            predictions.push(self.pulse(point.0.to_vec()));
+            targets.push(point.1.into_owned());
+        }
+        EvaluationReport::new(predictions, targets)
+    }
+}
+
+fn class_of(values: &[f64]) -> usize {
+    match values.len() {
+        1 => (values[0] >= 0.5) as usize,
+        _ => (0..values.len())
+            .max_by(|a, b| values[*a].total_cmp(&values[*b]))
+            .unwrap_or(0),
+    }
+}
+
+impl EvaluationReport {
+    pub fn new(predictions: Vec<Vec<f64>>, targets: Vec<Vec<f64>>) -> EvaluationReport {
+        /*!
+        Creates a report from a network's predictions and the matching expected outputs, one vector per point.
+        # Panics
+        <ul>
+        <li> If there are different numbers of predictions and targets.
+        </ul>
+        */
+        assert_eq!(
+            predictions.len(),
+            targets.len(),
+            "Every prediction needs a target"
+        );
+        let classes = match targets.first().map_or(0, |target| target.len()) {
+            1 => 2,
+            outputs => outputs,
+        };
+        let mut confusion = vec![vec![0; classes]; classes];
+        for (prediction, target) in predictions.iter().zip(targets.iter()) {
+            let (actual, predicted) = (class_of(target), class_of(prediction));
+            if actual < classes && predicted < classes {
+                confusion[actual][predicted] += 1;
+            }
+        }
+        EvaluationReport {
+            predictions,
+            targets,
+            confusion,
+        }
+    }
+    pub fn len(&self) -> usize {
+        //! The number of points the report was computed from.
+        self.targets.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+    pub fn classes(&self) -> usize {
+        self.confusion.len()
+    }
+    pub fn confusion_matrix(&self) -> &Vec<Vec<usize>> {
+        //! The number of points of each class (the outer index) predicted as each class (the inner index).
+        &self.confusion
+    }
+    pub fn accuracy(&self) -> f64 {
+        //! The fraction of points whose predicted class is their actual class.
+        let correct: usize = (0..self.classes()).map(|c| self.confusion[c][c]).sum();
+        correct as f64 / self.len() as f64
+    }
+    pub fn top_k_accuracy(&self, k: usize) -> f64 {
+        //! The fraction of points whose actual class is among the k classes the network scored highest.
+        let hits = self
+            .predictions
+            .iter()
+            .zip(self.targets.iter())
+            .filter(|(prediction, target)| {
+                let actual = class_of(target);
+                if prediction.len() == 1 {
+                    return k >= 2 || class_of(prediction) == actual;
+                }
+                let higher = prediction
+                    .iter()
+                    .filter(|score| **score > prediction[actual])
+                    .count();
+                higher < k
+            })
+            .count();
+        hits as f64 / self.len() as f64
+    }
+    fn class_counts(&self, c: usize) -> (f64, f64, f64) {
+        //(true positives, predicted as the class, actually the class)
+        let true_positives = self.confusion[c][c] as f64;
+        let predicted = self.confusion.iter().map(|row| row[c]).sum::<usize>() as f64;
+        let actual = self.confusion[c].iter().sum::<usize>() as f64;
+        (true_positives, predicted, actual)
+    }
+    fn average<F: Fn(f64, f64, f64) -> f64>(&self, average: Average, score: F) -> f64 {
+        let classes = self.classes();
+        match average {
+            Average::Micro => {
+                let (mut tp, mut predicted, mut actual) = (0.0, 0.0, 0.0);
+                for c in 0..classes {
+                    let counts = self.class_counts(c);
+                    tp += counts.0;
+                    predicted += counts.1;
+                    actual += counts.2;
+                }
+                score(tp, predicted, actual)
+            }
+            Average::Macro => {
+                (0..classes)
+                    .map(|c| {
+                        let (tp, predicted, actual) = self.class_counts(c);
+                        score(tp, predicted, actual)
+                    })
+                    .sum::<f64>()
+                    / classes as f64
+            }
+            Average::Weighted => {
+                (0..classes)
+                    .map(|c| {
+                        let (tp, predicted, actual) = self.class_counts(c);
+                        score(tp, predicted, actual) * actual
+                    })
+                    .sum::<f64>()
+                    / self.len() as f64
+            }
+        }
+    }
+    pub fn precision(&self, average: Average) -> f64 {
+        //! The fraction of points predicted as a class that actually belong to it. Classes that are never predicted score 0.
+        self.average(average, |tp, predicted, _| {
+            if predicted == 0.0 {
+                0.0
+            } else {
+                tp / predicted
+            }
+        })
+    }
+    pub fn recall(&self, average: Average) -> f64 {
+        //! The fraction of points of a class that are predicted as it. Classes without any points score 0.
+        self.average(
+            average,
+            |tp, _, actual| {
+                if actual == 0.0 {
+                    0.0
+                } else {
+                    tp / actual
+                }
+            },
+        )
+    }
+    pub fn f1(&self, average: Average) -> f64 {
+        //! The harmonic mean of precision and recall.
+        self.average(average, |tp, predicted, actual| {
+            if predicted + actual == 0.0 {
+                0.0
+            } else {
+                2.0 * tp / (predicted + actual)
+            }
+        })
+    }
+    pub fn roc_auc(&self) -> Option<f64> {
+        /*!
+        The area under the ROC curve for binary classifiers, being the chance a random positive point scores higher than a random negative one. The score is the only output, or the second output of a network with two. Returns None for other networks or if only one class is present.
+        ```
+        # use neurnet::*;
+        let report = EvaluationReport::new(
+            vec![vec![0.1], vec![0.4], vec![0.35], vec![0.8]],
+            vec![vec![0.0], vec![0.0], vec![1.0], vec![1.0]],
+        );
+        assert_eq!(report.roc_auc(), Some(0.75));
+        ```
+        */
+        let score_i = match self.targets.first()?.len() {
+            1 => 0,
+            2 => 1,
+            _ => return None,
+        };
+        let mut scored: Vec<(f64, bool)> = self
+            .predictions
+            .iter()
+            .zip(self.targets.iter())
+            .map(|(prediction, target)| (prediction[score_i], class_of(target) == 1))
+            .collect();
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        //Mann-Whitney U statistic, with tied scores sharing their average rank
+        let mut positive_rank_sum = 0.0;
+        let mut i = 0;
+        while i < scored.len() {
+            let mut j = i;
+            while j < scored.len() && scored[j].0 == scored[i].0 {
+                j += 1;
+            }
+            let rank = (i + j + 1) as f64 / 2.0;
+            positive_rank_sum += rank * scored[i..j].iter().filter(|s| s.1).count() as f64;
+            i = j;
+        }
+        let positives = scored.iter().filter(|s| s.1).count() as f64;
+        let negatives = scored.len() as f64 - positives;
+        if positives == 0.0 || negatives == 0.0 {
+            return None;
+        }
+        Some((positive_rank_sum - positives * (positives + 1.0) / 2.0) / (positives * negatives))
+    }
+    pub fn log_loss(&self) -> f64 {
+        //! The mean cross-entropy between the expected outputs and the network's outputs treated as probabilities. Outputs are clipped to 1e-15..1-1e-15, and networks with several outputs have them normalized to sum to 1.
+        const EPSILON: f64 = 1e-15;
+        let total: f64 = self
+            .predictions
+            .iter()
+            .zip(self.targets.iter())
+            .map(|(prediction, target)| {
+                if prediction.len() == 1 {
+                    let p = prediction[0].clamp(EPSILON, 1.0 - EPSILON);
+                    -(target[0] * p.ln() + (1.0 - target[0]) * (1.0 - p).ln())
+                } else {
+                    let clipped: Vec<f64> = prediction
+                        .iter()
+                        .map(|p| p.clamp(EPSILON, 1.0 - EPSILON))
+                        .collect();
+                    let sum: f64 = clipped.iter().sum();
+                    -clipped
+                        .iter()
+                        .zip(target.iter())
+                        .map(|(p, t)| t * (p / sum).ln())
+                        .sum::<f64>()
+                }
+            })
+            .sum();
+        total / self.len() as f64
+    }
+    fn errors(&self) -> impl Iterator<Item = f64> + '_ {
+        self.predictions
+            .iter()
+            .zip(self.targets.iter())
+            .flat_map(|(prediction, target)| {
+                prediction.iter().zip(target.iter()).map(|(p, t)| p - t)
+            })
+    }
+    pub fn mae(&self) -> f64 {
+        //! The mean absolute error across every output of every point.
+        let (sum, count) = self
+            .errors()
+            .fold((0.0, 0), |(sum, count), e| (sum + e.abs(), count + 1));
+        sum / count as f64
+    }
+    pub fn rmse(&self) -> f64 {
+        //! The root mean squared error across every output of every point.
+        let (sum, count) = self
+            .errors()
+            .fold((0.0, 0), |(sum, count), e| (sum + e * e, count + 1));
+        (sum / count as f64).sqrt()
+    }
+    pub fn r2(&self) -> f64 {
+        /*!
+        The coefficient of determination, averaged over the outputs. 1 is a perfect fit, while 0 is no better than always predicting the mean.
+        ```
+        # use neurnet::*;
+        let report = EvaluationReport::new(
+            vec![vec![1.0], vec![2.0], vec![3.0]],
+            vec![vec![1.0], vec![2.0], vec![4.0]],
+        );
+        assert!((report.mae() - 1.0 / 3.0).abs() < 1e-12);
+        assert!((report.rmse() - (1.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert!((report.r2() - (1.0 - 1.0 / (14.0 / 3.0))).abs() < 1e-12);
+        ```
+        */
+        let outputs = self.targets.first().map_or(0, |target| target.len());
+        let mut total = 0.0;
+        for output in 0..outputs {
+            let mean = self.targets.iter().map(|t| t[output]).sum::<f64>() / self.len() as f64;
+            let (mut residual, mut variance) = (0.0, 0.0);
+            for (prediction, target) in self.predictions.iter().zip(self.targets.iter()) {
+                residual += (target[output] - prediction[output]).powi(2);
+                variance += (target[output] - mean).powi(2);
+            }
+            total += 1.0 - residual / variance;
+        }
+        total / outputs as f64
+    }
+    fn metrics(&self) -> Vec<(String, String)> {
+        let mut metrics: Vec<(String, String)> = vec![
+            ("points".to_string(), self.len().to_string()),
+            ("accuracy".to_string(), self.accuracy().to_string()),
+        ];
+        for k in 2..self.classes().min(5) + 1 {
+            metrics.push((
+                format!("top_{}_accuracy", k),
+                self.top_k_accuracy(k).to_string(),
+            ));
+        }
+        for (name, average) in [
+            ("macro", Average::Macro),
+            ("micro", Average::Micro),
+            ("weighted", Average::Weighted),
+        ] {
+            metrics.push((
+                format!("precision_{}", name),
+                self.precision(average).to_string(),
+            ));
+            metrics.push((format!("recall_{}", name), self.recall(average).to_string()));
+            metrics.push((format!("f1_{}", name), self.f1(average).to_string()));
+        }
+        if let Some(auc) = self.roc_auc() {
+            metrics.push(("roc_auc".to_string(), auc.to_string()));
+        }
+        metrics.push(("log_loss".to_string(), self.log_loss().to_string()));
+        metrics.push(("mae".to_string(), self.mae().to_string()));
+        metrics.push(("rmse".to_string(), self.rmse().to_string()));
+        metrics.push(("r2".to_string(), self.r2().to_string()));
+        metrics
+    }
+    pub fn to_csv(&self, path: &str) -> Option<()> {
+        //! Saves every metric to a CSV file with `metric` and `value` columns. The confusion matrix follows as `confusion_<actual>_<predicted>` rows.
+        write_csv_file(path, SaveMode::Overwrite, ',', &self.to_csv_records())?;
+        Some(())
+    }
+    pub fn write_csv<W: Write>(&self, writer: W) -> Option<()> {
+        /*!
+        Writes the report as CSV to any writer. See [`EvaluationReport::to_csv`].
+        ```
+        # use neurnet::*;
+        let report = EvaluationReport::new(vec![vec![0.9], vec![0.2]], vec![vec![1.0], vec![1.0]]);
+        let mut buf: Vec<u8> = vec![];
+        report.write_csv(&mut buf).unwrap();
+        let csv = String::from_utf8(buf).unwrap();
+        assert!(csv.starts_with("metric,value\npoints,2\naccuracy,0.5\n"));
+        assert!(csv.contains("confusion_1_0,1\n"));
+        ```
+        */
+        write_csv_records(writer, ',', &self.to_csv_records())
+    }
+    fn to_csv_records(&self) -> Vec<Vec<String>> {
+        let mut records = vec![vec!["metric".to_string(), "value".to_string()]];
+        for (name, value) in self.metrics() {
+            records.push(vec![name, value]);
+        }
+        for (actual, row) in self.confusion.iter().enumerate() {
+            for (predicted, count) in row.iter().enumerate() {
+                records.push(vec![
+                    format!("confusion_{}_{}", actual, predicted),
+                    count.to_string(),
+                ]);
+            }
+        }
+        records
+    }
+}
+
+impl fmt::Display for EvaluationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = self.metrics();
+        let name_width = metrics
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for (name, value) in metrics {
+            writeln!(f, "{:<width$}  {}", name, value, width = name_width)?;
+        }
+        writeln!(
+            f,
+            "confusion matrix (rows are actual classes, columns predicted):"
+        )?;
+        let cell_width = self
+            .confusion
+            .iter()
+            .flatten()
+            .map(|count| count.to_string().len())
+            .max()
+            .unwrap_or(1);
+        for row in self.confusion.iter() {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|count| format!("{:>width$}", count, width = cell_width))
+                .collect();
+            writeln!(f, "  {}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
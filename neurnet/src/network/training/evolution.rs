@@ -0,0 +1,288 @@
+use super::super::layers::LayerParameters;
+use super::Network;
+use rand::Rng;
+
+/// How [`Evolution`] picks the parents of every child.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Selection {
+    /// Draws this many networks at random and picks the fittest of them. Larger tournaments favour the fittest networks more strongly.
+    Tournament(usize),
+    /// Picks every network with a chance proportional to its fitness, measured from the least fit network of the generation, which is never picked unless all are equally fit. Networks with a NaN or infinite fitness are never picked either, unless none have a share.
+    Roulette,
+}
+
+/// The options used by [`Evolution`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvolutionOptions {
+    pub selection: Selection,
+    /// The chance a child is bred from two parents, taking each weight and bias from either of them at random, rather than copied from one.
+    pub crossover_rate: f64,
+    /// The chance each of a child's weights and biases is mutated.
+    pub mutation_rate: f64,
+    /// The standard deviation of the Gaussian noise a mutation adds.
+    pub mutation_strength: f64,
+    /// How many of the fittest networks are carried into the next generation unchanged.
+    pub elitism: usize,
+}
+impl Default for EvolutionOptions {
+    fn default() -> EvolutionOptions {
+        EvolutionOptions {
+            selection: Selection::Tournament(3),
+            crossover_rate: 0.7,
+            mutation_rate: 0.1,
+            mutation_strength: 0.2,
+            elitism: 1,
+        }
+    }
+}
+
+/// Trains a population of networks with the same shape by a genetic algorithm, without gradients or labelled outputs: every generation is scored by a fitness function, and the next one is bred from the fittest through selection, crossover of weights and biases, Gaussian mutation and elitism.
+///
+/// Since nothing is differentiated, it works with activations that gradient descent can't use, like a step function, whose finite difference derivative is zero almost everywhere.
+pub struct Evolution {
+    population: Vec<Network>,
+    fitness: Vec<f64>,
+    options: EvolutionOptions,
+    generation: usize,
+}
+
+type Genome = Vec<LayerParameters>;
+
+fn genome(network: &Network) -> Genome {
+    network
+        .get_layers()
+        .iter()
+        .map(|layer| layer.parameters())
+        .collect()
+}
+fn set_genome(network: &mut Network, genome: &Genome) {
+    for (layer, parameters) in network.get_layers_mut().iter_mut().zip(genome.iter()) {
+        layer.set_parameters(parameters);
+    }
+}
+pub(crate) fn gaussian(rng: &mut impl Rng) -> f64 {
+    //Box-Muller transform, with the first uniform kept above zero for the logarithm
+    let (a, b): (f64, f64) = (1.0 - rng.gen::<f64>(), rng.gen());
+    (-2.0 * a.ln()).sqrt() * (2.0 * std::f64::consts::PI * b).cos()
+}
+
+impl Evolution {
+    pub fn new(
+        template: &Network,
+        size: usize,
+        weights_range: (f64, f64),
+        biases_range: (f64, f64),
+        options: EvolutionOptions,
+    ) -> Evolution {
+        /*!
+        Creates a population of the given size, made of copies of the template with randomized weights and biases. Everything else, like the activation and dropout, is kept from the template.
+        ```
+        # use neurnet::*;
+        // A step activation, which has no useful derivative
+        let step = |x: f64| if x > 0.0 { 1.0 } else { 0.0 };
+        let template = Network::new(vec![2, 2, 1], step, (0.0, 0.0), (0.0, 0.0));
+        let xor = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)];
+        let fitness = |nn: &Network| -> f64 {
+            -xor.iter().map(|(inputs, output)| (nn.pulse(inputs.to_vec())[0] - output).abs()).sum::<f64>()
+        };
+
+        let options = EvolutionOptions { mutation_strength: 0.5, ..Default::default() };
+        let mut evolution = Evolution::new(&template, 100, (-1.0, 1.0), (-1.0, 1.0), options);
+        let mut best = evolution.best().1;
+        for _ in 0..1000 {
+            if best == 0.0 {
+                break;
+            }
+            best = evolution.step(fitness);
+        }
+        assert_eq!(best, 0.0);
+        let (nn, _) = evolution.best();
+        assert_eq!(nn.pulse(vec![1.0, 0.0]), vec![1.0]);
+        assert_eq!(nn.pulse(vec![1.0, 1.0]), vec![0.0]);
+        ```
+        # Panics
+        <ul>
+        <li> If the size is zero.
+        </ul>
+        */
+        assert!(size > 0, "The population needs at least one network");
+        let population = (0..size)
+            .map(|_| {
+                let mut network = template.clone();
+                network.randomize(weights_range, biases_range);
+                network
+            })
+            .collect();
+        Evolution {
+            population,
+            fitness: vec![],
+            options,
+            generation: 0,
+        }
+    }
+    pub fn from_population(
+        population: Vec<Network>,
+        options: EvolutionOptions,
+    ) -> Option<Evolution> {
+        //! Starts from the networks given, such as ones that were already trained. Returns None if there are none, or they don't all have the same layers and amounts of parameters.
+        let shape = |network: &Network| -> Vec<(usize, usize)> {
+            genome(network)
+                .iter()
+                .map(|parameters| (parameters.weights.len(), parameters.biases.len()))
+                .collect()
+        };
+        let first = shape(population.first()?);
+        if population.iter().any(|network| shape(network) != first) {
+            return None;
+        }
+        Some(Evolution {
+            population,
+            fitness: vec![],
+            options,
+            generation: 0,
+        })
+    }
+    pub fn population(&self) -> &[Network] {
+        &self.population
+    }
+    pub fn fitness(&self) -> &[f64] {
+        //! The fitness of every network in the population, in the same order, or nothing if the population hasn't been scored yet.
+        &self.fitness
+    }
+    pub fn generation(&self) -> usize {
+        //! How many generations have been bred.
+        self.generation
+    }
+    pub fn get_options(&self) -> &EvolutionOptions {
+        &self.options
+    }
+    pub fn set_options(&mut self, options: EvolutionOptions) {
+        self.options = options;
+    }
+    pub fn best(&self) -> (&Network, f64) {
+        //! The fittest network of the current population and its fitness, or the first network and negative infinity if the population hasn't been scored yet.
+        match self.ranking().first() {
+            Some(index) => (&self.population[*index], self.fitness[*index]),
+            None => (&self.population[0], f64::NEG_INFINITY),
+        }
+    }
+    pub fn evaluate<F: FnMut(&Network) -> f64>(&mut self, mut fitness: F) {
+        //! Scores every network of the current population with the fitness function, where higher is fitter. A NaN fitness counts as the least fit possible.
+        self.fitness = self
+            .population
+            .iter()
+            .map(|network| {
+                let fitness = fitness(network);
+                if fitness.is_nan() {
+                    f64::NEG_INFINITY
+                } else {
+                    fitness
+                }
+            })
+            .collect();
+    }
+    pub fn step<F: FnMut(&Network) -> f64>(&mut self, mut fitness: F) -> f64 {
+        //! Breeds the next generation from the current one, scoring the current one first if it hasn't been, and then scores the new generation. Returns the new generation's best fitness.
+        if self.fitness.len() != self.population.len() {
+            self.evaluate(&mut fitness);
+        }
+        self.population = self.breed();
+        self.generation += 1;
+        self.evaluate(&mut fitness);
+        self.best().1
+    }
+    pub fn evolve<F: FnMut(&Network) -> f64>(&mut self, mut fitness: F, generations: usize) -> f64 {
+        //! Breeds the given number of generations with [`Evolution::step`], and returns the last generation's best fitness.
+        let mut best = f64::NEG_INFINITY;
+        for _ in 0..generations {
+            best = self.step(&mut fitness);
+        }
+        best
+    }
+    fn ranking(&self) -> Vec<usize> {
+        //The indices of the scored networks, fittest first
+        let mut ranking: Vec<usize> = (0..self.fitness.len()).collect();
+        ranking.sort_by(|a, b| self.fitness[*b].total_cmp(&self.fitness[*a]));
+        ranking
+    }
+    fn select(&self, rng: &mut impl Rng) -> usize {
+        let size = self.population.len();
+        match self.options.selection {
+            Selection::Tournament(rounds) => (0..rounds.max(1))
+                .map(|_| rng.gen_range(0..size))
+                .max_by(|a, b| self.fitness[*a].total_cmp(&self.fitness[*b]))
+                .unwrap(),
+            Selection::Roulette => {
+                //Non-finite fitnesses get no share, so one of them doesn't turn the wheel uniform
+                let least = self
+                    .fitness
+                    .iter()
+                    .copied()
+                    .filter(|fitness| fitness.is_finite())
+                    .fold(f64::INFINITY, f64::min);
+                let shares: Vec<f64> = self
+                    .fitness
+                    .iter()
+                    .map(|f| match f.is_finite() {
+                        true => f - least,
+                        false => 0.0,
+                    })
+                    .collect();
+                let total: f64 = shares.iter().sum();
+                if !total.is_finite() || total <= 0.0 {
+                    return rng.gen_range(0..size);
+                }
+                let mut spin = rng.gen::<f64>() * total;
+                for (index, share) in shares.iter().enumerate() {
+                    spin -= share;
+                    if spin < 0.0 {
+                        return index;
+                    }
+                }
+                shares.iter().rposition(|share| *share > 0.0).unwrap()
+            }
+        }
+    }
+    fn breed(&self) -> Vec<Network> {
+        let mut rng = rand::thread_rng();
+        let options = &self.options;
+        let mut next: Vec<Network> = self
+            .ranking()
+            .iter()
+            .take(options.elitism.min(self.population.len()))
+            .map(|index| self.population[*index].clone())
+            .collect();
+        while next.len() < self.population.len() {
+            let mother = &self.population[self.select(&mut rng)];
+            let mut genes = genome(mother);
+            if rng.gen::<f64>() < options.crossover_rate {
+                let father = genome(&self.population[self.select(&mut rng)]);
+                for (genes, father) in genes.iter_mut().zip(father.iter()) {
+                    for (gene, other) in genes
+                        .weights
+                        .iter_mut()
+                        .zip(father.weights.iter())
+                        .chain(genes.biases.iter_mut().zip(father.biases.iter()))
+                    {
+                        if rng.gen::<bool>() {
+                            *gene = *other;
+                        }
+                    }
+                }
+            }
+            for genes in genes.iter_mut() {
+                for gene in genes.weights.iter_mut().chain(genes.biases.iter_mut()) {
+                    if rng.gen::<f64>() < options.mutation_rate {
+                        *gene += options.mutation_strength * gaussian(&mut rng);
+                    }
+                }
+            }
+            let mut child = mother.clone();
+            set_genome(&mut child, &genes);
+            next.push(child);
+        }
+        next
+    }
+}
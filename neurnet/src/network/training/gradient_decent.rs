@@ -1,37 +1,75 @@
+use super::data::DataSplit;
+use super::dropout::DropoutMasks;
 use crate::NetworkFood;
 
 use super::Network;
 impl Network {
+    #[allow(dead_code)]
     fn get_activation_der(&self, x: f64) -> f64 {
-        let h = 0.0001;
-        ((self.activation_fn)(x + h / 2.0) - (self.activation_fn)(x - h / 2.0)) / h
+        self.activation.derivative(x)
     }
     pub fn test_point(&self, point: &(Vec<f64>, Vec<f64>)) -> Vec<f64> {
         //! Takes a data point (input values, output values) and returns the costs of each output neuron for that given point.
-        let point = point.clone(); /* Potential Bottleneck */
-        let outputs = self.pulse(point.0);
+        self.point_cost(&point.0, &point.1)
+    }
+    pub fn point_cost(&self, inputs: &[f64], expected_outputs: &[f64]) -> Vec<f64> {
+        //! Runs the inputs through the network and returns the costs of each output neuron compared to the expected outputs.
+        self.masked_point_cost(inputs, expected_outputs, None)
+    }
+    fn masked_point_cost(
+        &self,
+        inputs: &[f64],
+        expected_outputs: &[f64],
+        masks: Option<&DropoutMasks>,
+    ) -> Vec<f64> {
+        let outputs = self.pulse_masked(inputs.to_vec(), masks); /* Potential Bottleneck */
         let mut cost_vec: Vec<f64> = vec![];
-        for i in 0..outputs.len() {
+        for (i, output) in outputs.iter().enumerate() {
             cost_vec.push({
-                let c = outputs[i] - point.1.get(i).expect("test point has missized output vec");
+                let c = output
+                    - expected_outputs
+                        .get(i)
+                        .expect("test point has missized output vec");
                 c * c
             });
         }
         cost_vec
     }
+    fn penalized_point_cost(
+        &self,
+        inputs: &[f64],
+        expected_outputs: &[f64],
+        masks: Option<&DropoutMasks>,
+    ) -> Vec<f64> {
+        let penalty = self.regularization_cost();
+        let mut costs = self.masked_point_cost(inputs, expected_outputs, masks);
+        for cost in costs.iter_mut() {
+            *cost += penalty;
+        }
+        costs
+    }
     pub fn test(&self, food: &impl NetworkFood) -> Vec<f64> {
-        //! Takes a dataset, runs through the entire set of testing data, and returns the average cost for each neuron.
-        let testing_data = food.grab_testing_data();
+        //! Takes a dataset, runs through the entire set of testing data, and returns the average cost for each neuron. Any [`Regularization`](super::regularization::Regularization) penalty is added to every neuron's cost.
+        self.split_cost(food, DataSplit::Testing)
+    }
+    pub fn validate(&self, food: &impl NetworkFood) -> Vec<f64> {
+        //! Takes a dataset, runs through the entire set of validation data, and returns the average cost for each neuron. Like [`Network::test`], the costs include any regularization penalty.
+        self.split_cost(food, DataSplit::Validation)
+    }
+    fn split_cost(&self, food: &impl NetworkFood, split: DataSplit) -> Vec<f64> {
         let mut cost_totals: Vec<f64> = vec![0.0; *self.shape.last().unwrap()];
-        for data_pnt_i in 0..testing_data.len() {
-            let mut i = 0;
-            for neuron_value in self.test_point(&testing_data[data_pnt_i]) {
+        for data_pnt in food.points(split) {
+            for (i, neuron_value) in self
+                .point_cost(&data_pnt.0, &data_pnt.1)
+                .into_iter()
+                .enumerate()
+            {
                 cost_totals[i] += neuron_value;
-                i += 1;
             }
         }
+        let penalty = self.regularization_cost();
         for cost in cost_totals.iter_mut() {
-            *cost /= testing_data.len() as f64;
+            *cost = *cost / food.len(split) as f64 + penalty;
         }
         cost_totals
     }
@@ -51,7 +89,7 @@ impl Network {
         //! <li> Iterations is the amount of times to run through the dataset to train the network.
         //! <li> Iterations per cost print is the amount of iterations for each print to the console. If it is None, then nothing will be printed. If it is Some(10), then the cost vector will be printed every 10 generations.
         //! </ul>
-        if let Some(_) = iteration_per_cost_print {
+        if iteration_per_cost_print.is_some() {
             println!("Initial Cost Vector: {:?}", self.test(food));
         }
         for i in 0..iterations {
@@ -59,59 +97,48 @@ impl Network {
             if let Some(gens) = iteration_per_cost_print {
                 if i % gens == 0 {
                     let test_results = self.test(food);
-                    if test_results[0] == f64::NAN {
-                        self.randomize((-2.0, 2.0), (-5.0, 5.0))
-                    }
                     println!("Generation {i}: {:?}", test_results);
                 }
             }
         }
     }
     fn train(&mut self, food: &impl NetworkFood, rate: f64, used_data_fraction: f64) {
-        let training_data = food.grab_training_data();
-        for data_pnt in training_data.iter() {
+        for data_pnt in food.points(DataSplit::Training) {
+            let (inputs, expected_outputs) = (&data_pnt.0, &data_pnt.1);
             let random: f64 = rand::random();
             if random < used_data_fraction {
-                let mut initial_cost = self.test_point(&data_pnt);
-                if initial_cost[0] == f64::NAN {
-                    self.randomize((-2.0, 2.0), (-5.0, 5.0));
-                } //You only have to check the first output b/c if one output is nan then all will be.
+                //The same dropout is used for every nudge made for this point
+                let masks = match self.has_dropout() {
+                    true => Some(self.sample_dropout_masks(&mut rand::thread_rng())),
+                    false => None,
+                };
+                let mut initial_cost =
+                    self.penalized_point_cost(inputs, expected_outputs, masks.as_ref());
                 for output_i in 0..(*self.shape.last().unwrap()) {
-                    for layer in 0..(self.shape.len() - 1) {
-                        //-1 as to ignore the input layer
-                        for neuron_in_layer in 0..self.shape[layer + 1] {
-                            //Offset by one to make up for the fact that the shape includes the input layer
-                            for neuron_in_prev_layer in 0..self.shape[layer] {
-                                let current_weight = self
-                                    .get_weight(layer, neuron_in_layer, neuron_in_prev_layer)
-                                    .unwrap()
-                                    .clone();
-                                self.set_weight(
-                                    layer,
-                                    neuron_in_layer,
-                                    neuron_in_prev_layer,
-                                    current_weight + rate,
-                                );
-                                let new_cost = self.test_point(data_pnt); //Can potentially be eliminated by backwards propegation
+                    for layer in 0..self.layers.len() {
+                        let parameters = self.layers[layer].parameters();
+                        for (bias, values) in
+                            [(false, parameters.weights), (true, parameters.biases)]
+                        {
+                            for (i, current_value) in values.into_iter().enumerate() {
+                                self.set_parameter(layer, bias, i, current_value + rate);
+                                let new_cost = self.penalized_point_cost(
+                                    inputs,
+                                    expected_outputs,
+                                    masks.as_ref(),
+                                ); //Can potentially be eliminated by backwards propegation
                                 let der = new_cost[output_i] - initial_cost[output_i]; //Positive means increasing weight is bad
-                                self.set_weight(
-                                    layer,
-                                    neuron_in_layer,
-                                    neuron_in_prev_layer,
-                                    current_weight - der * rate,
+                                self.set_parameter(layer, bias, i, current_value - der * rate);
+                                initial_cost = self.penalized_point_cost(
+                                    inputs,
+                                    expected_outputs,
+                                    masks.as_ref(),
                                 );
-                                initial_cost = self.test_point(data_pnt);
                             }
-                            let current_bias =
-                                self.get_bias(layer, neuron_in_layer).unwrap().clone();
-                            self.set_bias(layer, neuron_in_layer, current_bias + rate);
-                            let new_cost = self.test_point(data_pnt);
-                            let der = new_cost[output_i] - initial_cost[output_i]; //Positive means increasing weight is bad
-                            self.set_bias(layer, neuron_in_layer, current_bias - der * rate);
-                            initial_cost = self.test_point(data_pnt);
                         }
                     }
                 }
+                self.apply_max_norm();
             }
         }
     }
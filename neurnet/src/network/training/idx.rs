@@ -0,0 +1,135 @@
+use super::super::super::files::{read_idx, read_idx_file, IDX_UBYTE};
+use super::data::{DataSet, DataSplit};
+use std::io::Read;
+
+/// The options used when loading IDX (MNIST-style) files into a [`DataSet`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IdxOptions {
+    /// Which half of the dataset the loaded points go into.
+    pub split: DataSplit,
+    /// Scales unsigned byte pixels from 0..=255 down to 0.0..=1.0. Other element types are left as they are.
+    pub scale_pixels: bool,
+    /// If Some(n), each label becomes a one-hot vector of n outputs. If None, the label is a single output holding its number.
+    pub one_hot_classes: Option<usize>,
+}
+impl Default for IdxOptions {
+    fn default() -> IdxOptions {
+        IdxOptions {
+            split: DataSplit::Training,
+            scale_pixels: true,
+            one_hot_classes: Some(10),
+        }
+    }
+}
+
+impl DataSet {
+    pub fn from_idx(images_path: &str, labels_path: &str, options: &IdxOptions) -> Option<DataSet> {
+        /*!
+        Loads an IDX image file and its matching label file (such as MNIST's `train-images-idx3-ubyte` and `train-labels-idx1-ubyte`) into a new dataset. Every image is flattened into one input vector. Returns None if either file can't be read or the two don't have the same number of items.
+        ```no_run
+        # use neurnet::*;
+        let mut mnist = DataSet::from_idx(
+            "train-images-idx3-ubyte",
+            "train-labels-idx1-ubyte",
+            &IdxOptions::default(),
+        )
+        .unwrap();
+        mnist
+            .push_idx(
+                "t10k-images-idx3-ubyte",
+                "t10k-labels-idx1-ubyte",
+                &IdxOptions { split: DataSplit::Testing, ..IdxOptions::default() },
+            )
+            .unwrap();
+        ```
+        */
+        let mut ds = DataSet::empty();
+        ds.push_idx(images_path, labels_path, options)?;
+        Some(ds)
+    }
+    pub fn push_idx(
+        &mut self,
+        images_path: &str,
+        labels_path: &str,
+        options: &IdxOptions,
+    ) -> Option<()> {
+        //! Loads an IDX image and label file pair into this dataset, adding the points to the half picked by the options. See [`DataSet::from_idx`].
+        self.push_idx_data(
+            read_idx_file(images_path)?,
+            read_idx_file(labels_path)?,
+            options,
+        )
+    }
+    pub fn push_idx_from<R1: Read, R2: Read>(
+        &mut self,
+        images: R1,
+        labels: R2,
+        options: &IdxOptions,
+    ) -> Option<()> {
+        /*!
+        Reads IDX image and label data from any readers into this dataset. See [`DataSet::from_idx`].
+        ```
+        # use neurnet::*;
+        // Two 2x2 unsigned byte images, labelled 1 and 0
+        let images: Vec<u8> = vec![0, 0, 8, 3, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 2, 0, 255, 51, 0, 255, 255, 0, 0];
+        let labels: Vec<u8> = vec![0, 0, 8, 1, 0, 0, 0, 2, 1, 0];
+        let mut ds = DataSet::empty();
+        ds.push_idx_from(
+            images.as_slice(),
+            labels.as_slice(),
+            &IdxOptions { split: DataSplit::Testing, one_hot_classes: Some(2), ..IdxOptions::default() },
+        )
+        .unwrap();
+        assert!(ds.grab_training_data().is_empty());
+        assert_eq!(ds.grab_testing_data()[0], (vec![0.0, 1.0, 0.2, 0.0], vec![0.0, 1.0]));
+        assert_eq!(ds.grab_testing_data()[1], (vec![1.0, 1.0, 0.0, 0.0], vec![1.0, 0.0]));
+        ```
+        */
+        self.push_idx_data(read_idx(images)?, read_idx(labels)?, options)
+    }
+    fn push_idx_data(
+        &mut self,
+        images: (u8, Vec<usize>, Vec<f64>),
+        labels: (u8, Vec<usize>, Vec<f64>),
+        options: &IdxOptions,
+    ) -> Option<()> {
+        let (image_type, image_dims, pixels) = images;
+        let (_, label_dims, label_values) = labels;
+        let count = *image_dims.first()?;
+        if label_dims.first() != Some(&count) || label_values.len() != count {
+            return None;
+        }
+        let image_size: usize = image_dims[1..].iter().product();
+        let scale = if options.scale_pixels && image_type == IDX_UBYTE {
+            1.0 / 255.0
+        } else {
+            1.0
+        };
+
+        let output_size = options.one_hot_classes.unwrap_or(1);
+        if count > 0 && !self.accepts(image_size, output_size) {
+            return None;
+        }
+        let mut points: Vec<(Vec<f64>, Vec<f64>)> = vec![];
+        for (image, label) in pixels.chunks_exact(image_size.max(1)).zip(label_values) {
+            let inputs: Vec<f64> = image.iter().map(|pixel| pixel * scale).collect();
+            let outputs = match options.one_hot_classes {
+                Some(classes) => {
+                    if label < 0.0 || label as usize >= classes {
+                        return None;
+                    }
+                    let mut buf = vec![0.0; classes];
+                    buf[label as usize] = 1.0;
+                    buf
+                }
+                None => vec![label],
+            };
+            points.push((inputs, outputs));
+        }
+        for point in points {
+            self.push_point(options.split, &point.0, &point.1)?;
+        }
+        Some(())
+    }
+}
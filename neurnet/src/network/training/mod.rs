@@ -1,3 +1,14 @@
-pub use super::{Layer, Network};
+pub use super::{layers::Layer, Network};
+pub mod backprop;
+pub mod csv;
 pub mod data;
+pub mod dropout;
+pub mod dset_file;
+pub mod evaluation;
+pub mod evolution;
 mod gradient_decent;
+pub mod idx;
+mod npy;
+pub mod regularization;
+pub mod sequence;
+pub mod split;
@@ -0,0 +1,67 @@
+use super::super::super::files::open_file;
+use super::super::super::numpy::read_npy;
+use super::data::{DataSet, DataSplit};
+use std::io::{BufReader, Read};
+
+impl DataSet {
+    pub fn from_npy(inputs_path: &str, outputs_path: &str) -> Option<DataSet> {
+        /*!
+        Loads a dataset from two `.npy` files holding 2D arrays, one of inputs and one of expected outputs, with one row per data point (like the `X` and `y` arrays used with NumPy). All the points become training data; use [`DataSet::push_npy`] to add testing data. Returns None if either file can't be read or the row counts don't match. A 1D array is treated as a single column.
+        ```no_run
+        # use neurnet::*;
+        let mut ds = DataSet::from_npy("x_train.npy", "y_train.npy").unwrap();
+        ds.push_npy("x_test.npy", "y_test.npy", DataSplit::Testing).unwrap();
+        ```
+        */
+        let mut ds = DataSet::empty();
+        ds.push_npy(inputs_path, outputs_path, DataSplit::Training)?;
+        Some(ds)
+    }
+    pub fn push_npy(
+        &mut self,
+        inputs_path: &str,
+        outputs_path: &str,
+        split: DataSplit,
+    ) -> Option<()> {
+        //! Loads a pair of `.npy` files into the given half of this dataset. See [`DataSet::from_npy`].
+        self.push_npy_from(
+            BufReader::new(open_file(inputs_path)?),
+            BufReader::new(open_file(outputs_path)?),
+            split,
+        )
+    }
+    pub fn push_npy_from<R1: Read, R2: Read>(
+        &mut self,
+        inputs: R1,
+        outputs: R2,
+        split: DataSplit,
+    ) -> Option<()> {
+        //! Reads a pair of `.npy` arrays from any readers into the given half of this dataset. See [`DataSet::from_npy`].
+        fn rows(array: (Vec<usize>, Vec<f64>)) -> Option<Vec<Vec<f64>>> {
+            let (shape, values) = array;
+            let width = match shape.len() {
+                1 => 1,
+                2 => shape[1],
+                _ => return None,
+            };
+            if width == 0 {
+                return Some(vec![vec![]; shape[0]]);
+            }
+            Some(values.chunks(width).map(|row| row.to_vec()).collect())
+        }
+        let inputs = rows(read_npy(inputs)?)?;
+        let outputs = rows(read_npy(outputs)?)?;
+        if inputs.len() != outputs.len() {
+            return None;
+        }
+        if let (Some(input), Some(output)) = (inputs.first(), outputs.first()) {
+            if !self.accepts(input.len(), output.len()) {
+                return None;
+            }
+        }
+        for point in inputs.into_iter().zip(outputs) {
+            self.push_point(split, &point.0, &point.1)?;
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,207 @@
+use super::super::layers::LayerParameters;
+use super::super::loss::Loss;
+use super::backprop::LayerGradient;
+use super::data::{DataSplit, NetworkFood};
+use super::dropout::DropoutMasks;
+use super::Network;
+
+/// Penalties on a layer's parameters that discourage overfitting, set with [`Network::set_regularization`].
+///
+/// The L1 penalty adds `l1 * |x|` and the L2 penalty adds `l2 * x²` to the cost for every weight (or bias) x of the layer. Both are part of the cost reported by [`Network::test`] and of the gradient used in training.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Regularization {
+    pub l1_weights: f64,
+    pub l2_weights: f64,
+    pub l1_biases: f64,
+    pub l2_biases: f64,
+    /// The largest length the incoming weights of each neuron may have. Longer ones are scaled down after every training update.
+    pub max_norm: Option<f64>,
+}
+impl Regularization {
+    pub fn l2(l2_weights: f64) -> Regularization {
+        //! Weight decay: an L2 penalty on the weights only.
+        Regularization {
+            l2_weights,
+            ..Regularization::default()
+        }
+    }
+    pub fn l1(l1_weights: f64) -> Regularization {
+        //! An L1 penalty on the weights only, which pushes unneeded weights to exactly zero.
+        Regularization {
+            l1_weights,
+            ..Regularization::default()
+        }
+    }
+    fn penalizes(&self) -> bool {
+        self.l1_weights != 0.0
+            || self.l2_weights != 0.0
+            || self.l1_biases != 0.0
+            || self.l2_biases != 0.0
+    }
+    fn cost(&self, parameters: &LayerParameters) -> f64 {
+        penalty(&parameters.weights, self.l1_weights, self.l2_weights)
+            + penalty(&parameters.biases, self.l1_biases, self.l2_biases)
+    }
+    fn add_gradient(&self, parameters: &LayerParameters, gradient: &mut LayerGradient) {
+        //The penalties reach every weight, so a sparse gradient has to be spread out first
+        *gradient = gradient.to_dense(parameters.weights.len());
+        for (grad, weight) in gradient.weights.iter_mut().zip(parameters.weights.iter()) {
+            *grad += penalty_derivative(*weight, self.l1_weights, self.l2_weights);
+        }
+        for (grad, bias) in gradient.biases.iter_mut().zip(parameters.biases.iter()) {
+            *grad += penalty_derivative(*bias, self.l1_biases, self.l2_biases);
+        }
+    }
+}
+
+fn penalty(values: &[f64], l1: f64, l2: f64) -> f64 {
+    values.iter().map(|x| l1 * x.abs() + l2 * x * x).sum()
+}
+fn penalty_derivative(x: f64, l1: f64, l2: f64) -> f64 {
+    l1 * x.signum() * (x != 0.0) as u8 as f64 + 2.0 * l2 * x
+}
+
+impl Network {
+    pub fn set_regularization(&mut self, layer: usize, regularization: Regularization) {
+        /*!
+        Sets the regularization of one layer, where layer 0 is the first layer after the inputs.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::with_activation(vec![1, 1], Activation::Identity, (0.0, 0.0), (0.0, 0.0));
+        nn.set_weight(0, 0, 0, 2.0);
+        nn.set_regularization(0, Regularization { l1_weights: 0.5, l2_weights: 0.25, ..Regularization::default() });
+        // 0.5 * |2| + 0.25 * 2²
+        assert_eq!(nn.regularization_cost(), 2.0);
+
+        let ds = DataSet::new(vec![], vec![(vec![1.0], vec![2.0])]);
+        assert_eq!(nn.test(&ds), vec![2.0]);
+
+        // The penalties are part of the gradient
+        let mut nn = Network::with_activation(vec![2, 3, 1], Activation::Tanh, (-1.0, 1.0), (-1.0, 1.0));
+        nn.set_regularization(0, Regularization { l1_weights: 0.1, l2_biases: 0.3, ..Regularization::default() });
+        nn.set_regularization(1, Regularization { l2_weights: 0.2, l1_biases: 0.05, ..Regularization::default() });
+        let errors = nn.gradient_check((&[0.5, -0.5], &[0.25]), &Loss::MeanSquaredError, 1e-6);
+        assert!(errors.iter().all(|error| *error < 1e-4));
+
+        // Max-norm constraints rescale each neuron's incoming weights after an update
+        nn.set_regularization(0, Regularization { max_norm: Some(0.5), ..Regularization::default() });
+        let gradient = nn.gradient(&[0.5, -0.5], &[0.25], &Loss::MeanSquaredError);
+        nn.apply_gradient(&gradient, 0.1);
+        for neuron in 0..3 {
+            let norm = (0..2).map(|i| nn.get_weight(0, neuron, i).unwrap().powi(2)).sum::<f64>().sqrt();
+            assert!(norm <= 0.5 + 1e-12);
+        }
+        ```
+        # Panics
+        <ul>
+        <li> If the layer doesn't exist.
+        </ul>
+        */
+        *self
+            .regularization
+            .get_mut(layer)
+            .expect("A viable layer ID") = regularization;
+    }
+    pub fn set_regularization_all(&mut self, regularization: Regularization) {
+        //! Sets the same regularization on every layer.
+        for layer_regularization in self.regularization.iter_mut() {
+            *layer_regularization = regularization;
+        }
+    }
+    pub fn get_regularization(&self, layer: usize) -> Option<&Regularization> {
+        self.regularization.get(layer)
+    }
+    pub fn regularization_cost(&self) -> f64 {
+        //! The total penalty of every layer's regularization for the network's current weights and biases.
+        self.layers
+            .iter()
+            .zip(self.regularization.iter())
+            .filter(|(_, regularization)| regularization.penalizes())
+            .map(|(layer, regularization)| regularization.cost(&layer.parameters()))
+            .sum()
+    }
+    pub(crate) fn add_regularization_gradient(&self, gradients: &mut [LayerGradient]) {
+        for ((layer, regularization), gradient) in self
+            .layers
+            .iter()
+            .zip(self.regularization.iter())
+            .zip(gradients.iter_mut())
+        {
+            if regularization.penalizes() {
+                regularization.add_gradient(&layer.parameters(), gradient);
+            }
+        }
+    }
+    pub(crate) fn apply_max_norm(&mut self) {
+        for (layer, regularization) in self.layers.iter_mut().zip(self.regularization.iter()) {
+            if let Some(max_norm) = regularization.max_norm {
+                layer.apply_max_norm(max_norm);
+            }
+        }
+    }
+    pub fn apply_gradient(&mut self, gradients: &[LayerGradient], rate: f64) {
+        //! Takes a gradient descent step, moving every weight and bias against its gradient scaled by the rate. Max-norm constraints are applied afterwards.
+        for (layer, gradient) in self.layers.iter_mut().zip(gradients.iter()) {
+            layer.apply_gradient(gradient, rate);
+        }
+        self.apply_max_norm();
+    }
+    pub fn train_with_loss(
+        &mut self,
+        food: &impl NetworkFood,
+        loss: &Loss,
+        rate: f64,
+        batch_size: usize,
+        epochs: usize,
+    ) {
+        /*!
+        Trains the network with mini-batch gradient descent on exact gradients. Each epoch runs through the training data in order, taking one step per batch along the [`Network::batch_gradient`], regularization included. Each point gets its own random [`Dropout`](super::dropout::Dropout), and layers that keep statistics across batches, like [`BatchNorm`](super::super::layers::BatchNorm), update them once per batch.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::gen_from_fn(
+            |x| vec![0.5 * x[0] + 0.25],
+            (-20..20).map(|x| vec![x as f64 / 20.0]).collect(),
+            (-5..5).map(|x| vec![x as f64 / 5.0]).collect(),
+        );
+        let mut nn = Network::with_activation(vec![1, 1], Activation::Identity, (-1.0, 1.0), (-1.0, 1.0));
+        nn.train_with_loss(&ds, &Loss::MeanSquaredError, 0.5, 8, 200);
+        assert!(nn.test(&ds)[0] < 1e-6);
+
+        // Weight decay pulls the weight towards zero, at the cost of a worse fit
+        nn.set_regularization_all(Regularization::l2(1.0));
+        nn.train_with_loss(&ds, &Loss::MeanSquaredError, 0.1, 8, 200);
+        assert!(nn.get_weight(0, 0, 0).unwrap().abs() < 0.5);
+        ```
+        */
+        let batch_size = batch_size.max(1);
+        let len = food.len(DataSplit::Training);
+        let mut rng = rand::thread_rng();
+        for _ in 0..epochs {
+            for start in (0..len).step_by(batch_size) {
+                let batch = food.batch(DataSplit::Training, start..(start + batch_size).min(len));
+                if batch.is_empty() {
+                    continue;
+                }
+                let points: Vec<(&[f64], &[f64])> = batch
+                    .iter()
+                    .map(|(inputs, expected_outputs)| (inputs.as_ref(), expected_outputs.as_ref()))
+                    .collect();
+                let masks: Option<Vec<DropoutMasks>> = match self.has_dropout() {
+                    true => Some(
+                        points
+                            .iter()
+                            .map(|_| self.sample_dropout_masks(&mut rng))
+                            .collect(),
+                    ),
+                    false => None,
+                };
+                let (mut gradients, layer_inputs) =
+                    self.batch_loss_gradient(&points, loss, masks.as_deref(), true);
+                self.add_regularization_gradient(&mut gradients);
+                self.update_statistics(&layer_inputs);
+                self.apply_gradient(&gradients, rate);
+            }
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use super::super::loss::Loss;
+use super::backprop::LayerGradient;
+use super::data::DataSplit;
+use super::dropout::DropoutMasks;
+use super::Network;
+use std::borrow::Cow;
+
+/// What a network carries from one step of a sequence to the next: the state of every layer, which is empty for layers that aren't recurrent. Start from [`Network::initial_state`] and advance it with [`Network::pulse_step`].
+pub type NetworkState = Vec<Vec<f64>>;
+
+/// A single sequence: (the inputs of every step, the expected outputs). There is either one expected output per step, or a single one that only the last step is compared against.
+pub type SequencePoint<'a> = (Cow<'a, [Vec<f64>]>, Cow<'a, [Vec<f64>]>);
+
+/// An owned sequence: (the inputs of every step, the expected outputs), as [`SequenceDataSet`] stores them.
+pub type Sequence = (Vec<Vec<f64>>, Vec<Vec<f64>>);
+
+/// The sequence counterpart of [`NetworkFood`](super::data::NetworkFood): the trait used for datasets of sequences that [`Network::train_sequences`] can learn from.
+pub trait SequenceFood {
+    /// The number of sequences in the given half of the dataset
+    fn len(&self, split: DataSplit) -> usize;
+    /// Gets the sequence at the index in the given half of the dataset, or None if the index is out of range.
+    fn get(&self, split: DataSplit, i: usize) -> Option<SequencePoint<'_>>;
+    /// Whether the given half of the dataset has no sequences
+    fn is_empty(&self, split: DataSplit) -> bool {
+        self.len(split) == 0
+    }
+}
+
+/// The recommended implementation of the SequenceFood trait. The sequences are kept in memory, and can all have different lengths.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SequenceDataSet {
+    training_data: Vec<Sequence>,
+    testing_data: Vec<Sequence>,
+    validation_data: Vec<Sequence>,
+}
+impl SequenceFood for SequenceDataSet {
+    fn len(&self, split: DataSplit) -> usize {
+        self.grab_data(split).len()
+    }
+    fn get(&self, split: DataSplit, i: usize) -> Option<SequencePoint<'_>> {
+        let (inputs, outputs) = self.grab_data(split).get(i)?;
+        Some((Cow::Borrowed(inputs), Cow::Borrowed(outputs)))
+    }
+}
+impl SequenceDataSet {
+    pub fn new(training_data: Vec<Sequence>, testing_data: Vec<Sequence>) -> SequenceDataSet {
+        /*!
+        Creates a dataset out of the training and testing sequences, each one (the inputs of every step, the expected outputs).
+        # Panics
+        Panics if a sequence is empty, or doesn't have either one expected output per step or a single one.
+        */
+        let mut ds = SequenceDataSet::empty();
+        for (inputs, outputs) in training_data {
+            ds.push_sequence(DataSplit::Training, inputs, outputs)
+                .expect("Invalid sequence");
+        }
+        for (inputs, outputs) in testing_data {
+            ds.push_sequence(DataSplit::Testing, inputs, outputs)
+                .expect("Invalid sequence");
+        }
+        ds
+    }
+    pub fn empty() -> SequenceDataSet {
+        SequenceDataSet::default()
+    }
+    pub fn push_sequence(
+        &mut self,
+        split: DataSplit,
+        inputs: Vec<Vec<f64>>,
+        outputs: Vec<Vec<f64>>,
+    ) -> Option<()> {
+        /*!
+        Adds a sequence to the given half of the dataset. Returns None (adding nothing) if it has no steps, or doesn't have either one expected output per step or a single one for its last step.
+        ```
+        # use neurnet::*;
+        let mut ds = SequenceDataSet::empty();
+        let inputs = vec![vec![1.0], vec![2.0], vec![3.0]];
+        assert!(ds.push_sequence(DataSplit::Training, inputs.clone(), vec![vec![6.0]]).is_some());
+        assert!(ds.push_sequence(DataSplit::Training, inputs.clone(), vec![vec![1.0], vec![3.0], vec![6.0]]).is_some());
+        assert!(ds.push_sequence(DataSplit::Training, inputs, vec![vec![1.0], vec![3.0]]).is_none());
+        assert_eq!(ds.len(DataSplit::Training), 2);
+        ```
+        */
+        if inputs.is_empty() || (outputs.len() != 1 && outputs.len() != inputs.len()) {
+            return None;
+        }
+        let data = match split {
+            DataSplit::Training => &mut self.training_data,
+            DataSplit::Testing => &mut self.testing_data,
+            DataSplit::Validation => &mut self.validation_data,
+        };
+        data.push((inputs, outputs));
+        Some(())
+    }
+    fn grab_data(&self, split: DataSplit) -> &Vec<Sequence> {
+        match split {
+            DataSplit::Training => &self.training_data,
+            DataSplit::Testing => &self.testing_data,
+            DataSplit::Validation => &self.validation_data,
+        }
+    }
+}
+
+fn step_targets(steps: usize, expected_outputs: &[Vec<f64>]) -> Vec<Option<&[f64]>> {
+    //The expected outputs of every step, if the step is compared against any
+    if expected_outputs.len() == steps {
+        return expected_outputs
+            .iter()
+            .map(|x| Some(x.as_slice()))
+            .collect();
+    }
+    assert_eq!(
+        expected_outputs.len(),
+        1,
+        "A sequence needs one expected output per step, or a single one for its last step"
+    );
+    let mut targets = vec![None; steps];
+    if let Some(target) = targets.last_mut() {
+        *target = Some(expected_outputs[0].as_slice());
+    }
+    targets
+}
+
+impl Network {
+    pub fn initial_state(&self) -> NetworkState {
+        //! The state every sequence starts from, with every recurrent layer's state all zeros.
+        self.layers
+            .iter()
+            .map(|layer| vec![0.0; layer.state_len()])
+            .collect()
+    }
+    pub fn pulse_step(&self, input: Vec<f64>, state: &mut NetworkState) -> Vec<f64> {
+        /*!
+        Runs one step of a sequence through the network like [`Network::pulse`], starting from the state the previous step left and replacing it with the new state. Recurrent layers, like [`Lstm`](super::super::layers::Lstm), use the state to remember earlier steps; every other layer runs each step on its own.
+        ```
+        # use neurnet::*;
+        let mut nn = Network::from_layers(vec![Box::new(Gru::new(3, 1)), Box::new(Dense::new(1, 3))], Activation::Identity).unwrap();
+        nn.randomize((-1.0, 1.0), (-1.0, 1.0));
+        let inputs = vec![vec![0.5], vec![-0.5], vec![1.0]];
+
+        let mut state = nn.initial_state();
+        let outputs: Vec<Vec<f64>> = inputs.iter().map(|input| nn.pulse_step(input.clone(), &mut state)).collect();
+        assert_eq!(outputs, nn.pulse_sequence(&inputs));
+
+        // The same input gives a different output once the network has seen earlier steps
+        let mut fresh = nn.initial_state();
+        assert_ne!(nn.pulse_step(vec![1.0], &mut fresh), outputs[2]);
+        ```
+        # Panics
+        <ul>
+        <li> If the input vector's length is not equal to the amount of input neurons.
+        <li> If the state isn't one for this network.
+        </ul>
+        */
+        let outputs = self.step_trace(&input, state, None).pop().unwrap();
+        match &self.output_scaler {
+            Some(scaler) => scaler.inverse_transform(&outputs),
+            None => outputs,
+        }
+    }
+    pub fn pulse_sequence(&self, inputs: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        //! Runs a whole sequence through the network with [`Network::pulse_step`], starting from the [`Network::initial_state`], and returns the outputs of every step.
+        //! # Panics
+        //! <ul>
+        //! <li> If an input vector's length is not equal to the amount of input neurons.
+        //! </ul>
+        let mut state = self.initial_state();
+        inputs
+            .iter()
+            .map(|input| self.pulse_step(input.clone(), &mut state))
+            .collect()
+    }
+    fn step_trace(
+        &self,
+        input: &[f64],
+        state: &mut NetworkState,
+        masks: Option<&DropoutMasks>,
+    ) -> Vec<Vec<f64>> {
+        //Runs one step, returning every layer's input plus the last layer's outputs
+        if input.len() != self.shape[0] {
+            panic!("Network was passed more inputs than there are neurons in the first layer of the network");
+        }
+        assert_eq!(
+            state.len(),
+            self.layers.len(),
+            "The state must be one made for this network"
+        );
+        let mut activations = vec![match &self.input_scaler {
+            Some(scaler) => scaler.transform(input),
+            None => input.to_vec(),
+        }];
+        for (layer_i, layer) in self.layers.iter().enumerate() {
+            assert_eq!(
+                state[layer_i].len(),
+                layer.state_len(),
+                "The state must be one made for this network"
+            );
+            let (mut outputs, new_state) = layer.forward_step(
+                activations.last().unwrap(),
+                &state[layer_i],
+                &self.activation,
+            );
+            self.apply_dropout(layer_i, &mut outputs, masks.map(|masks| &masks[layer_i]));
+            state[layer_i] = new_state;
+            activations.push(outputs);
+        }
+        activations
+    }
+    pub fn sequence_loss(&self, sequence: (&[Vec<f64>], &[Vec<f64>]), loss: &Loss) -> f64 {
+        //! Runs the sequence through the network and returns the average loss over the steps that have expected outputs, plus the [`Network::regularization_cost`].
+        //! # Panics
+        //! <ul>
+        //! <li> If the sequence doesn't have either one expected output per step or a single one.
+        //! </ul>
+        let (inputs, expected_outputs) = sequence;
+        let targets = step_targets(inputs.len(), expected_outputs);
+        let scored = targets.iter().flatten().count();
+        let total: f64 = self
+            .pulse_sequence(inputs)
+            .iter()
+            .zip(targets.iter())
+            .filter_map(|(outputs, target)| Some(loss.cost(outputs, (*target)?)))
+            .sum();
+        total / scored as f64 + self.regularization_cost()
+    }
+    pub fn sequence_gradient(
+        &self,
+        sequence: (&[Vec<f64>], &[Vec<f64>]),
+        loss: &Loss,
+    ) -> Vec<LayerGradient> {
+        //! Computes the exact gradient of [`Network::sequence_loss`] with respect to every parameter, using backpropagation through every step of the sequence.
+        let (inputs, expected_outputs) = sequence;
+        let targets = step_targets(inputs.len(), expected_outputs);
+        let scored = targets.iter().flatten().count();
+        let mut gradients = self.sequence_loss_gradient(
+            inputs,
+            &targets,
+            &mut self.initial_state(),
+            loss,
+            None,
+            scored,
+        );
+        self.add_regularization_gradient(&mut gradients);
+        gradients
+    }
+    pub fn sequence_gradient_check(
+        &self,
+        sequence: (&[Vec<f64>], &[Vec<f64>]),
+        loss: &Loss,
+        epsilon: f64,
+    ) -> Vec<f64> {
+        //! Like [`Network::gradient_check`], but compares [`Network::sequence_gradient`] against central differences of [`Network::sequence_loss`]. See [`Rnn::new`](super::super::layers::Rnn::new) for an example.
+        self.check_gradients(
+            &self.sequence_gradient(sequence, loss),
+            &|network| network.sequence_loss(sequence, loss),
+            epsilon,
+        )
+    }
+    fn sequence_loss_gradient(
+        &self,
+        inputs: &[Vec<f64>],
+        targets: &[Option<&[f64]>],
+        state: &mut NetworkState,
+        loss: &Loss,
+        masks: Option<&DropoutMasks>,
+        scored: usize,
+    ) -> Vec<LayerGradient> {
+        //Backpropagates through the steps, starting from the state and leaving the state after the last step in it. The loss is averaged over the given number of scored steps
+        let mut states: Vec<NetworkState> = vec![];
+        let mut traces: Vec<Vec<Vec<f64>>> = vec![];
+        for input in inputs {
+            states.push(state.clone());
+            traces.push(self.step_trace(input, state, masks));
+        }
+
+        let mut gradients: Vec<LayerGradient> = self
+            .layers
+            .iter()
+            .map(|layer| layer.zero_gradient())
+            .collect();
+        let mut state_grads: Vec<Vec<f64>> = self
+            .layers
+            .iter()
+            .map(|layer| vec![0.0; layer.state_len()])
+            .collect();
+        for ((trace, state), target) in traces.iter().zip(states.iter()).zip(targets).rev() {
+            let outputs = trace.last().unwrap();
+            let mut output_grad = match target {
+                Some(expected_outputs) => {
+                    self.output_gradient(outputs, expected_outputs, loss, scored)
+                }
+                None => vec![0.0; outputs.len()],
+            };
+            for (layer_i, layer) in self.layers.iter().enumerate().rev() {
+                let mask = masks.map(|masks| &masks[layer_i]);
+                for (neuron, grad) in output_grad.iter_mut().enumerate() {
+                    *grad *= self.dropout_multiplier(layer_i, neuron, mask);
+                }
+                let (input_grad, state_grad, gradient) = layer.backward_step(
+                    &trace[layer_i],
+                    &state[layer_i],
+                    &output_grad,
+                    &state_grads[layer_i],
+                    &self.activation,
+                );
+                gradients[layer_i].add(&gradient);
+                state_grads[layer_i] = state_grad;
+                output_grad = input_grad;
+            }
+        }
+        gradients
+    }
+    pub fn train_sequences(
+        &mut self,
+        food: &impl SequenceFood,
+        loss: &Loss,
+        rate: f64,
+        bptt_steps: usize,
+        epochs: usize,
+    ) {
+        /*!
+        Trains the network on sequences with truncated backpropagation through time. Each sequence is run in windows of `bptt_steps` steps: the state carries on from one window to the next, but gradients only flow back to the start of their window, and a gradient descent step is taken after every window that has expected outputs. Regularization is included, and each sequence gets its own random [`Dropout`](super::dropout::Dropout) that stays the same across its steps. Layers like [`BatchNorm`](super::super::layers::BatchNorm) use their running statistics.
+        ```
+        # use neurnet::*;
+        // Learn to output half the sum of a sequence
+        let sequences: Vec<Sequence> = (0..40)
+            .map(|i| {
+                let inputs: Vec<Vec<f64>> = (0..4).map(|step| vec![(((i * 7 + step * 3) % 11) as f64 - 5.0) / 10.0]).collect();
+                let sum: f64 = inputs.iter().map(|x| x[0]).sum();
+                (inputs, vec![vec![0.5 * sum]])
+            })
+            .collect();
+        let ds = SequenceDataSet::new(sequences[..30].to_vec(), sequences[30..].to_vec());
+        let mut nn = Network::from_layers(vec![Box::new(Rnn::new(4, 1)), Box::new(Dense::new(1, 4))], Activation::Identity).unwrap();
+        nn.randomize((-0.5, 0.5), (-0.1, 0.1));
+        let before = nn.test_sequences(&ds, &Loss::MeanSquaredError);
+        nn.train_sequences(&ds, &Loss::MeanSquaredError, 0.1, 4, 300);
+        assert!(nn.test_sequences(&ds, &Loss::MeanSquaredError) < before / 20.0);
+
+        // Windows as long as the sequence give the exact gradient
+        let (inputs, expected) = &sequences[0];
+        let mut stepped = nn.clone();
+        stepped.apply_gradient(&nn.sequence_gradient((inputs, expected), &Loss::MeanSquaredError), 0.1);
+        nn.train_sequences(&SequenceDataSet::new(vec![sequences[0].clone()], vec![]), &Loss::MeanSquaredError, 0.1, 4, 1);
+        assert_eq!(nn.pulse_sequence(inputs), stepped.pulse_sequence(inputs));
+        ```
+        */
+        let bptt_steps = bptt_steps.max(1);
+        let mut rng = rand::thread_rng();
+        for _ in 0..epochs {
+            for i in 0..food.len(DataSplit::Training) {
+                let Some((inputs, expected_outputs)) = food.get(DataSplit::Training, i) else {
+                    continue;
+                };
+                let targets = step_targets(inputs.len(), &expected_outputs);
+                let scored = targets.iter().flatten().count();
+                let masks = match self.has_dropout() {
+                    true => Some(self.sample_dropout_masks(&mut rng)),
+                    false => None,
+                };
+                let mut state = self.initial_state();
+                for start in (0..inputs.len()).step_by(bptt_steps) {
+                    let end = (start + bptt_steps).min(inputs.len());
+                    let mut gradients = self.sequence_loss_gradient(
+                        &inputs[start..end],
+                        &targets[start..end],
+                        &mut state,
+                        loss,
+                        masks.as_ref(),
+                        scored,
+                    );
+                    if targets[start..end].iter().all(|target| target.is_none()) {
+                        continue;
+                    }
+                    self.add_regularization_gradient(&mut gradients);
+                    self.apply_gradient(&gradients, rate);
+                }
+            }
+        }
+    }
+    pub fn test_sequences(&self, food: &impl SequenceFood, loss: &Loss) -> f64 {
+        //! Returns the average [`Network::sequence_loss`] over the testing sequences.
+        let len = food.len(DataSplit::Testing);
+        let total: f64 = (0..len)
+            .filter_map(|i| food.get(DataSplit::Testing, i))
+            .map(|(inputs, expected_outputs)| {
+                self.sequence_loss((&inputs, &expected_outputs), loss)
+            })
+            .sum();
+        total / len as f64
+    }
+}
@@ -0,0 +1,267 @@
+use super::data::{DataSet, DataSplit, NetworkFood};
+use super::Network;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// An iterator over the folds of a dataset, created by [`DataSet::k_fold`].
+pub struct KFolds<'a> {
+    ds: &'a DataSet,
+    k: usize,
+    next: usize,
+}
+impl Iterator for KFolds<'_> {
+    type Item = DataSet;
+    fn next(&mut self) -> Option<DataSet> {
+        if self.next >= self.k {
+            return None;
+        }
+        let len = self.ds.len(DataSplit::Training);
+        let fold = self.next * len / self.k..(self.next + 1) * len / self.k;
+        self.next += 1;
+
+        let mut ds = self.ds.empty_like();
+        for i in 0..len {
+            let split = if fold.contains(&i) {
+                DataSplit::Validation
+            } else {
+                DataSplit::Training
+            };
+            self.ds.copy_point(DataSplit::Training, i, &mut ds, split);
+        }
+        for i in 0..self.ds.len(DataSplit::Testing) {
+            self.ds
+                .copy_point(DataSplit::Testing, i, &mut ds, DataSplit::Testing);
+        }
+        Some(ds)
+    }
+}
+
+impl DataSet {
+    fn copy_point(&self, from: DataSplit, i: usize, into: &mut DataSet, to: DataSplit) {
+        if let Some((inputs, outputs)) = self.point(from, i) {
+            into.push_point(to, inputs, outputs)
+                .expect("Datasets built from this one share its dimensions");
+        }
+    }
+    fn all_points(&self) -> Vec<(DataSplit, usize)> {
+        [
+            DataSplit::Training,
+            DataSplit::Testing,
+            DataSplit::Validation,
+        ]
+        .into_iter()
+        .flat_map(|split| (0..self.len(split)).map(move |i| (split, i)))
+        .collect()
+    }
+    pub fn shuffle(&mut self, seed: u64) {
+        /*!
+        Shuffles the order of the points in each part of the dataset, without moving any between parts. The same seed always gives the same order.
+        ```
+        # use neurnet::*;
+        let mut ds = DataSet::gen_from_fn(|x| x, (0..10).map(|x| vec![x as f64]).collect(), vec![]);
+        ds.shuffle(3);
+        let mut inputs = ds.inputs(DataSplit::Training).to_vec();
+        assert_ne!(inputs, (0..10).map(|x| x as f64).collect::<Vec<f64>>());
+        inputs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(inputs, (0..10).map(|x| x as f64).collect::<Vec<f64>>());
+        ```
+        */
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut shuffled = self.empty_like();
+        for split in [
+            DataSplit::Training,
+            DataSplit::Testing,
+            DataSplit::Validation,
+        ] {
+            let mut order: Vec<usize> = (0..self.len(split)).collect();
+            order.shuffle(&mut rng);
+            for i in order {
+                self.copy_point(split, i, &mut shuffled, split);
+            }
+        }
+        *self = shuffled;
+    }
+    pub fn split(&self, training_ratio: f64, seed: u64) -> DataSet {
+        /*!
+        Pools every point in the dataset, shuffles them, and returns a new dataset with the training ratio of them as training points and the rest as testing points. The same seed always gives the same split.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::gen_from_fn(|x| x, (0..100).map(|x| vec![x as f64]).collect(), vec![]);
+        let split = ds.split(0.8, 1);
+        assert_eq!(split.len(DataSplit::Training), 80);
+        assert_eq!(split.len(DataSplit::Testing), 20);
+        assert_eq!(split, ds.split(0.8, 1));
+        ```
+        */
+        let mut points = self.all_points();
+        points.shuffle(&mut StdRng::seed_from_u64(seed));
+        let training_count = (points.len() as f64 * training_ratio).round() as usize;
+
+        let mut ds = self.empty_like();
+        for (n, (split, i)) in points.into_iter().enumerate() {
+            let to = if n < training_count {
+                DataSplit::Training
+            } else {
+                DataSplit::Testing
+            };
+            self.copy_point(split, i, &mut ds, to);
+        }
+        ds
+    }
+    pub fn stratified_split(&self, training_ratio: f64, seed: u64) -> DataSet {
+        /*!
+        Like [`DataSet::split`], but splits each class separately so the training and testing points keep the dataset's class balance. A point's class is the index of its largest expected output, as with one-hot encoded targets.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::gen_from_fn(
+            |x| if x[0] < 90.0 { vec![1.0, 0.0] } else { vec![0.0, 1.0] },
+            (0..100).map(|x| vec![x as f64]).collect(),
+            vec![],
+        );
+        let split = ds.stratified_split(0.5, 4);
+        let class_1_testing = split
+            .points(DataSplit::Testing)
+            .filter(|point| point.1[1] == 1.0)
+            .count();
+        assert_eq!(class_1_testing, 5);
+        ```
+        */
+        let class = |split: DataSplit, i: usize| -> usize {
+            let (_, outputs) = self.point(split, i).unwrap();
+            (0..outputs.len())
+                .max_by(|a, b| outputs[*a].total_cmp(&outputs[*b]))
+                .unwrap_or(0)
+        };
+        let mut classes: Vec<Vec<(DataSplit, usize)>> = vec![vec![]; self.output_dim().max(1)];
+        for (split, i) in self.all_points() {
+            classes[class(split, i)].push((split, i));
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut ds = self.empty_like();
+        for mut points in classes {
+            points.shuffle(&mut rng);
+            let training_count = (points.len() as f64 * training_ratio).round() as usize;
+            for (n, (split, i)) in points.into_iter().enumerate() {
+                let to = if n < training_count {
+                    DataSplit::Training
+                } else {
+                    DataSplit::Testing
+                };
+                self.copy_point(split, i, &mut ds, to);
+            }
+        }
+        ds
+    }
+    pub fn split_validation(&mut self, fraction: f64, seed: u64) {
+        /*!
+        Moves a random fraction of the training points into the validation part of the dataset, leaving the testing points untouched. Validation costs can then be checked with [`Network::validate`] while training, keeping the testing points unseen.
+        ```
+        # use neurnet::*;
+        let mut ds = DataSet::gen_from_fn(
+            |x| x,
+            (0..100).map(|x| vec![x as f64]).collect(),
+            (0..10).map(|x| vec![x as f64]).collect(),
+        );
+        ds.split_validation(0.25, 9);
+        assert_eq!(ds.len(DataSplit::Training), 75);
+        assert_eq!(ds.len(DataSplit::Validation), 25);
+        assert_eq!(ds.len(DataSplit::Testing), 10);
+        ```
+        */
+        let len = self.len(DataSplit::Training);
+        let validation_count = (len as f64 * fraction).round() as usize;
+        let mut order: Vec<usize> = (0..len).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(seed));
+        let mut is_validation = vec![false; len];
+        for i in order.into_iter().take(validation_count) {
+            is_validation[i] = true;
+        }
+
+        let mut ds = self.empty_like();
+        for (i, to_validation) in is_validation.into_iter().enumerate() {
+            let to = if to_validation {
+                DataSplit::Validation
+            } else {
+                DataSplit::Training
+            };
+            self.copy_point(DataSplit::Training, i, &mut ds, to);
+        }
+        for split in [DataSplit::Testing, DataSplit::Validation] {
+            for i in 0..self.len(split) {
+                self.copy_point(split, i, &mut ds, split);
+            }
+        }
+        *self = ds;
+    }
+    pub fn k_fold(&self, k: usize) -> KFolds<'_> {
+        /*!
+        Iterates over the k folds of the training points for cross-validation. Each fold is a dataset whose validation points are one k-th of the training points and whose training points are the rest, with this dataset's testing points carried over unchanged. The folds are taken in order, so shuffle the dataset first if its points are sorted.
+        ```
+        # use neurnet::*;
+        let ds = DataSet::gen_from_fn(|x| x, (0..10).map(|x| vec![x as f64]).collect(), vec![]);
+        let folds: Vec<DataSet> = ds.k_fold(5).collect();
+        assert_eq!(folds.len(), 5);
+        assert_eq!(folds[1].inputs(DataSplit::Validation), &[2.0, 3.0]);
+        assert_eq!(folds[1].len(DataSplit::Training), 8);
+        ```
+        # Panics
+        <ul>
+        <li> If k is less than 2 or more than the number of training points.
+        </ul>
+        */
+        assert!(
+            k >= 2 && k <= self.len(DataSplit::Training),
+            "k must be between 2 and the number of training points"
+        );
+        KFolds {
+            ds: self,
+            k,
+            next: 0,
+        }
+    }
+    pub fn cross_validate<F: FnMut(&DataSet) -> Network>(
+        &self,
+        k: usize,
+        mut train: F,
+    ) -> Vec<f64> {
+        /*!
+        Runs k-fold cross-validation: the closure trains a network on each fold from [`DataSet::k_fold`], and the network's costs on that fold's validation points are averaged across every fold.
+        ```
+        # use neurnet::*;
+        let mut ds = DataSet::gen_from_fn(
+            |x| vec![0.5 * x[0]],
+            (-20..20).map(|x| vec![x as f64 / 20.0]).collect(),
+            vec![],
+        );
+        ds.shuffle(0);
+        let costs = ds.cross_validate(4, |fold| {
+            let mut nn = Network::new(vec![1, 1], |x| x, (-1.0, 1.0), (-1.0, 1.0));
+            nn.train_loop(fold, 0.1, 1.0, 50, None);
+            nn
+        });
+        assert_eq!(costs.len(), 1);
+        assert!(costs[0].is_finite());
+        ```
+        # Panics
+        <ul>
+        <li> If k is less than 2 or more than the number of training points.
+        </ul>
+        */
+        let mut cost_totals: Vec<f64> = vec![];
+        for fold in self.k_fold(k) {
+            let costs = train(&fold).validate(&fold);
+            if cost_totals.is_empty() {
+                cost_totals = vec![0.0; costs.len()];
+            }
+            for (total, cost) in cost_totals.iter_mut().zip(costs) {
+                *total += cost;
+            }
+        }
+        for cost in cost_totals.iter_mut() {
+            *cost /= k as f64;
+        }
+        cost_totals
+    }
+}
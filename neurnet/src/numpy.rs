@@ -0,0 +1,276 @@
+//! Reading and writing NumPy's `.npy` arrays and (uncompressed) `.npz` archives.
+use std::io::{Read, Write};
+
+/// An n-dimensional array as (shape, values in row-major order).
+pub type NpyArray = (Vec<usize>, Vec<f64>);
+
+pub fn read_npy<R: Read>(mut reader: R) -> Option<NpyArray> {
+    let mut bytes: Vec<u8> = vec![];
+    reader.read_to_end(&mut bytes).ok()?;
+    parse_npy(&bytes)
+}
+
+fn parse_npy(bytes: &[u8]) -> Option<NpyArray> {
+    if bytes.len() < 10 || &bytes[0..6] != b"\x93NUMPY" {
+        return None;
+    }
+    let (header_len, header_start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 => {
+            let len = u32::from_le_bytes(bytes.get(8..12)?.try_into().ok()?);
+            (len as usize, 12)
+        }
+        _ => return None,
+    };
+    let header = std::str::from_utf8(bytes.get(header_start..header_start + header_len)?).ok()?;
+    let data = &bytes[header_start + header_len..];
+
+    fn dict_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+        let start = header.find(&format!("'{}'", key))? + key.len() + 2;
+        let rest = header[start..].trim_start().strip_prefix(':')?.trim_start();
+        let end = if rest.starts_with('(') {
+            rest.find(')')? + 1
+        } else if let Some(quoted) = rest.strip_prefix('\'') {
+            quoted.find('\'')? + 2
+        } else {
+            rest.find([',', '}'])?
+        };
+        Some(rest[..end].trim())
+    }
+    let descr = dict_value(header, "descr")?.trim_matches('\'');
+    let fortran_order = dict_value(header, "fortran_order")? == "True";
+    let shape: Vec<usize> = {
+        let inner = dict_value(header, "shape")?
+            .trim_start_matches('(')
+            .trim_end_matches(')');
+        inner
+            .split(',')
+            .map(|dim| dim.trim())
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| dim.parse::<usize>().ok())
+            .collect::<Option<Vec<usize>>>()?
+    };
+
+    let mut descr_chars = descr.chars();
+    let big_endian = match descr_chars.next()? {
+        '<' | '|' | '=' => false,
+        '>' => true,
+        _ => return None,
+    };
+    let kind = descr_chars.next()?;
+    let size: usize = descr_chars.as_str().parse().ok()?;
+    if size == 0 || size > 8 {
+        return None;
+    }
+    let count: usize = shape.iter().product();
+    if data.len() < count.checked_mul(size)? {
+        return None;
+    }
+    let mut values: Vec<f64> = vec![];
+    for chunk in data.chunks_exact(size).take(count) {
+        let mut buf = [0u8; 8];
+        if big_endian {
+            buf[8 - size..].copy_from_slice(chunk);
+            buf.reverse();
+        } else {
+            buf[..size].copy_from_slice(chunk);
+        }
+        values.push(match (kind, size) {
+            ('f', 8) => f64::from_le_bytes(buf),
+            ('f', 4) => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ('i', 1) => buf[0] as i8 as f64,
+            ('i', 2) => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ('i', 4) => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ('i', 8) => i64::from_le_bytes(buf) as f64,
+            ('u', 1) | ('b', 1) => buf[0] as f64,
+            ('u', 2) => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ('u', 4) => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ('u', 8) => u64::from_le_bytes(buf) as f64,
+            _ => return None,
+        });
+    }
+    if fortran_order && shape.len() > 1 {
+        //Reorder from column-major to row-major
+        let mut row_major = vec![0.0; count];
+        let mut strides = vec![0; shape.len()];
+        let mut stride = 1;
+        for (dim_i, dim) in shape.iter().enumerate().rev() {
+            strides[dim_i] = stride;
+            stride *= dim;
+        }
+        for (column_major_i, value) in values.iter().enumerate() {
+            let mut rest = column_major_i;
+            let mut row_major_i = 0;
+            for (dim_i, dim) in shape.iter().enumerate() {
+                row_major_i += (rest % dim) * strides[dim_i];
+                rest /= dim;
+            }
+            row_major[row_major_i] = *value;
+        }
+        values = row_major;
+    }
+    Some((shape, values))
+}
+
+pub fn write_npy<W: Write>(mut writer: W, array: &NpyArray) -> Option<()> {
+    writer.write_all(&npy_bytes(array)).ok()?;
+    writer.flush().ok()
+}
+
+fn npy_bytes(array: &NpyArray) -> Vec<u8> {
+    let (shape, values) = array;
+    let shape_string = match shape.len() {
+        1 => format!("({},)", shape[0]),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|dim| dim.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+        shape_string
+    );
+    //The header is padded so the data starts on a 64 byte boundary
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut buf: Vec<u8> = b"\x93NUMPY\x01\x00".to_vec();
+    buf.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    buf.extend_from_slice(header.as_bytes());
+    for value in values {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+    buf
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFFFFFF;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Reads every array in an `.npz` archive, keyed by name without the `.npy` extension. Only archives written without compression (`numpy.savez`, not `numpy.savez_compressed`) are supported.
+pub fn read_npz<R: Read>(mut reader: R) -> Option<Vec<(String, NpyArray)>> {
+    let mut bytes: Vec<u8> = vec![];
+    reader.read_to_end(&mut bytes).ok()?;
+    let u16_at = |i: usize| -> Option<usize> {
+        Some(u16::from_le_bytes(bytes.get(i..i + 2)?.try_into().ok()?) as usize)
+    };
+    let u32_at = |i: usize| -> Option<usize> {
+        Some(u32::from_le_bytes(bytes.get(i..i + 4)?.try_into().ok()?) as usize)
+    };
+    let u64_at = |i: usize| -> Option<usize> {
+        Some(u64::from_le_bytes(bytes.get(i..i + 8)?.try_into().ok()?) as usize)
+    };
+
+    let end_record = (0..bytes.len().checked_sub(21)?)
+        .rev()
+        .find(|i| bytes[*i..*i + 4] == [0x50, 0x4b, 0x05, 0x06])?;
+    let entry_count = u16_at(end_record + 10)?;
+    let mut entry = u32_at(end_record + 16)?;
+
+    let mut arrays: Vec<(String, NpyArray)> = vec![];
+    for _ in 0..entry_count {
+        if u32_at(entry)? != 0x02014b50 || u16_at(entry + 10)? != 0 {
+            //Not a central directory entry, or a compressed one
+            return None;
+        }
+        let mut size = u32_at(entry + 24)?;
+        let name_len = u16_at(entry + 28)?;
+        let extra_len = u16_at(entry + 30)?;
+        let comment_len = u16_at(entry + 32)?;
+        let mut offset = u32_at(entry + 42)?;
+        let name =
+            String::from_utf8(bytes.get(entry + 46..entry + 46 + name_len)?.to_vec()).ok()?;
+
+        //Sizes and offsets too big for 32 bits (or forced to zip64, as numpy does) are stored in the zip64 extra field
+        let mut extra = entry + 46 + name_len;
+        let extra_end = extra + extra_len;
+        while extra + 4 <= extra_end {
+            let (id, len) = (u16_at(extra)?, u16_at(extra + 2)?);
+            if id == 0x0001 {
+                let mut field = extra + 4;
+                if size == 0xFFFFFFFF {
+                    size = u64_at(field)?;
+                    field += 8;
+                }
+                if u32_at(entry + 20)? == 0xFFFFFFFF {
+                    field += 8;
+                }
+                if offset == 0xFFFFFFFF {
+                    offset = u64_at(field)?;
+                }
+            }
+            extra += 4 + len;
+        }
+
+        let data_start = offset + 30 + u16_at(offset + 26)? + u16_at(offset + 28)?;
+        let array = parse_npy(bytes.get(data_start..data_start + size)?)?;
+        arrays.push((name.trim_end_matches(".npy").to_string(), array));
+        entry = extra_end + comment_len;
+    }
+    Some(arrays)
+}
+
+/// Writes the arrays as an uncompressed `.npz` archive, the same layout as `numpy.savez`.
+pub fn write_npz<W: Write>(mut writer: W, arrays: &[(String, NpyArray)]) -> Option<()> {
+    let mut buf: Vec<u8> = vec![];
+    let mut central: Vec<u8> = vec![];
+    for (name, array) in arrays {
+        let name = format!("{}.npy", name);
+        let data = npy_bytes(array);
+        let crc = crc32(&data);
+        let offset = buf.len() as u32;
+
+        let mut common: Vec<u8> = vec![];
+        common.extend_from_slice(&20u16.to_le_bytes()); //Version needed to extract
+        common.extend_from_slice(&0u16.to_le_bytes()); //Flags
+        common.extend_from_slice(&0u16.to_le_bytes()); //Stored, no compression
+        common.extend_from_slice(&0u16.to_le_bytes()); //Modification time
+        common.extend_from_slice(&0x21u16.to_le_bytes()); //Modification date, 1980-01-01
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); //Extra field length
+
+        buf.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        buf.extend_from_slice(&common);
+        buf.extend_from_slice(name.as_bytes());
+        buf.extend_from_slice(&data);
+
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); //Version made by
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&[0; 10]); //Comment length, disk, attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = buf.len() as u32;
+    buf.extend_from_slice(&central);
+    buf.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    buf.extend_from_slice(&[0; 4]); //Disk numbers
+    buf.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&central_offset.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes()); //Comment length
+
+    writer.write_all(&buf).ok()?;
+    writer.flush().ok()
+}
@@ -0,0 +1,479 @@
+//! A minimal protobuf encoder/decoder covering the parts of the ONNX format needed to store dense networks as Gemm + activation nodes.
+use std::io::{Read, Write};
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+/// One dense layer: (weights as [neuron][prev neuron], biases, ONNX activation op with its optional alpha attribute).
+pub type OnnxLayer = (Vec<Vec<f64>>, Vec<f64>, String, Option<f32>);
+/// A scaler's (center, scale), stored as two elementwise nodes.
+pub type OnnxScaler = (Vec<f64>, Vec<f64>);
+/// A whole model: (input scaler, layers, output scaler).
+pub type OnnxModel = (Option<OnnxScaler>, Vec<OnnxLayer>, Option<OnnxScaler>);
+
+const IR_VERSION: u64 = 7;
+const OPSET_VERSION: u64 = 13;
+const TENSOR_FLOAT: u64 = 1;
+const TENSOR_DOUBLE: u64 = 11;
+const ATTRIBUTE_FLOAT: u64 = 1;
+const ATTRIBUTE_INT: u64 = 2;
+
+#[derive(Default)]
+struct Message {
+    buf: Vec<u8>,
+}
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.buf.push(byte);
+                break;
+            }
+            self.buf.push(byte | 0x80);
+        }
+    }
+    fn key(&mut self, field: u64, wire_type: u64) {
+        self.varint(field << 3 | wire_type);
+    }
+    fn int(&mut self, field: u64, value: u64) -> &mut Message {
+        self.key(field, 0);
+        self.varint(value);
+        self
+    }
+    fn float(&mut self, field: u64, value: f32) -> &mut Message {
+        self.key(field, 5);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+    fn bytes(&mut self, field: u64, value: &[u8]) -> &mut Message {
+        self.key(field, 2);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+        self
+    }
+    fn string(&mut self, field: u64, value: &str) -> &mut Message {
+        self.bytes(field, value.as_bytes())
+    }
+    fn message(&mut self, field: u64, value: Message) -> &mut Message {
+        self.bytes(field, &value.buf)
+    }
+}
+
+fn tensor(name: &str, dims: &[usize], values: &[f64]) -> Message {
+    let mut tensor = Message::default();
+    for dim in dims {
+        tensor.int(1, *dim as u64);
+    }
+    tensor.int(2, TENSOR_FLOAT);
+    tensor.string(8, name);
+    let raw: Vec<u8> = values
+        .iter()
+        .flat_map(|value| (*value as f32).to_le_bytes())
+        .collect();
+    tensor.bytes(9, &raw);
+    tensor
+}
+
+fn value_info(name: &str, width: usize) -> Message {
+    let mut batch_dim = Message::default();
+    batch_dim.string(2, "N");
+    let mut width_dim = Message::default();
+    width_dim.int(1, width as u64);
+    let mut shape = Message::default();
+    shape.message(1, batch_dim).message(1, width_dim);
+    let mut tensor_type = Message::default();
+    tensor_type.int(1, TENSOR_FLOAT).message(2, shape);
+    let mut type_proto = Message::default();
+    type_proto.message(1, tensor_type);
+    let mut info = Message::default();
+    info.string(1, name).message(2, type_proto);
+    info
+}
+
+fn node(op_type: &str, inputs: &[&str], output: &str, attributes: Vec<Message>) -> Message {
+    let mut node = Message::default();
+    for input in inputs {
+        node.string(1, input);
+    }
+    node.string(2, output);
+    node.string(3, output);
+    node.string(4, op_type);
+    for attribute in attributes {
+        node.message(5, attribute);
+    }
+    node
+}
+
+fn scaler_nodes(
+    graph: &mut Message,
+    name: &str,
+    (center, scale): &OnnxScaler,
+    input: &str,
+    output: &str,
+    inverse: bool,
+) {
+    //An input scaler maps x to (x - center) / scale, and an output scaler maps it back with x * scale + center
+    let (center_name, scale_name) = (format!("{}.center", name), format!("{}.scale", name));
+    graph.message(5, tensor(&center_name, &[center.len()], center));
+    graph.message(5, tensor(&scale_name, &[scale.len()], scale));
+    let middle = format!("{}.middle", name);
+    let nodes = match inverse {
+        false => [("Sub", input, center_name), ("Div", &middle, scale_name)],
+        true => [("Mul", input, scale_name), ("Add", &middle, center_name)],
+    };
+    for ((op_type, input, tensor), output) in nodes.iter().zip([&middle, output]) {
+        graph.message(1, node(op_type, &[input, tensor], output, vec![]));
+    }
+}
+
+/// Writes an ONNX model whose input is `input` (N x input_size) and output is `output` (N x last layer size).
+pub fn write_onnx<W: Write>(mut writer: W, input_size: usize, model: &OnnxModel) -> Option<()> {
+    let (input_scaler, layers, output_scaler) = model;
+    let mut graph = Message::default();
+    let mut current = String::from("input");
+    if let Some(scaler) = input_scaler {
+        scaler_nodes(
+            &mut graph,
+            "input_scaler",
+            scaler,
+            &current,
+            "input_scaler.output",
+            false,
+        );
+        current = String::from("input_scaler.output");
+    }
+    for (i, (weights, biases, activation, alpha)) in layers.iter().enumerate() {
+        let prev_len = weights.first().map_or(0, |neuron| neuron.len());
+        let weight_name = format!("layers.{}.weight", i);
+        let bias_name = format!("layers.{}.bias", i);
+        let flat: Vec<f64> = weights.iter().flatten().copied().collect();
+        graph.message(5, tensor(&weight_name, &[weights.len(), prev_len], &flat));
+        graph.message(5, tensor(&bias_name, &[biases.len()], biases));
+
+        let mut trans_b = Message::default();
+        trans_b.string(1, "transB").int(3, 1).int(20, ATTRIBUTE_INT);
+        let gemm_output = format!("layers.{}.gemm", i);
+        graph.message(
+            1,
+            node(
+                "Gemm",
+                &[&current, &weight_name, &bias_name],
+                &gemm_output,
+                vec![trans_b],
+            ),
+        );
+
+        let output = if i == layers.len() - 1 && output_scaler.is_none() {
+            String::from("output")
+        } else {
+            format!("layers.{}.output", i)
+        };
+        let mut attributes = vec![];
+        if let Some(alpha) = alpha {
+            let mut attribute = Message::default();
+            attribute
+                .string(1, "alpha")
+                .float(2, *alpha)
+                .int(20, ATTRIBUTE_FLOAT);
+            attributes.push(attribute);
+        }
+        graph.message(1, node(activation, &[&gemm_output], &output, attributes));
+        current = output;
+    }
+    if let Some(scaler) = output_scaler {
+        scaler_nodes(
+            &mut graph,
+            "output_scaler",
+            scaler,
+            &current,
+            "output",
+            true,
+        );
+        current = String::from("output");
+    }
+    graph.string(2, "neurnet");
+    graph.message(11, value_info("input", input_size));
+    let output_size = layers.last().map_or(input_size, |layer| layer.1.len());
+    graph.message(12, value_info(&current, output_size));
+
+    let mut opset = Message::default();
+    opset.string(1, "").int(2, OPSET_VERSION);
+    let mut model = Message::default();
+    model
+        .int(1, IR_VERSION)
+        .string(2, "neurnet")
+        .string(3, env!("CARGO_PKG_VERSION"))
+        .message(7, graph)
+        .message(8, opset);
+
+    writer.write_all(&model.buf).ok()?;
+    writer.flush().ok()
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Bytes(&'a [u8]),
+    Fixed32([u8; 4]),
+}
+
+/// Splits a protobuf message into its (field number, value) pairs.
+fn fields(mut data: &[u8]) -> Option<Vec<(u64, Field<'_>)>> {
+    fn varint(data: &mut &[u8]) -> Option<u64> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let (byte, rest) = data.split_first()?;
+            *data = rest;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+    let mut buf = vec![];
+    while !data.is_empty() {
+        let key = varint(&mut data)?;
+        let field = match key & 7 {
+            0 => Field::Varint(varint(&mut data)?),
+            1 => {
+                let (bytes, rest) = data.split_at_checked(8)?;
+                data = rest;
+                Field::Fixed64(bytes.try_into().ok()?)
+            }
+            2 => {
+                let len = varint(&mut data)? as usize;
+                let (bytes, rest) = data.split_at_checked(len)?;
+                data = rest;
+                Field::Bytes(bytes)
+            }
+            5 => {
+                let (bytes, rest) = data.split_at_checked(4)?;
+                data = rest;
+                Field::Fixed32(bytes.try_into().ok()?)
+            }
+            _ => return None,
+        };
+        buf.push((key >> 3, field));
+    }
+    Some(buf)
+}
+
+fn string(bytes: &[u8]) -> Option<String> {
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+struct Node {
+    op_type: String,
+    inputs: Vec<String>,
+    output: String,
+    ints: Vec<(String, u64)>,
+    floats: Vec<(String, f32)>,
+}
+
+fn parse_node(data: &[u8]) -> Option<Node> {
+    let mut node = Node {
+        op_type: String::new(),
+        inputs: vec![],
+        output: String::new(),
+        ints: vec![],
+        floats: vec![],
+    };
+    for (field, value) in fields(data)? {
+        match (field, value) {
+            (1, Field::Bytes(bytes)) => node.inputs.push(string(bytes)?),
+            (2, Field::Bytes(bytes)) => node.output = string(bytes)?,
+            (4, Field::Bytes(bytes)) => node.op_type = string(bytes)?,
+            (5, Field::Bytes(bytes)) => {
+                let mut name = String::new();
+                let mut int = None;
+                let mut float = None;
+                for (field, value) in fields(bytes)? {
+                    match (field, value) {
+                        (1, Field::Bytes(bytes)) => name = string(bytes)?,
+                        (2, Field::Fixed32(bytes)) => float = Some(f32::from_le_bytes(bytes)),
+                        (3, Field::Varint(value)) => int = Some(value),
+                        _ => (),
+                    }
+                }
+                if let Some(int) = int {
+                    node.ints.push((name.clone(), int));
+                }
+                if let Some(float) = float {
+                    node.floats.push((name, float));
+                }
+            }
+            _ => (),
+        }
+    }
+    Some(node)
+}
+
+fn parse_tensor(data: &[u8]) -> Option<(String, Vec<usize>, Vec<f64>)> {
+    let mut name = String::new();
+    let mut dims: Vec<usize> = vec![];
+    let mut data_type = 0;
+    let mut raw: &[u8] = &[];
+    let mut values: Vec<f64> = vec![];
+    for (field, value) in fields(data)? {
+        match (field, value) {
+            (1, Field::Varint(dim)) => dims.push(dim as usize),
+            (2, Field::Varint(value)) => data_type = value,
+            (8, Field::Bytes(bytes)) => name = string(bytes)?,
+            (9, Field::Bytes(bytes)) => raw = bytes,
+            //Packed float_data and double_data
+            (4, Field::Bytes(bytes)) => values.extend(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64),
+            ),
+            (4, Field::Fixed32(bytes)) => values.push(f32::from_le_bytes(bytes) as f64),
+            (10, Field::Bytes(bytes)) => values.extend(
+                bytes
+                    .chunks_exact(8)
+                    .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])),
+            ),
+            (10, Field::Fixed64(bytes)) => values.push(f64::from_le_bytes(bytes)),
+            _ => (),
+        }
+    }
+    if !raw.is_empty() {
+        values = match data_type {
+            TENSOR_FLOAT => raw
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                .collect(),
+            TENSOR_DOUBLE => raw
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+                .collect(),
+            _ => return None,
+        };
+    }
+    if values.len() != dims.iter().product::<usize>() {
+        return None;
+    }
+    Some((name, dims, values))
+}
+
+fn read_scaler(
+    nodes: &mut Peekable<IntoIter<Node>>,
+    tensors: &[(String, Vec<usize>, Vec<f64>)],
+    ops: [&str; 2],
+    current: &mut Option<String>,
+) -> Option<Option<OnnxScaler>> {
+    //A scaler is a pair of elementwise nodes, each taking the previous node's output and a constant tensor
+    if nodes.peek().map(|node| node.op_type.as_str()) != Some(ops[0]) {
+        return Some(None);
+    }
+    let mut values = vec![];
+    for op in ops {
+        let node = nodes.next()?;
+        if node.op_type != op
+            || node.inputs.len() != 2
+            || current
+                .as_ref()
+                .is_some_and(|current| *current != node.inputs[0])
+        {
+            return None;
+        }
+        let (_, _, tensor) = tensors.iter().find(|tensor| tensor.0 == node.inputs[1])?;
+        values.push(tensor.clone());
+        *current = Some(node.output);
+    }
+    let (second, first) = (values.pop()?, values.pop()?);
+    //Input scalers subtract the center first, output scalers multiply by the scale first
+    Some(Some(match ops[0] {
+        "Sub" => (first, second),
+        _ => (second, first),
+    }))
+}
+
+/// Reads an ONNX model made of Gemm nodes, each optionally followed by one activation node, back into dense layers, along with the scalers that may come before and after them.
+pub fn read_onnx<R: Read>(mut reader: R) -> Option<OnnxModel> {
+    let mut bytes: Vec<u8> = vec![];
+    reader.read_to_end(&mut bytes).ok()?;
+    let graph = fields(&bytes)?
+        .into_iter()
+        .find_map(|(field, value)| match (field, value) {
+            (7, Field::Bytes(bytes)) => Some(bytes),
+            _ => None,
+        })?;
+
+    let mut nodes: Vec<Node> = vec![];
+    let mut tensors: Vec<(String, Vec<usize>, Vec<f64>)> = vec![];
+    for (field, value) in fields(graph)? {
+        match (field, value) {
+            (1, Field::Bytes(bytes)) => nodes.push(parse_node(bytes)?),
+            (5, Field::Bytes(bytes)) => tensors.push(parse_tensor(bytes)?),
+            _ => (),
+        }
+    }
+    let find_tensor = |name: &String| tensors.iter().find(|tensor| tensor.0 == *name);
+
+    let mut nodes = nodes.into_iter().peekable();
+    let mut current: Option<String> = None;
+    let input_scaler = read_scaler(&mut nodes, &tensors, ["Sub", "Div"], &mut current)?;
+
+    let mut layers: Vec<OnnxLayer> = vec![];
+    while let Some(gemm) = nodes.next_if(|node| node.op_type != "Mul") {
+        if gemm.op_type != "Gemm" || gemm.inputs.len() != 3 {
+            return None;
+        }
+        if current.is_some() && current.as_ref() != gemm.inputs.first() {
+            //The network must be a single chain of layers
+            return None;
+        }
+        let int_attribute = |name: &str| gemm.ints.iter().find(|a| a.0 == name).map(|a| a.1);
+        let float_attribute = |name: &str| gemm.floats.iter().find(|a| a.0 == name).map(|a| a.1);
+        if int_attribute("transA").unwrap_or(0) != 0
+            || float_attribute("alpha").unwrap_or(1.0) != 1.0
+            || float_attribute("beta").unwrap_or(1.0) != 1.0
+        {
+            return None;
+        }
+        let (_, weight_dims, weight_values) = find_tensor(&gemm.inputs[1])?;
+        let (_, _, biases) = find_tensor(&gemm.inputs[2])?;
+        if weight_dims.len() != 2 {
+            return None;
+        }
+        let weights: Vec<Vec<f64>> = if int_attribute("transB").unwrap_or(0) == 1 {
+            weight_values
+                .chunks(weight_dims[1].max(1))
+                .map(|neuron| neuron.to_vec())
+                .collect()
+        } else {
+            //Stored as [prev neuron][neuron]
+            (0..weight_dims[1])
+                .map(|neuron| {
+                    (0..weight_dims[0])
+                        .map(|prev| weight_values[prev * weight_dims[1] + neuron])
+                        .collect()
+                })
+                .collect()
+        };
+        if biases.len() != weights.len() {
+            return None;
+        }
+
+        let mut activation = (String::from("Identity"), None);
+        current = Some(gemm.output.clone());
+        if let Some(next) = nodes.peek() {
+            if !matches!(next.op_type.as_str(), "Gemm" | "Mul")
+                && next.inputs.first() == Some(&gemm.output)
+            {
+                let next = nodes.next()?;
+                let alpha = next.floats.iter().find(|a| a.0 == "alpha").map(|a| a.1);
+                activation = (next.op_type, alpha);
+                current = Some(next.output);
+            }
+        }
+        layers.push((weights, biases.clone(), activation.0, activation.1));
+    }
+    let output_scaler = read_scaler(&mut nodes, &tensors, ["Mul", "Add"], &mut current)?;
+    if nodes.next().is_some() {
+        return None;
+    }
+    Some((input_scaler, layers, output_scaler))
+}
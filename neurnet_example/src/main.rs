@@ -35,5 +35,5 @@ fn main() {
         testing_inputs,
     );
     ds.save("idk.dset").unwrap();
-    let ds2 = DataSet::load("idk.dset");
+    let _ds2 = DataSet::load("idk.dset");
 }